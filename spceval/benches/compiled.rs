@@ -0,0 +1,44 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use spceval::{EvalContext, IntType, Number};
+
+// Sweeps an address formula over a range of page numbers, re-parsing the expression for every
+// page versus parsing it once.
+const EXPR: &str = "(base + page * PAGE_SIZE + offset) & ~(PAGE_SIZE - 1) | bit(63)";
+const PAGES: u64 = 1000;
+
+fn new_eval_ctx() -> EvalContext {
+    let mut eval_ctx = EvalContext::new();
+    eval_ctx.set_var("base", Number::from_int(0xfee0_0000, IntType::U64));
+    eval_ctx.set_var("offset", Number::from_int(0x123, IntType::U64));
+    eval_ctx
+}
+
+fn sweep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sweep");
+
+    group.bench_function("evaluate", |b| {
+        let mut eval_ctx = new_eval_ctx();
+        b.iter(|| {
+            for page in 0..PAGES {
+                eval_ctx.set_var("page", Number::from_int(page as u128, IntType::U64));
+                black_box(spceval::evaluate_with(&mut eval_ctx, black_box(EXPR)).unwrap());
+            }
+        })
+    });
+
+    group.bench_function("compiled", |b| {
+        let mut eval_ctx = new_eval_ctx();
+        let compiled = spceval::compile(EXPR).unwrap();
+        b.iter(|| {
+            for page in 0..PAGES {
+                eval_ctx.set_var("page", Number::from_int(page as u128, IntType::U64));
+                black_box(compiled.evaluate(&mut eval_ctx).unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, sweep);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+use crate::Number;
+use std::fmt;
+
+// Byte range of an expression in the string it was parsed from (end is exclusive).
+// Parentheses aren't part of the tree, so the span of a parenthesized expression excludes them.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub const fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    // The smallest span covering both spans.
+    pub fn to(self, other: Span) -> Self {
+        Self { start: self.start.min(other.start), end: self.end.max(other.end) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    // Number literal or constant, 'text' is as written (without whitespace), e.g. "0xff" or "pi".
+    Num { number: Number, text: String },
+    Var(String),
+    // Prefix operator or cast, e.g. "-x" or "(u8)x". Casts like "x as u8" use the prefix name.
+    Unary { oper: &'static str, operand: Box<Expr> },
+    Binary { oper: &'static str, lhs: Box<Expr>, rhs: Box<Expr> },
+    Ternary { cond: Box<Expr>, if_true: Box<Expr>, if_false: Box<Expr> },
+    Call { func: String, args: Vec<Expr> },
+    // Assignment to a variable, 'oper' is the operator of a compound assignment (e.g. "+" for "+=").
+    Assign { var: String, oper: Option<&'static str>, value: Box<Expr> },
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+// Formats the expression fully parenthesized with normalized whitespace, e.g. "1+2*x" is
+// "(1 + (2 * x))". The result parses to the same expression.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ExprKind::Num { text, .. } => write!(f, "{}", text),
+            ExprKind::Var(name) => write!(f, "{}", name),
+            ExprKind::Unary { oper, operand } => write!(f, "({}{})", oper, operand),
+            ExprKind::Binary { oper, lhs, rhs } => write!(f, "({} {} {})", lhs, oper, rhs),
+            ExprKind::Ternary { cond, if_true, if_false } => write!(f, "({} ? {} : {})", cond, if_true, if_false),
+            ExprKind::Call { func, args } => {
+                write!(f, "{}(", func)?;
+                for (idx, arg) in args.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::Assign { var, oper, value } => write!(f, "{} {}= {}", var, oper.unwrap_or(""), value),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{IntType, Number};
+
+pub const KIB: u128 = 0x400;
+pub const MIB: u128 = 0x100000;
+pub const GIB: u128 = 0x40000000;
+pub const TIB: u128 = 0x10000000000;
+pub const PIB: u128 = 0x4000000000000;
+pub const PAGE_SIZE: u128 = 4 * KIB;
+
+#[rustfmt::skip]
+pub static CONSTS: [Const<'static>; 30] = [
+    // Mathematical
+    Const { name: "e",               value: Number::from_f64(std::f64::consts::E),                   help: "Euler's number",                     },
+    Const { name: "pi",              value: Number::from_f64(std::f64::consts::PI),                  help: "Ratio of circumference to diameter", },
+    // Sizes
+    Const { name: "KiB",             value: Number::from_int(KIB, IntType::I64),                     help: "Kibibyte (2^10 bytes)",              },
+    Const { name: "MiB",             value: Number::from_int(MIB, IntType::I64),                     help: "Mebibyte (2^20 bytes)",              },
+    Const { name: "GiB",             value: Number::from_int(GIB, IntType::I64),                     help: "Gibibyte (2^30 bytes)",              },
+    Const { name: "TiB",             value: Number::from_int(TIB, IntType::I64),                     help: "Tebibyte (2^40 bytes)",              },
+    Const { name: "PiB",             value: Number::from_int(PIB, IntType::I64),                     help: "Pebibyte (2^50 bytes)",              },
+    Const { name: "PAGE_SIZE",       value: Number::from_int(PAGE_SIZE, IntType::I64),               help: "Page size (4 KiB)",                  },
+    Const { name: "LARGE_PAGE_SIZE", value: Number::from_int(2 * MIB, IntType::I64),                 help: "Large page size (2 MiB)",            },
+    Const { name: "HUGE_PAGE_SIZE",  value: Number::from_int(GIB, IntType::I64),                     help: "Huge page size (1 GiB)",             },
+    // Integer limits
+    Const { name: "U8_MAX",          value: Number::from_int(u8::MAX as u128, IntType::I64),         help: "Maximum unsigned 8-bit integer",     },
+    Const { name: "U16_MAX",         value: Number::from_int(u16::MAX as u128, IntType::I64),        help: "Maximum unsigned 16-bit integer",    },
+    Const { name: "U32_MAX",         value: Number::from_int(u32::MAX as u128, IntType::I64),        help: "Maximum unsigned 32-bit integer",    },
+    Const { name: "U64_MAX",         value: Number::from_int(u64::MAX as u128, IntType::U64),        help: "Maximum unsigned 64-bit integer",    },
+    Const { name: "U128_MAX",        value: Number::from_int(u128::MAX, IntType::U128),              help: "Maximum unsigned 128-bit integer",   },
+    Const { name: "I8_MIN",          value: Number::from_int(i8::MIN as u64 as u128, IntType::I64),  help: "Minimum signed 8-bit integer",       },
+    Const { name: "I8_MAX",          value: Number::from_int(i8::MAX as u128, IntType::I64),         help: "Maximum signed 8-bit integer",       },
+    Const { name: "I16_MIN",         value: Number::from_int(i16::MIN as u64 as u128, IntType::I64), help: "Minimum signed 16-bit integer",      },
+    Const { name: "I16_MAX",         value: Number::from_int(i16::MAX as u128, IntType::I64),        help: "Maximum signed 16-bit integer",      },
+    Const { name: "I32_MIN",         value: Number::from_int(i32::MIN as u64 as u128, IntType::I64), help: "Minimum signed 32-bit integer",      },
+    Const { name: "I32_MAX",         value: Number::from_int(i32::MAX as u128, IntType::I64),        help: "Maximum signed 32-bit integer",      },
+    Const { name: "I64_MIN",         value: Number::from_int(i64::MIN as u64 as u128, IntType::I64), help: "Minimum signed 64-bit integer",      },
+    Const { name: "I64_MAX",         value: Number::from_int(i64::MAX as u128, IntType::I64),        help: "Maximum signed 64-bit integer",      },
+    Const { name: "I128_MIN",        value: Number::from_int(i128::MIN as u128, IntType::I128),      help: "Minimum signed 128-bit integer",     },
+    Const { name: "I128_MAX",        value: Number::from_int(i128::MAX as u128, IntType::I128),      help: "Maximum signed 128-bit integer",     },
+    // Bit widths
+    Const { name: "BYTE_BITS",       value: Number::from_int(u8::BITS as u128, IntType::I64),        help: "Bits in a byte",                     },
+    Const { name: "WORD_BITS",       value: Number::from_int(u16::BITS as u128, IntType::I64),       help: "Bits in a word",                     },
+    Const { name: "DWORD_BITS",      value: Number::from_int(u32::BITS as u128, IntType::I64),       help: "Bits in a double word",              },
+    Const { name: "QWORD_BITS",      value: Number::from_int(u64::BITS as u128, IntType::I64),       help: "Bits in a quad word",                },
+    Const { name: "OWORD_BITS",      value: Number::from_int(u128::BITS as u128, IntType::I64),      help: "Bits in an octa word",               },
+];
+
+pub struct Const<'a> {
+    pub name: &'a str,
+    pub value: Number,
+    pub help: &'a str,
+}
+
+// The built-in constants as (name, value, help), e.g. ("PAGE_SIZE", 4096, "Page size (4 KiB)").
+pub fn constants() -> impl Iterator<Item = (&'static str, &'static Number, &'static str)> {
+    CONSTS.iter().map(|constant| (constant.name, &constant.value, constant.help))
+}
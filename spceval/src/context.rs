@@ -0,0 +1,104 @@
+use crate::evaluator::UserFunc;
+use crate::{FuncRegistry, IntType, Number};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IntMode {
+    // Integers have a fixed width and wrap on overflow.
+    #[default]
+    Fixed,
+    // Integers grow as needed so intermediate results never overflow. Integers that are explicitly
+    // cast to a type (e.g. "(u32)x") still wrap at that type's width.
+    Arbitrary,
+    // All integers have the given type and wrap at its width, like the word size of a hardware
+    // programmer's calculator.
+    Word(IntType),
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Overflow {
+    // Integers silently wrap around at the width of their type.
+    #[default]
+    Wrapping,
+    // Overflowing operations fail to evaluate.
+    Checked,
+    // Overflowing operations produce the minimum or maximum value of their type.
+    Saturating,
+}
+
+// Evaluation context that persists across evaluations (e.g., for the duration of an
+// interactive session). Variables assigned and functions defined (see define()) in one expression
+// can be used in later ones.
+#[derive(Default, Debug, Clone)]
+pub struct EvalContext {
+    vars: HashMap<String, Number>,
+    funcs: HashMap<String, Arc<UserFunc>>,
+    registry: Arc<FuncRegistry>,
+    int_mode: IntMode,
+    overflow: Overflow,
+}
+
+impl EvalContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn var(&self, name: &str) -> Option<Number> {
+        self.vars.get(name).cloned()
+    }
+
+    pub fn set_var(&mut self, name: &str, number: Number) {
+        self.vars.insert(name.to_string(), number);
+    }
+
+    pub fn remove_var(&mut self, name: &str) -> Option<Number> {
+        self.vars.remove(name)
+    }
+
+    pub fn vars(&self) -> impl Iterator<Item = (&str, &Number)> {
+        self.vars.iter().map(|(name, number)| (name.as_str(), number))
+    }
+
+    pub(crate) fn func(&self, name: &str) -> Option<Arc<UserFunc>> {
+        self.funcs.get(name).cloned()
+    }
+
+    pub(crate) fn set_func(&mut self, user_func: UserFunc) {
+        self.funcs.insert(user_func.name().to_string(), Arc::new(user_func));
+    }
+
+    pub fn remove_func(&mut self, name: &str) -> bool {
+        self.funcs.remove(name).is_some()
+    }
+
+    // Defined functions and their definitions, e.g. ("align", "align(x, a) = (x + a - 1) & ~(a - 1)").
+    pub fn funcs(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.funcs.iter().map(|(name, user_func)| (name.as_str(), user_func.def()))
+    }
+
+    pub fn registry(&self) -> &FuncRegistry {
+        &self.registry
+    }
+
+    // The registry is shared by clones of the context until either of them modifies it.
+    pub fn registry_mut(&mut self) -> &mut FuncRegistry {
+        Arc::make_mut(&mut self.registry)
+    }
+
+    pub fn int_mode(&self) -> IntMode {
+        self.int_mode
+    }
+
+    pub fn set_int_mode(&mut self, int_mode: IntMode) {
+        self.int_mode = int_mode;
+    }
+
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+}
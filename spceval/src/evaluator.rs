@@ -1,11 +1,19 @@
-use crate::functions::{FUNCS, Func};
+use crate::ast::{Expr, ExprKind, Span};
+use crate::constants::{CONSTS, Const, GIB, KIB, MIB, PIB, TIB};
+use crate::context::{EvalContext, IntMode, Overflow};
+use crate::functions::{Func, FuncKind, FuncRegistry, Function, MAX_FN_PARAMS};
+use crate::number::{IntType, Number};
 use crate::operators::{OPERS, Oper, OperAssoc, OperKind};
 
+use num_bigint::Sign;
+
 use arrayvec::ArrayString;
 use log::{debug, trace}; // others: {warn,info}
 use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
 
 extern crate static_assertions as sa;
 
@@ -21,15 +29,19 @@ pub enum ExprErrorKind {
     EmptyExpr,
     ExceededMaxSubExpr,
     FailedEvaluation,
+    InvalidAssignment,
+    InvalidDefinition,
     InvalidExpr,
     InvalidParamCount,
     InvalidParamType,
+    InvalidType,
     MismatchParenthesis,
     MissingFunction,
     MissingOperand,
     MissingOperator,
     MissingOperatorOrFunction,
     MissingParenthesis,
+    UndefinedVariable,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -58,14 +70,54 @@ impl ExprError {
 }
 
 pub fn evaluate(str_expr: &str) -> Result<Number, ExprError> {
-    let mut expr_ctx = parse_expr(str_expr)?;
-    evaluate_expr(&mut expr_ctx)
+    evaluate_with(&mut EvalContext::new(), str_expr)
+}
+
+pub fn evaluate_with(eval_ctx: &mut EvalContext, str_expr: &str) -> Result<Number, ExprError> {
+    let expr_ctx = parse_expr(str_expr, eval_ctx)?;
+    evaluate_expr(&expr_ctx, eval_ctx)
+}
+
+// Parses the expression once so it can be evaluated repeatedly, e.g. over a range of values
+// assigned to its variables in the evaluation context. Like parse(), errors that don't depend on
+// values (e.g. missing operands) are reported here rather than when evaluating.
+pub fn compile(str_expr: &str) -> Result<CompiledExpr, ExprError> {
+    compile_with(&EvalContext::new(), str_expr)
 }
 
-#[derive(Default, Copy, Clone, Debug)]
-pub struct Number {
-    pub integer: u64,
-    pub float: f64,
+// Like compile(), but the expression can call functions defined in the evaluation context.
+pub fn compile_with(eval_ctx: &EvalContext, str_expr: &str) -> Result<CompiledExpr, ExprError> {
+    let expr_ctx = parse_expr(str_expr, eval_ctx)?;
+    build_expr_tree(&expr_ctx, str_expr)?;
+    Ok(CompiledExpr { expr_ctx })
+}
+
+// Defines a function that expressions evaluated later in the same context can call, e.g.
+// "align(x, a) = (x + a - 1) & ~(a - 1)". The body can only refer to the function's parameters,
+// constants and functions defined before it. Redefining a function replaces it for expressions
+// parsed from then on.
+pub fn define(eval_ctx: &mut EvalContext, str_def: &str) -> Result<(), ExprError> {
+    let user_func = parse_def(eval_ctx, str_def)?;
+    eval_ctx.set_func(user_func);
+    Ok(())
+}
+
+// Parses the expression without evaluating it.
+pub fn parse(str_expr: &str) -> Result<Expr, ExprError> {
+    let expr_ctx = parse_expr(str_expr, &EvalContext::new())?;
+    build_expr_tree(&expr_ctx, str_expr)
+}
+
+pub struct CompiledExpr {
+    expr_ctx: ExprCtx,
+}
+
+impl CompiledExpr {
+    // Evaluation doesn't consume the parsed expression, variables are looked up in the context
+    // (and assigned to) on every evaluation.
+    pub fn evaluate(&self, eval_ctx: &mut EvalContext) -> Result<Number, ExprError> {
+        evaluate_expr(&self.expr_ctx, eval_ctx)
+    }
 }
 
 impl fmt::Display for ExprError {
@@ -74,24 +126,35 @@ impl fmt::Display for ExprError {
             ExprErrorKind::EmptyExpr => "expression empty",
             ExprErrorKind::ExceededMaxSubExpr => "exceeded maximum sub-expression count",
             ExprErrorKind::FailedEvaluation => "evaluation failed",
+            ExprErrorKind::InvalidAssignment => "invalid assignment",
+            ExprErrorKind::InvalidDefinition => "invalid function definition",
             ExprErrorKind::InvalidExpr => "invalid character",
             ExprErrorKind::InvalidParamCount => "incorrect number of parameters",
             ExprErrorKind::InvalidParamType => "invalid parameter type",
+            ExprErrorKind::InvalidType => "invalid type",
             ExprErrorKind::MismatchParenthesis => "parenthesis mismatch",
             ExprErrorKind::MissingFunction => "function missing",
             ExprErrorKind::MissingOperand => "operand missing",
             ExprErrorKind::MissingOperator => "operator missing",
             ExprErrorKind::MissingOperatorOrFunction => "operator or function missing",
             ExprErrorKind::MissingParenthesis => "parenthesis missing",
+            ExprErrorKind::UndefinedVariable => "undefined variable",
         };
         write!(f, "{} {}", err_kind, self.message)
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 struct NumToken {
     number: Number,
     idx_expr: usize,
+    len: usize,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct VarToken {
+    idx_var: usize,
+    idx_expr: usize,
 }
 
 #[derive(Copy, Clone)]
@@ -110,33 +173,132 @@ impl fmt::Debug for OperToken {
     }
 }
 
-#[derive(Copy, Clone)]
+// 'idx_func' is the index of the function in the functions called by the expression, see ExprCtx::funcs.
+#[derive(Debug, Copy, Clone)]
 struct FuncToken {
     idx_func: usize,
     idx_expr: usize,
     params: u8,
+    idx_close_paren: usize,
 }
 
-impl fmt::Debug for FuncToken {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if let Some(func) = FUNCS.get(self.idx_func) {
-            write!(f, "'{}'", func.name)
-        } else {
-            write!(f, "Invalid Index {}", self.idx_func)
+// A function called by an expression, either built-in, registered by the application or defined by
+// the user. Functions are resolved while parsing, so redefining a function doesn't affect already
+// parsed expressions.
+#[derive(Clone)]
+enum FuncRef {
+    Builtin(&'static Func<'static>),
+    Registered(Arc<dyn Function>),
+    User(Arc<UserFunc>),
+}
+
+impl FuncRef {
+    fn name(&self) -> &str {
+        match self {
+            FuncRef::Builtin(func) => func.name,
+            FuncRef::Registered(func) => func.name(),
+            FuncRef::User(user_func) => &user_func.name,
+        }
+    }
+
+    fn params(&self) -> Range<u8> {
+        match self {
+            FuncRef::Builtin(func) => func.params.clone(),
+            FuncRef::Registered(func) => func.params(),
+            FuncRef::User(user_func) => {
+                let params = user_func.params.len() as u8;
+                Range { start: params, end: params + 1 }
+            }
         }
     }
+
+    fn is_cond(&self) -> bool {
+        matches!(self, FuncRef::Builtin(func) if func.kind == FuncKind::Cond)
+    }
+}
+
+// A function defined by the user, e.g. "align(x, a) = (x + a - 1) & ~(a - 1)". The parameters
+// are the only variables in the body.
+pub(crate) struct UserFunc {
+    name: String,
+    params: Vec<String>,
+    body: ExprCtx,
+    def: String,
 }
 
+impl UserFunc {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn def(&self) -> &str {
+        &self.def
+    }
+
+    fn evaluate(&self, eval_ctx: &EvalContext, idx_expr: usize, params: &[Number]) -> Result<Number, ExprError> {
+        debug_assert_eq!(self.params.len(), params.len());
+        let mut func_ctx = EvalContext::new();
+        func_ctx.set_int_mode(eval_ctx.int_mode());
+        func_ctx.set_overflow(eval_ctx.overflow());
+        for (name, number) in self.params.iter().zip(params) {
+            func_ctx.set_var(name, number.clone());
+        }
+        // Errors are reported at the call since the body isn't part of the expression being evaluated.
+        evaluate_expr(&self.body, &mut func_ctx).map_err(|err| {
+            let message = format!("{} in function '{}' at {}", err.message, self.name, idx_expr);
+            trace!("{:?} {}", err.kind, message);
+            ExprError { idx_expr, kind: err.kind, message }
+        })
+    }
+}
+
+impl fmt::Debug for UserFunc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}'", self.def)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum JumpCond {
+    IfZero,
+    IfNonZero,
+    Always,
+}
+
+// Skips the evaluation of a sub-expression (e.g. right-hand side of "&&") depending on the value
+// at the top of the output stack (or unconditionally, e.g. the "else" expression of "?:"). When
+// the jump is taken, a placeholder is pushed in place of the skipped sub-expression so the
+// operator at 'idx_target' still receives all its parameters.
 #[derive(Debug, Copy, Clone)]
+struct JumpToken {
+    cond: JumpCond,
+    idx_target: usize,
+}
+
+#[derive(Debug, Clone)]
 enum Token {
     Num(NumToken),
+    Var(VarToken),
     Oper(OperToken),
     Func(FuncToken),
+    Jump(JumpToken),
+}
+
+// Assignment of the expression's result to a variable, e.g. "x = 5" or "x += 5".
+// For compound assignments, 'idx_oper' is the binary operator applied to the variable.
+struct Assign {
+    var_token: VarToken,
+    idx_oper: Option<usize>,
+    idx_expr: usize,
 }
 
 struct ExprCtx {
     queue_output: VecDeque<Token>,
     stack_op: Vec<Token>,
+    stack_jump: Vec<usize>,
+    vars: Vec<String>,
+    funcs: Vec<FuncRef>,
+    assign: Option<Assign>,
     sub_exprs: u16,
 }
 
@@ -175,14 +337,106 @@ impl ExprCtx {
         Self {
             queue_output: VecDeque::with_capacity(PRE_ALLOC_TOKENS),
             stack_op: Vec::with_capacity(PRE_ALLOC_TOKENS),
+            stack_jump: Vec::new(),
+            vars: Vec::new(),
+            funcs: Vec::new(),
+            assign: None,
             sub_exprs: 0,
         }
     }
 
-    fn pop_to_output_queue(&mut self) {
+    fn add_var(&mut self, name: &str) -> usize {
+        self.vars.push(name.to_string());
+        self.vars.len() - 1
+    }
+
+    fn add_func(&mut self, func: FuncRef) -> usize {
+        self.funcs.push(func);
+        self.funcs.len() - 1
+    }
+
+    fn push_jump_to_output_queue(&mut self, cond: JumpCond) {
+        // The target isn't known until the operator owning the jump is popped to the output queue.
+        // Record where the jump is so we can patch the target later, see patch_jump().
+        self.stack_jump.push(self.queue_output.len());
+        self.queue_output.push_back(Token::Jump(JumpToken { cond, idx_target: 0 }));
+    }
+
+    fn set_jump_target(&mut self, idx_jump: usize, idx_target: usize) {
+        if let Some(Token::Jump(jump_token)) = self.queue_output.get_mut(idx_jump) {
+            jump_token.idx_target = idx_target;
+        } else {
+            debug_assert!(false, "pending jump at {} is not a jump token", idx_jump);
+        }
+    }
+
+    fn patch_jump(&mut self) -> bool {
+        // Jumps are pending in the same order as the operators owning them are on the op stack,
+        // so the last pending jump always belongs to the operator being popped.
+        let idx_target = self.queue_output.len();
+        let idx_jump = self.stack_jump.pop();
+        debug_assert!(idx_jump.is_some());
+        let idx_jump = idx_jump.unwrap();
+        self.set_jump_target(idx_jump, idx_target);
+
+        // If the jump doesn't skip anything, the operand it skips is missing (e.g. "0 &&").
+        // We must catch this here as the placeholder pushed when jumping would hide it.
+        idx_target > idx_jump + 1
+    }
+
+    fn push_else_jump_to_output_queue(&mut self) -> bool {
+        // The first of two alternatives (e.g. "a" in "c ? a : b") is now complete in the output queue.
+        // Follow it with a jump over the second alternative and make the conditional jump preceding
+        // the first alternative skip to the start of the second one.
+        let idx_jump_cond = self.stack_jump.pop();
+        debug_assert!(idx_jump_cond.is_some());
+        let idx_jump_cond = idx_jump_cond.unwrap();
+        let is_first_valid = self.queue_output.len() > idx_jump_cond + 1;
+        self.push_jump_to_output_queue(JumpCond::Always);
+        let idx_target = self.queue_output.len();
+        self.set_jump_target(idx_jump_cond, idx_target);
+        is_first_valid
+    }
+
+    fn pop_to_output_queue(&mut self) -> Result<(), ExprError> {
         let token = self.stack_op.pop();
         debug_assert!(token.is_some());
-        self.queue_output.push_back(token.unwrap());
+        let token = token.unwrap();
+        let is_jump_valid = match token {
+            Token::Oper(OperToken { idx_oper, idx_expr }) => match OPERS[idx_oper].kind {
+                OperKind::LogicalAnd | OperKind::LogicalOr | OperKind::TernaryElse => self.patch_jump(),
+                OperKind::TernaryCond => {
+                    // A matched '?' is replaced by its ':' on the op stack, so this one is unmatched.
+                    let message = format!("':' for operator '{}' at {}", OPERS[idx_oper].name, idx_expr);
+                    trace!("{:?} {}", ExprErrorKind::MissingOperator, message);
+                    return Err(ExprError { idx_expr, kind: ExprErrorKind::MissingOperator, message });
+                }
+                _ => true,
+            },
+            // The else jump of a conditional function is pushed at its second parameter separator,
+            // see process_param_sep().
+            Token::Func(FuncToken { idx_func, params, .. }) if self.funcs[idx_func].is_cond() && params == 3 => {
+                self.patch_jump()
+            }
+            _ => true,
+        };
+
+        if is_jump_valid {
+            self.queue_output.push_back(token);
+            Ok(())
+        } else {
+            let (idx_expr, message) = match token {
+                Token::Oper(OperToken { idx_oper, idx_expr }) => {
+                    (idx_expr, format!("for operator '{}' at {}", OPERS[idx_oper].name, idx_expr))
+                }
+                Token::Func(FuncToken { idx_func, idx_expr, .. }) => {
+                    (idx_expr, format!("for function '{}' at {}", self.funcs[idx_func].name(), idx_expr))
+                }
+                _ => unreachable!(),
+            };
+            trace!("{:?} {}", ExprErrorKind::InvalidParamCount, message);
+            Err(ExprError { idx_expr, kind: ExprErrorKind::InvalidParamCount, message })
+        }
     }
 
     fn pop_all_to_output_queue(&mut self) -> Result<(), ExprError> {
@@ -194,7 +448,7 @@ impl ExprCtx {
                     trace!("Parenthesis mismatch {}", message);
                     return Err(ExprError { idx_expr: *idx_expr, kind: ExprErrorKind::MismatchParenthesis, message });
                 }
-                _ => self.pop_to_output_queue(),
+                _ => self.pop_to_output_queue()?,
             }
         }
         Ok(())
@@ -213,21 +467,25 @@ impl ExprCtx {
     }
 
     fn push_func_to_op_stack(&mut self, func_token: FuncToken) -> Result<(), ExprError> {
-        let func = &FUNCS[func_token.idx_func];
-        if func.params.contains(&func_token.params) {
+        let func = &self.funcs[func_token.idx_func];
+        let func_params = func.params();
+        if func_params.contains(&func_token.params) {
             self.stack_op.push(Token::Func(func_token));
             Ok(())
         } else {
             // Too many or too few parameters passed to the function, bail.
             let message = format!(
                 "for function '{}'. expects [{}..{}) parameters, got {} instead",
-                func.name, func.params.start, func.params.end, func_token.params
+                func.name(),
+                func_params.start,
+                func_params.end,
+                func_token.params
             );
             Err(ExprError { idx_expr: func_token.idx_expr, kind: ExprErrorKind::InvalidParamCount, message })
         }
     }
 
-    fn collect_params(&mut self, params: usize, stack_output: &mut Vec<Number>) -> Option<Vec<Number>> {
+    fn collect_params<T>(params: usize, stack_output: &mut Vec<T>) -> Option<Vec<T>> {
         if params > 0 {
             let stack_len = stack_output.len();
             if stack_len >= params {
@@ -242,11 +500,20 @@ impl ExprCtx {
         }
     }
 
+    // Unlike built-in functions, other functions can have no parameters, e.g. "answer()".
+    fn collect_args<T>(func: &FuncRef, params: u8, stack_output: &mut Vec<T>) -> Option<Vec<T>> {
+        if params == 0 && func.params().contains(&0) {
+            Some(Vec::new())
+        } else {
+            Self::collect_params(params as usize, stack_output)
+        }
+    }
+
     fn process_open_paren(&mut self, oper_token: OperToken, opt_prev_token: &Option<Token>) -> Result<(), ExprError> {
         // Previous token if any cannot be a close parenthesis or a number.
         // E.g "(5)(2)" or "5(2)".
         let is_prev_token_valid = match opt_prev_token {
-            Some(Token::Num(_)) => false,
+            Some(Token::Num(_)) | Some(Token::Var(_)) => false,
             Some(Token::Oper(OperToken { idx_oper, .. })) => OPERS[*idx_oper].kind != OperKind::CloseParen,
             _ => true,
         };
@@ -277,7 +544,7 @@ impl ExprCtx {
                     break;
                 }
                 // Pop any other tokens to the output queue.
-                _ => self.pop_to_output_queue(),
+                _ => self.pop_to_output_queue()?,
             }
         }
 
@@ -290,6 +557,8 @@ impl ExprCtx {
 
             // Check if a function preceeds the open parenthesis.
             if let Some(mut func_token) = self.pop_func_from_op_stack() {
+                func_token.idx_close_paren = oper_token.idx_expr;
+
                 // If we've already counted parameters (due to parameter separators), we will fix up
                 // the overlapping parameter count here. E.g "avg(5,6,7)" -- the count will be 4
                 // (i.e 2 for each parameter separator) but it should be 3 (N/2+1).
@@ -297,12 +566,17 @@ impl ExprCtx {
                     func_token.params /= 2;
                     func_token.params += 1;
                 } else {
-                    // If the previous token is a number, the function has 1 parameter.
+                    // If the previous token is a number, variable or close parenthesis, the function has 1 parameter.
                     // If the previous token is a unary left associative operator, the function has 1 parameter.
                     // Operator parsing code should've verified the unary operator has a valid parameter.
                     // Any other token implies an invalid sequence and we count it as 0 parameters.
                     func_token.params = match opt_prev_token {
-                        Some(Token::Num(_)) => 1,
+                        Some(Token::Num(_)) | Some(Token::Var(_)) => 1,
+                        Some(Token::Oper(OperToken { idx_oper, .. }))
+                            if OPERS[*idx_oper].kind == OperKind::CloseParen =>
+                        {
+                            1
+                        }
                         Some(Token::Oper(OperToken { idx_oper, .. }))
                             if OPERS[*idx_oper].assoc == OperAssoc::Left && OPERS[*idx_oper].params == 1 =>
                         {
@@ -328,7 +602,7 @@ impl ExprCtx {
         while let Some(ref_token) = self.stack_op.last() {
             match ref_token {
                 Token::Oper(OperToken { idx_oper, .. }) if OPERS[*idx_oper].kind == OperKind::OpenParen => break,
-                _ => self.pop_to_output_queue(),
+                _ => self.pop_to_output_queue()?,
             }
         }
 
@@ -337,7 +611,7 @@ impl ExprCtx {
         if self.stack_op.last().is_some() {
             let paren_token = self.stack_op.pop().unwrap();
             if cfg!(debug_assertions) {
-                let oper_paren = OperToken::try_from(paren_token).unwrap();
+                let oper_paren = OperToken::try_from(paren_token.clone()).unwrap();
                 debug_assert!(OPERS[oper_paren.idx_oper].kind == OperKind::OpenParen);
             }
 
@@ -346,12 +620,38 @@ impl ExprCtx {
             if let Some(mut func_token) = self.pop_func_from_op_stack() {
                 if let Some(params) = func_token.params.checked_add(2) {
                     func_token.params = params;
+                    // Conditional functions only evaluate one of the parameters following the condition.
+                    // Parameters are counted twice per separator here, see process_close_paren().
+                    if self.funcs[func_token.idx_func].is_cond() {
+                        match func_token.params {
+                            2 => self.push_jump_to_output_queue(JumpCond::IfZero),
+                            4 if !self.push_else_jump_to_output_queue() => {
+                                let message = format!(
+                                    "for function '{}' at {}",
+                                    self.funcs[func_token.idx_func].name(),
+                                    func_token.idx_expr
+                                );
+                                trace!("{:?} {}", ExprErrorKind::InvalidParamCount, message);
+                                return Err(ExprError {
+                                    idx_expr: func_token.idx_expr,
+                                    kind: ExprErrorKind::InvalidParamCount,
+                                    message,
+                                });
+                            }
+                            _ => (),
+                        }
+                    }
                     self.stack_op.push(Token::Func(func_token));
                     self.stack_op.push(paren_token);
                     Ok(())
                 } else {
                     // Too many parameters!
-                    let message = format!("for function '{}' at {} params {}", &FUNCS[func_token.idx_func].name, func_token.idx_expr, func_token.params);
+                    let message = format!(
+                        "for function '{}' at {} params {}",
+                        self.funcs[func_token.idx_func].name(),
+                        func_token.idx_expr,
+                        func_token.params
+                    );
                     trace!("{:?} {}", ExprErrorKind::InvalidParamCount, message);
                     Err(ExprError { idx_expr: func_token.idx_expr, kind: ExprErrorKind::InvalidParamCount, message })
                 }
@@ -371,10 +671,10 @@ impl ExprCtx {
 
     fn process_regular_oper(&mut self, oper_token: OperToken, opt_prev_token: &Option<Token>) -> Result<(), ExprError> {
         let oper = &OPERS[oper_token.idx_oper];
-        // Validate left associative operator.
+        // Validate infix (left associative or right associative binary) operator.
         // We could squeeze this into parse_oper() but doing it here gives us better
         // error messages in some cases (see integration test).
-        if oper.assoc == OperAssoc::Left {
+        if !oper.is_prefix() && oper.assoc != OperAssoc::Nil {
             // Assuming we've parsed left-associative operator "<<".
             // Rules for previous token are:
             // 1. It must exist. E.g. "<< 2" is invalid but we've already handled this in parse_oper().
@@ -395,9 +695,29 @@ impl ExprCtx {
                 }
                 _ => (),
             }
+        } else if oper.is_prefix() {
+            // Validate prefix operator.
+            // It must not follow an operand, e.g. "5 ~3" or "(2) (i8)3" are invalid.
+            match opt_prev_token {
+                Some(Token::Num(_)) | Some(Token::Var(_)) => check_prev_token_not_number(opt_prev_token)?,
+                Some(Token::Oper(OperToken { idx_oper, idx_expr }))
+                    if OPERS[*idx_oper].kind == OperKind::CloseParen =>
+                {
+                    let message = format!("following close parenthesis at {}", idx_expr);
+                    trace!("{:?} {}", ExprErrorKind::MissingOperator, message);
+                    return Err(ExprError { idx_expr: *idx_expr, kind: ExprErrorKind::MissingOperator, message });
+                }
+                _ => (),
+            }
         }
 
-        while let Some(ref_token) = self.stack_op.last() {
+        if oper.kind == OperKind::TernaryElse {
+            return self.process_ternary_else(oper_token);
+        }
+
+        // A prefix operator has no left operand to complete, so it never pops pending operators, e.g.
+        // the cast in "(i8)-1" must wait for its operand even though casts bind tighter than "-".
+        while let Some(ref_token) = self.stack_op.last().filter(|_| !oper.is_prefix()) {
             match ref_token {
                 Token::Oper(OperToken { idx_oper, .. }) => {
                     let token_stack_oper = &OPERS[*idx_oper];
@@ -405,25 +725,89 @@ impl ExprCtx {
                     if token_stack_oper.kind == OperKind::OpenParen {
                         break;
                     } else if token_stack_oper.prec < oper.prec
-                        || (oper.assoc == OperAssoc::Left && oper.prec == token_stack_oper.prec)
+                        || (oper.assoc == OperAssoc::Left
+                            && oper.prec == token_stack_oper.prec
+                            && oper.kind != OperKind::TernaryCond)
                     {
                         // Pop operator with higher priority (depending on associativity) to the output queue.
-                        self.pop_to_output_queue();
+                        // The ternary conditional groups from the right, i.e. "a ? b : c ? d : e" is
+                        // "a ? b : (c ? d : e)", so we never pop a pending ternary for another one.
+                        self.pop_to_output_queue()?;
                     } else {
                         break;
                     }
                 }
 
                 // Pop functions (which always take priority over a normal operators) to the output queue.
-                Token::Func(_) => self.pop_to_output_queue(),
+                Token::Func(_) => self.pop_to_output_queue()?,
 
                 _ => break,
             }
         }
+
+        // The left-hand side of a logical operator (or the condition of a ternary) is now complete
+        // in the output queue. Follow it with a jump that short-circuits evaluation of the right-hand side.
+        match oper.kind {
+            OperKind::LogicalAnd | OperKind::TernaryCond => self.push_jump_to_output_queue(JumpCond::IfZero),
+            OperKind::LogicalOr => self.push_jump_to_output_queue(JumpCond::IfNonZero),
+            _ => (),
+        }
         self.stack_op.push(Token::Oper(oper_token));
         Ok(())
     }
 
+    fn process_ternary_else(&mut self, oper_token: OperToken) -> Result<(), ExprError> {
+        // Pop the expression preceding ':' (including any nested ternaries) to the output queue
+        // until we find the matching '?'.
+        let oper = &OPERS[oper_token.idx_oper];
+        while let Some(ref_token) = self.stack_op.last() {
+            match ref_token {
+                Token::Oper(OperToken { idx_oper, .. }) if OPERS[*idx_oper].kind == OperKind::TernaryCond => {
+                    // Replace the '?' with the ':' on the op stack as it's the ':' that gets evaluated.
+                    self.stack_op.pop();
+                    if self.push_else_jump_to_output_queue() {
+                        self.stack_op.push(Token::Oper(oper_token));
+                        return Ok(());
+                    } else {
+                        // Nothing between '?' and ':', e.g. "1 ? () : 2".
+                        let message = format!("for operator '{}' at {}", oper.name, oper_token.idx_expr);
+                        trace!("{:?} {}", ExprErrorKind::InvalidParamCount, message);
+                        return Err(ExprError {
+                            idx_expr: oper_token.idx_expr,
+                            kind: ExprErrorKind::InvalidParamCount,
+                            message,
+                        });
+                    }
+                }
+                Token::Oper(OperToken { idx_oper, .. }) if OPERS[*idx_oper].kind == OperKind::OpenParen => break,
+                _ => self.pop_to_output_queue()?,
+            }
+        }
+
+        // No matching '?', e.g. "1 : 2" or "1 ? (2 : 3)".
+        let message = format!("'?' for operator '{}' at {}", oper.name, oper_token.idx_expr);
+        trace!("{:?} {}", ExprErrorKind::MissingOperator, message);
+        Err(ExprError { idx_expr: oper_token.idx_expr, kind: ExprErrorKind::MissingOperator, message })
+    }
+
+    fn process_cast_as(&mut self, oper_token: OperToken) -> Result<(), ExprError> {
+        // Like Rust, "as" binds tighter than any binary operator but looser than prefix operators,
+        // i.e. "-x as u8" is "(-x) as u8". The operand is already in the output queue, so pop
+        // the pending prefix operators applied to it and then follow it with the cast. A function
+        // on the top of the stack has had its parenthesis closed, so it's also part of the operand.
+        while let Some(token) = self.stack_op.last() {
+            match token {
+                Token::Oper(OperToken { idx_oper, .. }) if OPERS[*idx_oper].is_prefix() => {
+                    self.pop_to_output_queue()?
+                }
+                Token::Func(_) => self.pop_to_output_queue()?,
+                _ => break,
+            }
+        }
+        self.queue_output.push_back(Token::Oper(oper_token));
+        Ok(())
+    }
+
     #[inline]
     fn process_oper(&mut self, oper_token: OperToken, opt_prev_token: &Option<Token>) -> Result<(), ExprError> {
         debug_assert!(oper_token.idx_oper < OPERS.len());
@@ -438,27 +822,184 @@ impl ExprCtx {
     }
 }
 
-fn parse_function(str_expr: &str, funcs: &[Func]) -> Option<usize> {
+fn parse_ident(str_expr: &str) -> Option<&str> {
     debug_assert_eq!(str_expr.trim_start_matches(char::is_whitespace), str_expr);
-    // Todo: Sort and use binary search if function table grows.
-    let mut is_found = false;
-    let mut idx_found = 0;
-    for (idx, func) in funcs.iter().enumerate() {
-        // If this is the first occurrence of this function, record where we found it.
-        // Otherwise, record the currently found function only if its length exceeds that
-        // of a previously found one (e.g., find "bits" and not stop at "bit").
-        if str_expr.starts_with(func.name) && (!is_found || func.name.len() > funcs[idx_found].name.len()) {
-            idx_found = idx;
-            is_found = true;
-        }
+    // Identifiers (function and variable names) must start with a letter or '_' followed
+    // by any number of letters, digits or '_' characters.
+    let is_ident_start = |chr: char| chr.is_ascii_alphabetic() || chr == '_';
+    let is_ident_char = |chr: char| chr.is_ascii_alphanumeric() || chr == '_';
+    if str_expr.starts_with(is_ident_start) {
+        let len_ident = str_expr.find(|chr| !is_ident_char(chr)).unwrap_or(str_expr.len());
+        Some(&str_expr[..len_ident])
+    } else {
+        None
     }
+}
 
-    if is_found {
-        trace!("found {}", &funcs[idx_found].help);
-        Some(idx_found)
+// Whether the whole string is an identifier, e.g. the name of a registered function.
+pub(crate) fn is_ident(str_ident: &str) -> bool {
+    !str_ident.starts_with(char::is_whitespace) && parse_ident(str_ident) == Some(str_ident)
+}
+
+fn parse_function(str_ident: &str, registry: &FuncRegistry) -> Option<FuncRef> {
+    if let Some(func) = registry.registered(str_ident) {
+        trace!("found '{}' - {} ({})", func.name(), func.help(), func.syntax());
+        Some(FuncRef::Registered(func.clone()))
     } else {
-        None
+        let func = registry.builtin(str_ident)?;
+        trace!("found '{}' - {} ({})", func.name, func.help, func.syntax);
+        Some(FuncRef::Builtin(func))
+    }
+}
+
+fn parse_const(str_ident: &str, consts: &[Const]) -> Option<usize> {
+    let idx_found = consts.iter().position(|constant| constant.name == str_ident)?;
+    trace!("found '{}' - {}", consts[idx_found].name, consts[idx_found].help);
+    Some(idx_found)
+}
+
+// Parses an assignment to a variable at the start of the expression, e.g. "x = 5" or "x <<= 2".
+// Returns the length of the assignment, or 0 if the expression isn't assigned to a variable.
+fn parse_assign(str_expr: &str, registry: &FuncRegistry, expr_ctx: &mut ExprCtx) -> Result<usize, ExprError> {
+    // Binary operators that can be combined with '=' for compound assignment.
+    // We can't allow all binary operators as we would end up parsing "x <= 5" as "x < = 5".
+    static COMPOUND_ASSIGN_OPERS: [&str; 11] = ["+", "-", "**", "*", "/", "%", "<<", ">>", "&", "^", "|"];
+
+    let str_ident = str_expr.trim_start();
+    let idx_var = str_expr.len() - str_ident.len();
+    let Some(str_var) = parse_ident(str_ident) else {
+        return Ok(0);
+    };
+
+    let str_oper = str_ident[str_var.len()..].trim_start();
+    let idx_oper = str_expr.len() - str_oper.len();
+    let opt_compound_oper = COMPOUND_ASSIGN_OPERS
+        .iter()
+        .find(|name| str_oper.starts_with(*name) && str_oper[name.len()..].starts_with('='));
+    let len_oper = opt_compound_oper.map_or(0, |name| name.len());
+    if !str_oper[len_oper..].starts_with('=') || str_oper[len_oper + 1..].starts_with('=') {
+        // Not an assignment, e.g. "x + 5" or "x == 5".
+        return Ok(0);
+    }
+
+    if registry.contains(str_var) {
+        let message = format!("to function '{}' at {}", str_var, idx_var);
+        trace!("{:?} {}", ExprErrorKind::InvalidAssignment, message);
+        return Err(ExprError { idx_expr: idx_var, kind: ExprErrorKind::InvalidAssignment, message });
+    }
+
+    if parse_const(str_var, &CONSTS).is_some() {
+        let message = format!("to constant '{}' at {}", str_var, idx_var);
+        trace!("{:?} {}", ExprErrorKind::InvalidAssignment, message);
+        return Err(ExprError { idx_expr: idx_var, kind: ExprErrorKind::InvalidAssignment, message });
     }
+
+    let opt_idx_oper = opt_compound_oper.and_then(|name| {
+        OPERS.iter().position(|oper| oper.name == *name && oper.kind == OperKind::Regular && oper.params == 2)
+    });
+    debug_assert!(opt_compound_oper.is_none() || opt_idx_oper.is_some());
+    trace!("assign  : {} ({})", str_var, &str_oper[..len_oper + 1]);
+    let var_token = VarToken { idx_var: expr_ctx.add_var(str_var), idx_expr: idx_var };
+    expr_ctx.assign = Some(Assign { var_token, idx_oper: opt_idx_oper, idx_expr: idx_oper });
+    Ok(idx_oper + len_oper + 1)
+}
+
+// Parses a function definition, e.g. "align(x, a) = (x + a - 1) & ~(a - 1)".
+fn parse_def(eval_ctx: &EvalContext, str_def: &str) -> Result<UserFunc, ExprError> {
+    let def_error = |idx_expr: usize, message: String| {
+        trace!("{:?} {}", ExprErrorKind::InvalidDefinition, message);
+        Err(ExprError { idx_expr, kind: ExprErrorKind::InvalidDefinition, message })
+    };
+    let is_builtin = |name: &str| eval_ctx.registry().contains(name) || parse_const(name, &CONSTS).is_some();
+
+    let str_name = str_def.trim_start();
+    let idx_name = str_def.len() - str_name.len();
+    let Some(name) = parse_ident(str_name) else {
+        return def_error(idx_name, format!("function name expected at {}", idx_name));
+    };
+    if is_builtin(name) {
+        return def_error(idx_name, format!("of built-in '{}' at {}", name, idx_name));
+    }
+
+    let str_params = str_name[name.len()..].trim_start();
+    let idx_open_paren = str_def.len() - str_params.len();
+    if !str_params.starts_with('(') {
+        let message = format!("at {} for function '{}'", idx_open_paren, name);
+        trace!("{:?} {}", ExprErrorKind::MissingParenthesis, message);
+        return Err(ExprError { idx_expr: idx_open_paren, kind: ExprErrorKind::MissingParenthesis, message });
+    }
+
+    // Parameters are separated by ',' and the list may be empty, e.g. "answer() = 42".
+    let mut params: Vec<String> = Vec::new();
+    let mut str_rest = str_params[1..].trim_start();
+    if !str_rest.starts_with(')') {
+        loop {
+            let idx_param = str_def.len() - str_rest.len();
+            let Some(param) = parse_ident(str_rest) else {
+                return def_error(idx_param, format!("parameter expected at {}", idx_param));
+            };
+            if is_builtin(param) || params.iter().any(|name| name == param) {
+                return def_error(idx_param, format!("parameter '{}' at {}", param, idx_param));
+            }
+            if params.len() + 1 >= MAX_FN_PARAMS as usize {
+                return def_error(idx_param, format!("exceeded maximum parameter count at {}", idx_param));
+            }
+            params.push(param.to_string());
+            str_rest = str_rest[param.len()..].trim_start();
+            match str_rest.chars().next() {
+                Some(',') => str_rest = str_rest[1..].trim_start(),
+                Some(')') => break,
+                _ => {
+                    let idx_sep = str_def.len() - str_rest.len();
+                    return def_error(idx_sep, format!("',' or ')' expected at {}", idx_sep));
+                }
+            }
+        }
+    }
+
+    let str_body = str_rest[1..].trim_start();
+    let idx_assign = str_def.len() - str_body.len();
+    if !str_body.starts_with('=') || str_body[1..].starts_with('=') {
+        return def_error(idx_assign, format!("'=' expected at {}", idx_assign));
+    }
+
+    // Pad the body so errors in it are reported at their position in the definition.
+    let idx_body = idx_assign + 1;
+    let str_body = format!("{:width$}{}", "", &str_def[idx_body..], width = idx_body);
+    let body = parse_expr(&str_body, eval_ctx)?;
+    if let Some(assign) = &body.assign {
+        let idx_var = assign.var_token.idx_expr;
+        return def_error(idx_var, format!("due to assignment at {}", idx_var));
+    }
+    for token in &body.queue_output {
+        if let Token::Var(VarToken { idx_var, idx_expr }) = *token
+            && !params.contains(&body.vars[idx_var])
+        {
+            let message = format!("'{}' at {}", body.vars[idx_var], idx_expr);
+            trace!("{:?} {}", ExprErrorKind::UndefinedVariable, message);
+            return Err(ExprError { idx_expr, kind: ExprErrorKind::UndefinedVariable, message });
+        }
+    }
+    build_expr_tree(&body, &str_body)?;
+
+    let def = format!("{}({}) = {}", name, params.join(", "), str_body.trim());
+    trace!("define  : {}", def);
+    Ok(UserFunc { name: name.to_string(), params, body, def })
+}
+
+// Size suffixes of numbers (e.g. "4K", "2MiB" or "512KB"). A bare unit letter is a binary (IEC)
+// unit like in linker scripts, while "KB", "MB" etc. are decimal (SI) units.
+#[rustfmt::skip]
+static SIZE_SUFFIXES: [(&str, u128); 15] = [
+    ("K",   KIB), ("M",   MIB), ("G",   GIB), ("T",   TIB), ("P",   PIB),
+    ("KiB", KIB), ("MiB", MIB), ("GiB", GIB), ("TiB", TIB), ("PiB", PIB),
+    ("KB",  1_000), ("MB", 1_000_000), ("GB", 1_000_000_000), ("TB", 1_000_000_000_000), ("PB", 1_000_000_000_000_000),
+];
+
+// Parses a size suffix directly following a number, returning the unit and the length of the suffix.
+fn parse_size_suffix(str_expr: &str) -> Option<(u128, usize)> {
+    let str_suffix = parse_ident(str_expr)?;
+    SIZE_SUFFIXES.iter().find(|(name, _)| *name == str_suffix).map(|&(_, unit)| (unit, str_suffix.len()))
 }
 
 fn parse_num(str_expr: &str) -> (Option<Number>, usize) {
@@ -492,12 +1033,12 @@ fn parse_num(str_expr: &str) -> (Option<Number>, usize) {
                 _ => (),
             }
         } else {
-            return (Some(Number { integer: 0, float: 0.0 }), 1);
+            return (Some(Number::from_u128(0)), 1);
         }
     }
 
-    const MAX_DIGITS: usize = 64 + b"0b".len();
-    const STR_SIZE: usize = 72; // Allowed array sizes (for u8) in https://docs.rs/arrayvec/0.3.25/src/arrayvec/array.rs.html
+    const MAX_DIGITS: usize = 128 + b"0b".len();
+    const STR_SIZE: usize = 136;
 
     // Rust string to number conversion functions do not grok prefixes (e.g., "0xf" will have
     // to be fed to it as just "f"). So we aggregate the number into 'str_num' and keep track
@@ -517,35 +1058,77 @@ fn parse_num(str_expr: &str) -> (Option<Number>, usize) {
     // a function call to str_num.len() with only a cost of one extra sub on in the break
     // condition in the loop below.
     let mut consumed = len_prefix;
-    for chr in iter_expr {
+    let mut separators = 0;
+    let mut is_after_space = false;
+    let mut is_after_digit = len_prefix == 1;
+    while let Some(chr) = iter_expr.next() {
         consumed += 1;
-        if consumed > MAX_DIGITS {
+        if consumed - separators > MAX_DIGITS {
             return (None, 0);
-        } else if !chr.is_whitespace() {
-            if chr.is_digit(radix) {
+        } else if chr.is_whitespace() {
+            is_after_space = true;
+        } else {
+            // Digits following whitespace are part of the number (e.g. "0xffff 0000") unless they
+            // begin a word that isn't entirely digits (e.g. "as" in "0xff as u8" is not hex digits).
+            let is_word = || iter_expr.clone().take_while(char::is_ascii_alphanumeric).any(|c| !c.is_digit(radix));
+            if is_after_space && chr.is_digit(radix) && is_word() {
+                consumed -= 1;
+                break;
+            } else if chr.is_digit(radix) {
                 str_num.push(chr);
-            } else if chr == '.' && radix == 10 && !has_dec_pt {
+            } else if chr == '.' && (radix == 10 || radix == 16) && !has_dec_pt && !is_fp_exp_notation {
                 has_dec_pt = true;
                 str_num.push(chr);
+            } else if (chr == 'p' || chr == 'P')
+                && radix == 16
+                && !is_fp_exp_notation
+                && iter_expr.peek().is_some_and(|&c| c.is_ascii_digit() || c == '+' || c == '-')
+            {
+                // Hexadecimal floating point binary exponent (e.g. "0x1.8p3" or "0x1p-2").
+                str_num.push('p');
+                is_fp_exp_notation = true;
             } else if (chr == 'e' || chr == 'E') && has_dec_pt {
                 // Floating point exponent notation (e.g., "2.5e10" or "2.5E-10").
                 str_num.push(chr);
                 is_fp_exp_notation = true;
-            } else if (chr == '+' || chr == '-') && is_fp_exp_notation && !is_fp_exp_sign {
-                // Floating point exponent notation (e.g, +/- power-of character).
+            } else if (chr == '+' || chr == '-')
+                && is_fp_exp_notation
+                && !is_fp_exp_sign
+                && str_num.ends_with(['e', 'E', 'p'])
+            {
+                // Floating point exponent notation (e.g, +/- power-of character). The sign must
+                // directly follow the exponent character, otherwise it's an operator (e.g. "0x1p3+1").
                 str_num.push(chr);
                 is_fp_exp_sign = true;
+            } else if (chr == '_' || chr == '\'')
+                && is_after_digit
+                && iter_expr.peek().is_some_and(|c| c.is_digit(radix))
+            {
+                // Digit separators between digits (e.g. "0xffff_8000_0000_0000" or "0b1010'1010").
+                separators += 1;
             } else {
                 consumed -= 1;
                 break;
             }
         }
+        is_after_digit = chr.is_digit(radix);
+    }
+
+    // A size suffix must directly follow the last digit (e.g. "4K" but not "4 K"). Hexadecimal
+    // numbers have none, as "P" would be ambiguous with the binary exponent (e.g. "0x10P3").
+    let mut opt_unit = None;
+    if is_after_digit && radix != 16 {
+        let idx_suffix = str_expr.char_indices().nth(consumed).map_or(str_expr.len(), |(idx, _)| idx);
+        if let Some((unit, len_suffix)) = parse_size_suffix(&str_expr[idx_suffix..]) {
+            opt_unit = Some(unit);
+            consumed += len_suffix;
+        }
     }
 
     if str_num.is_empty() {
         if len_prefix == 1 {
-            // The number is "0" followed by some non-numeric character, return 0.
-            (Some(Number { integer: 0, float: 0.0 }), 1)
+            // The number is "0" followed by some non-numeric character (or a size suffix), return 0.
+            (Some(Number::from_u128(0)), if opt_unit.is_some() { consumed } else { 1 })
         } else {
             // No numeric characters with/without prefix, it's invalid (e.g "0x", "0n" or "/").
             (None, 0)
@@ -553,25 +1136,194 @@ fn parse_num(str_expr: &str) -> (Option<Number>, usize) {
     } else if str_num.ends_with('.') {
         // Number ends in a decimal point, return invalid.
         (None, 0)
+    } else if radix == 16 && (has_dec_pt || is_fp_exp_notation) {
+        // Hexadecimal float, which requires the binary exponent like in C (e.g. "0x1.8" is invalid).
+        match parse_hex_float(&str_num) {
+            Some(v) => float_num(v, opt_unit, consumed),
+            None => (None, 0),
+        }
     } else if !has_dec_pt {
         // Integer.
-        match u64::from_str_radix(&str_num, radix) {
-            Ok(v) => (Some(Number { integer: v, float: v as f64 }), consumed),
+        match u128::from_str_radix(&str_num, radix).ok().and_then(|v| v.checked_mul(opt_unit.unwrap_or(1))) {
+            Some(v) => (Some(Number::from_u128(v)), consumed),
             _ => (None, 0),
         }
     } else {
         // Float.
-        // If the float is (+/-)Inf/NaN or otherwise not representable in a u64, casting it
-        // results in 0. Right now, I don't know a fool proof way of determining this.
         // TODO: We might also want to consider aborting parsing here in the Inf/NaN case.
         use std::str::FromStr;
         match f64::from_str(&str_num) {
-            Ok(v) => (Some(Number { integer: v as u64, float: v }), consumed),
+            Ok(v) => float_num(v, opt_unit, consumed),
             _ => (None, 0),
         }
     }
 }
 
+fn float_num(float: f64, opt_unit: Option<u128>, consumed: usize) -> (Option<Number>, usize) {
+    let bytes = float * opt_unit.unwrap_or(1) as f64;
+    if opt_unit.is_some() && bytes.fract() == 0.0 && bytes < u128::MAX as f64 {
+        // Sizes are in bytes, so a whole number of bytes is an integer (e.g. "1.5K").
+        (Some(Number::from_u128(bytes as u128)), consumed)
+    } else {
+        (Some(Number::from_f64(bytes)), consumed)
+    }
+}
+
+// Parses the digits of a hexadecimal float without the "0x" prefix (e.g. "1.8p3").
+fn parse_hex_float(str_num: &str) -> Option<f64> {
+    let (str_man, str_exp) = str_num.split_once('p')?;
+    let (str_int, str_frac) = str_man.split_once('.').unwrap_or((str_man, ""));
+    let exp = str_exp.parse::<i32>().ok()?;
+    if str_int.is_empty() && str_frac.is_empty() {
+        return None;
+    }
+
+    // Digits beyond the 120 bits of mantissa kept here are dropped, which is far more than the 53
+    // bits of an f64 and can at most affect rounding of the last bit.
+    let mut man: u128 = 0;
+    let mut exp_man: i64 = 0;
+    for (idx, chr) in str_int.chars().chain(str_frac.chars()).enumerate() {
+        let is_frac = idx >= str_int.len();
+        if man >> (u128::BITS - 8) == 0 {
+            man = man * 16 + chr.to_digit(16)? as u128;
+            exp_man -= if is_frac { 4 } else { 0 };
+        } else {
+            chr.to_digit(16)?;
+            exp_man += if is_frac { 0 } else { 4 };
+        }
+    }
+    if man == 0 {
+        return Some(0.0);
+    }
+    // Scale in two steps so a large mantissa with a small exponent doesn't underflow (or vice versa).
+    let exp = (exp as i64 + exp_man).clamp(-4096, 4096) as i32;
+    Some(man as f64 * 2f64.powi(exp / 2) * 2f64.powi(exp - exp / 2))
+}
+
+// Parses a character literal (e.g. "'A'", "'\n'" or multi-character constants like "'ELF\x7f'"
+// where the first character is the most significant byte), or a string of bytes in memory order
+// (e.g. "bytes(\"abc\")" is the little-endian integer 0x636261). Returns the number and the
+// length of the literal.
+fn parse_char_lit(str_expr: &str, idx_expr: usize) -> Result<Option<(Number, usize)>, ExprError> {
+    debug_assert_eq!(str_expr.trim_start_matches(char::is_whitespace), str_expr);
+    if str_expr.starts_with('\'') {
+        let (bytes, len_lit) = parse_quoted(str_expr, idx_expr)?;
+        let integer = bytes.iter().fold(0, |integer, &byte| (integer << 8) | byte as u128);
+        return Ok(Some((Number::from_u128(integer), len_lit)));
+    }
+
+    // The string of bytes must be the only parameter, e.g. "bytes( \"abc\" )".
+    if parse_ident(str_expr) != Some("bytes") {
+        return Ok(None);
+    }
+    let str_open_paren = str_expr["bytes".len()..].trim_start_matches(|chr: char| chr.is_ascii_whitespace());
+    let Some(str_params) = str_open_paren.strip_prefix('(') else {
+        return Ok(None);
+    };
+    let str_str = str_params.trim_start_matches(|chr: char| chr.is_ascii_whitespace());
+    if !str_str.starts_with('"') {
+        return Ok(None);
+    }
+    let idx_str = idx_expr + str_expr.len() - str_str.len();
+    let (bytes, len_str) = parse_quoted(str_str, idx_str)?;
+    let str_close_paren = str_str[len_str..].trim_start_matches(|chr: char| chr.is_ascii_whitespace());
+    if !str_close_paren.starts_with(')') {
+        let idx_close_paren = idx_expr + str_expr.len() - str_close_paren.len();
+        let message = format!("for function 'bytes' at {}", idx_close_paren);
+        trace!("{:?} {}", ExprErrorKind::MissingParenthesis, message);
+        return Err(ExprError { idx_expr: idx_close_paren, kind: ExprErrorKind::MissingParenthesis, message });
+    }
+    let integer = bytes.iter().rev().fold(0, |integer, &byte| (integer << 8) | byte as u128);
+    Ok(Some((Number::from_u128(integer), str_expr.len() - str_close_paren.len() + 1)))
+}
+
+// Parses the bytes of a literal in single or double quotes, which consist of printable ASCII
+// characters and C escape sequences (e.g. "\n" or "\x7f"). Returns the bytes and the length of
+// the literal including the quotes.
+fn parse_quoted(str_expr: &str, idx_expr: usize) -> Result<(Vec<u8>, usize), ExprError> {
+    const MAX_BYTES: usize = u128::BITS as usize / 8;
+    let quote = str_expr.as_bytes()[0];
+    let mut bytes = Vec::with_capacity(MAX_BYTES);
+    let mut iter_bytes = str_expr.bytes().enumerate().skip(1);
+    while let Some((idx, byte)) = iter_bytes.next() {
+        let opt_byte = match byte {
+            b'\\' => match iter_bytes.next().map(|(_, esc)| esc) {
+                Some(b'0') => Some(0),
+                Some(b'a') => Some(0x07),
+                Some(b'b') => Some(0x08),
+                Some(b't') => Some(b'\t'),
+                Some(b'n') => Some(b'\n'),
+                Some(b'v') => Some(0x0b),
+                Some(b'f') => Some(0x0c),
+                Some(b'r') => Some(b'\r'),
+                Some(esc @ (b'\\' | b'\'' | b'"')) => Some(esc),
+                Some(b'x') => {
+                    let hex = [iter_bytes.next(), iter_bytes.next()];
+                    let str_hex: String = hex.iter().flatten().map(|&(_, digit)| digit as char).collect();
+                    u8::from_str_radix(&str_hex, 16).ok().filter(|_| str_hex.len() == 2)
+                }
+                _ => None,
+            },
+            _ if byte == quote => {
+                if !(1..=MAX_BYTES).contains(&bytes.len()) {
+                    let message = if bytes.is_empty() {
+                        format!("for empty literal at {}", idx_expr)
+                    } else {
+                        format!("for literal exceeding {} bytes at {}", MAX_BYTES, idx_expr)
+                    };
+                    trace!("{:?} {}", ExprErrorKind::InvalidExpr, message);
+                    return Err(ExprError { idx_expr, kind: ExprErrorKind::InvalidExpr, message });
+                }
+                return Ok((bytes, idx + 1));
+            }
+            b' '..=b'~' => Some(byte),
+            _ => None,
+        };
+        let Some(byte) = opt_byte else {
+            let message = format!("in literal at {}", idx_expr + idx);
+            trace!("{:?} {}", ExprErrorKind::InvalidExpr, message);
+            return Err(ExprError { idx_expr: idx_expr + idx, kind: ExprErrorKind::InvalidExpr, message });
+        };
+        bytes.push(byte);
+    }
+
+    let message = format!("for unterminated literal at {}", idx_expr);
+    trace!("{:?} {}", ExprErrorKind::InvalidExpr, message);
+    Err(ExprError { idx_expr, kind: ExprErrorKind::InvalidExpr, message })
+}
+
+// Parses a cast of the preceding operand, e.g. "as u8" in "x as u8".
+// Returns the index of the cast operator and the length of the cast.
+fn parse_cast_as(
+    str_expr: &str,
+    idx_expr: usize,
+    opt_prev_token: &Option<Token>,
+) -> Result<Option<(usize, usize)>, ExprError> {
+    debug_assert_eq!(str_expr.trim_start_matches(char::is_whitespace), str_expr);
+    match opt_prev_token {
+        Some(Token::Num(_)) | Some(Token::Var(_)) => (),
+        Some(Token::Oper(OperToken { idx_oper, .. })) if OPERS[*idx_oper].kind == OperKind::CloseParen => (),
+        _ => return Ok(None),
+    }
+    if parse_ident(str_expr) != Some("as") {
+        return Ok(None);
+    }
+
+    let str_type = str_expr["as".len()..].trim_start();
+    let idx_type = idx_expr + str_expr.len() - str_type.len();
+    let opt_int_type = parse_ident(str_type).and_then(IntType::from_name);
+    let Some(int_type) = opt_int_type else {
+        let message = format!("for cast at {}", idx_type);
+        trace!("{:?} {}", ExprErrorKind::InvalidType, message);
+        return Err(ExprError { idx_expr: idx_type, kind: ExprErrorKind::InvalidType, message });
+    };
+
+    let name_cast = format!("({})", int_type);
+    let idx_oper = OPERS.iter().position(|oper| oper.kind == OperKind::Cast && oper.name == name_cast);
+    debug_assert!(idx_oper.is_some());
+    Ok(Some((idx_oper.unwrap(), idx_type - idx_expr + int_type.name().len())))
+}
+
 fn parse_oper(str_expr: &str, opers: &[Oper], opt_prev_token: &Option<Token>) -> Option<usize> {
     debug_assert_eq!(str_expr.trim_start_matches(char::is_whitespace), str_expr);
 
@@ -583,32 +1335,39 @@ fn parse_oper(str_expr: &str, opers: &[Oper], opt_prev_token: &Option<Token>) ->
         // Otherwise, record the currently found operator only if its length exceeds that
         // of a previously found one (e.g., find "<<" and not stop at "<").
         if str_expr.starts_with(op.name) && (!is_found || op.name.len() > opers[idx_found].name.len()) {
-            // Is this a left associative operator, ensure a previous token exists and that
-            // it's not an operator (other than close parenthesis), otherwise skip finding
-            // it as a valid operator.
-            if op.assoc == OperAssoc::Left {
+            // Is this an infix operator (left associative or right associative binary), ensure a
+            // previous token exists and that it's not an operator (other than close parenthesis),
+            // otherwise skip finding it as a valid operator.
+            if !op.is_prefix() && op.assoc != OperAssoc::Nil {
                 match opt_prev_token {
                     // E.g. "<<4" or ",5".
                     None => continue,
-                    Some(Token::Oper(OperToken { idx_oper, .. })) => {
-                        // E.g. ")-5" when parsing "-" can be valid. So don't skip finding "-".
-                        // E.g. "(<<7" when parsing "<<" is invalid, so skip finding it.
-                        if opers[*idx_oper].kind == OperKind::OpenParen || opers[*idx_oper].kind == OperKind::ParamSep {
-                            continue;
-                        }
+                    // E.g. ")-5" when parsing "-" can be valid. So don't skip finding "-".
+                    // E.g. "(<<7" when parsing "<<" is invalid, so skip finding it.
+                    // E.g. "(i8)-1" when parsing "-" must find unary minus, so skip finding it.
+                    Some(Token::Oper(OperToken { idx_oper, .. }))
+                        if opers[*idx_oper].kind == OperKind::OpenParen
+                            || opers[*idx_oper].kind == OperKind::ParamSep
+                            || opers[*idx_oper].kind == OperKind::Cast =>
+                    {
+                        continue;
                     }
                     _ => (),
                 }
             }
-            // If this is a right associative operator, ensure if a previous token exists
-            // that it's not a right associative unary operator. If it is, it's a malformed
+            // If this is a prefix operator, ensure if a previous token exists that it's not
+            // a prefix operator. If it is, it's a malformed
             // expression like "2+++4". Note: "2++4" is 2+(+4), i.e. 2 plus unary plus 4 which is valid.
             //
             // I've got rid of post/pre inc/dec. operators but this does handle the case if I add it back.
             // Maybe error messages might not be great.
-            else if op.assoc == OperAssoc::Right
+            //
+            // Casts are exempt from this, e.g. "(i8)-1" and "-(i8)1" are both valid.
+            else if op.is_prefix()
+                && op.kind != OperKind::Cast
                 && let Some(Token::Oper(OperToken { idx_oper, .. })) = opt_prev_token
-                && opers[*idx_oper].assoc == OperAssoc::Right
+                && opers[*idx_oper].is_prefix()
+                && opers[*idx_oper].kind != OperKind::Cast
             {
                 continue;
             }
@@ -618,18 +1377,19 @@ fn parse_oper(str_expr: &str, opers: &[Oper], opt_prev_token: &Option<Token>) ->
     }
 
     if is_found {
-        trace!("found '{}' - {}", opers[idx_found].name, opers[idx_found].help);
+        trace!("found '{}' - {} ({})", opers[idx_found].name, opers[idx_found].help, opers[idx_found].syntax);
         Some(idx_found)
     } else {
         None
     }
 }
 
-fn check_prev_token_not_function(opt_prev_token: &Option<Token>) -> Result<(), ExprError> {
+fn check_prev_token_not_function(expr_ctx: &ExprCtx, opt_prev_token: &Option<Token>) -> Result<(), ExprError> {
     match opt_prev_token {
         Some(Token::Func(FuncToken { idx_func, idx_expr, .. })) => {
-            let idx_open_paren = idx_expr + FUNCS[*idx_func].name.len();
-            let message = format!("at {} for function '{}'", idx_open_paren, &FUNCS[*idx_func].name);
+            let name = expr_ctx.funcs[*idx_func].name();
+            let idx_open_paren = idx_expr + name.len();
+            let message = format!("at {} for function '{}'", idx_open_paren, name);
             trace!("{:?} {}", ExprErrorKind::MissingParenthesis, message);
             Err(ExprError { idx_expr: idx_open_paren, kind: ExprErrorKind::MissingParenthesis, message })
         }
@@ -639,8 +1399,13 @@ fn check_prev_token_not_function(opt_prev_token: &Option<Token>) -> Result<(), E
 
 fn check_prev_token_not_number(opt_prev_token: &Option<Token>) -> Result<(), ExprError> {
     match opt_prev_token {
-        Some(Token::Num(NumToken { number, idx_expr })) => {
-            let message = format!("following number {} at {}", number.float, idx_expr);
+        Some(Token::Num(NumToken { number, idx_expr, .. })) => {
+            let message = format!("following number {} at {}", number, idx_expr);
+            trace!("{:?} {}", ExprErrorKind::MissingOperator, message);
+            Err(ExprError { idx_expr: *idx_expr, kind: ExprErrorKind::MissingOperator, message })
+        }
+        Some(Token::Var(VarToken { idx_expr, .. })) => {
+            let message = format!("following variable at {}", idx_expr);
             trace!("{:?} {}", ExprErrorKind::MissingOperator, message);
             Err(ExprError { idx_expr: *idx_expr, kind: ExprErrorKind::MissingOperator, message })
         }
@@ -660,13 +1425,18 @@ fn check_prev_token_not_close_paren(opt_prev_token: &Option<Token>) -> Result<()
     }
 }
 
-fn check_open_paren_for_func(oper_token: &OperToken, opt_prev_token: &Option<Token>) -> Result<(), ExprError> {
+fn check_open_paren_for_func(
+    expr_ctx: &ExprCtx,
+    oper_token: &OperToken,
+    opt_prev_token: &Option<Token>,
+) -> Result<(), ExprError> {
     debug_assert!(oper_token.idx_oper < OPERS.len());
     let oper = &OPERS[oper_token.idx_oper];
     match opt_prev_token {
         Some(Token::Func(FuncToken { idx_func, idx_expr, .. })) if oper.kind != OperKind::OpenParen => {
-            let idx_open_paren = idx_expr + FUNCS[*idx_func].name.len();
-            let message = format!("at {} for function '{}'", idx_open_paren, &FUNCS[*idx_func].name);
+            let name = expr_ctx.funcs[*idx_func].name();
+            let idx_open_paren = idx_expr + name.len();
+            let message = format!("at {} for function '{}'", idx_open_paren, name);
             trace!("{:?} {}", ExprErrorKind::MissingParenthesis, message);
             Err(ExprError { idx_expr: idx_open_paren, kind: ExprErrorKind::MissingParenthesis, message })
         }
@@ -674,14 +1444,17 @@ fn check_open_paren_for_func(oper_token: &OperToken, opt_prev_token: &Option<Tok
     }
 }
 
-fn parse_expr(str_expr: &str) -> Result<ExprCtx, ExprError> {
+fn parse_expr(str_expr: &str, eval_ctx: &EvalContext) -> Result<ExprCtx, ExprError> {
     // We iterate by characters here because we want to know the index of every token.
     // The index is primarily for reporting parsing and evaluation errors.
     // If we didn't need to store the index, we can easily loop, trim_start whitespaces,
     // and just re-assign 'str_subexpr' to the string slice given by parse_num().
     let mut expr_ctx = ExprCtx::new();
     let mut opt_prev_token: Option<Token> = None;
-    let mut iter_str = str_expr.char_indices();
+
+    // If the expression is assigned to a variable, parse only what follows the assignment.
+    let len_assign = parse_assign(str_expr, eval_ctx.registry(), &mut expr_ctx)?;
+    let mut iter_str = str_expr.char_indices().skip_while(|&(idx, _)| idx < len_assign);
 
     let mut last_idx = 0;
     while let Some((idx, chr)) = iter_str.next() {
@@ -699,37 +1472,75 @@ fn parse_expr(str_expr: &str) -> Result<ExprCtx, ExprError> {
         let len_token;
         let str_subexpr = &str_expr[idx..];
         if let (Some(number), len_str) = parse_num(str_subexpr) {
-            // If the previous token was a function or a close parenthesis, it's invalid.
-            // E.g "avg 32.5" or "(2)3" or "(1).5".
-            check_prev_token_not_function(&opt_prev_token)?;
+            // If the previous token was a function, variable or a close parenthesis, it's invalid.
+            // E.g "avg 32.5" or "x 5" or "(2)3" or "(1).5".
+            check_prev_token_not_function(&expr_ctx, &opt_prev_token)?;
+            check_prev_token_not_number(&opt_prev_token)?;
             check_prev_token_not_close_paren(&opt_prev_token)?;
-            trace!("number  : {} (0x{:x})", number.integer, number.integer);
-            let num_token = NumToken { number, idx_expr: idx };
-            expr_ctx.queue_output.push_back(Token::Num(num_token));
+            trace!("number  : {}", number);
+            let num_token = NumToken { number, idx_expr: idx, len: len_str };
+            expr_ctx.queue_output.push_back(Token::Num(num_token.clone()));
             len_token = len_str;
             opt_prev_token = Some(Token::Num(num_token));
+        } else if let Some((number, len_lit)) = parse_char_lit(str_subexpr, idx)? {
+            // Character literals are numbers, e.g. "'A' + 1" or "bytes(\"abc\")".
+            check_prev_token_not_function(&expr_ctx, &opt_prev_token)?;
+            check_prev_token_not_number(&opt_prev_token)?;
+            check_prev_token_not_close_paren(&opt_prev_token)?;
+            trace!("literal : {}", number);
+            let num_token = NumToken { number, idx_expr: idx, len: len_lit };
+            expr_ctx.queue_output.push_back(Token::Num(num_token.clone()));
+            len_token = len_lit;
+            opt_prev_token = Some(Token::Num(num_token));
         } else if let Some(idx_oper) = parse_oper(str_subexpr, &OPERS, &opt_prev_token) {
             debug_assert!(idx_oper < OPERS.len());
             let oper_token = OperToken { idx_oper, idx_expr: idx };
             // If the previous token was a function, this must be an open parenthesis.
             // E.g "avg +"; otherwise this is an invalid expression.
-            check_open_paren_for_func(&oper_token, &opt_prev_token)?;
+            check_open_paren_for_func(&expr_ctx, &oper_token, &opt_prev_token)?;
             trace!("operator: {}", &OPERS[idx_oper].name);
             expr_ctx.process_oper(oper_token, &opt_prev_token)?;
             len_token = OPERS[idx_oper].name.len();
             opt_prev_token = Some(Token::Oper(oper_token));
-        } else if let Some(idx_func) = parse_function(str_subexpr, &FUNCS) {
-            debug_assert!(idx_func < FUNCS.len());
+        } else if let Some((idx_oper, len_cast)) = parse_cast_as(str_subexpr, idx, &opt_prev_token)? {
+            // The cast is applied to the preceding operand, so the previous token remains as is.
+            let oper_token = OperToken { idx_oper, idx_expr: idx };
+            trace!("operator: {}", &OPERS[idx_oper].name);
+            expr_ctx.process_cast_as(oper_token)?;
+            len_token = len_cast;
+        } else if let Some(str_ident) = parse_ident(str_subexpr) {
             // If the previous token was a function or a number or a closing paren,
             // we have an invalid expression. E.g "avg avg" or "5 bit(2)" or "bit(3)bit(2)"
-            check_prev_token_not_function(&opt_prev_token)?;
+            check_prev_token_not_function(&expr_ctx, &opt_prev_token)?;
             check_prev_token_not_number(&opt_prev_token)?;
             check_prev_token_not_close_paren(&opt_prev_token)?;
-            trace!("function: {}", &FUNCS[idx_func].name);
-            let func_token = FuncToken { idx_func, idx_expr: idx, params: 0 };
-            expr_ctx.stack_op.push(Token::Func(func_token));
-            len_token = FUNCS[idx_func].name.len();
-            opt_prev_token = Some(Token::Func(func_token));
+            // User-defined functions only take precedence over variables when called, e.g. "x(5)".
+            let is_call = str_subexpr[str_ident.len()..].trim_start().starts_with('(');
+            let opt_func = parse_function(str_ident, eval_ctx.registry())
+                .or_else(|| eval_ctx.func(str_ident).filter(|_| is_call).map(FuncRef::User));
+            if let Some(func) = opt_func {
+                trace!("function: {}", func.name());
+                let idx_func = expr_ctx.add_func(func);
+                let func_token = FuncToken { idx_func, idx_expr: idx, params: 0, idx_close_paren: 0 };
+                expr_ctx.stack_op.push(Token::Func(func_token));
+                opt_prev_token = Some(Token::Func(func_token));
+            } else if let Some(idx_const) = parse_const(str_ident, &CONSTS) {
+                // Constants are known while parsing, so they're just numbers from here on.
+                debug_assert!(idx_const < CONSTS.len());
+                let number = CONSTS[idx_const].value.clone();
+                trace!("constant: {} ({})", &CONSTS[idx_const].name, number);
+                let num_token = NumToken { number, idx_expr: idx, len: str_ident.len() };
+                expr_ctx.queue_output.push_back(Token::Num(num_token.clone()));
+                opt_prev_token = Some(Token::Num(num_token));
+            } else {
+                // Variables are looked up while evaluating, so the same parsed expression
+                // can be evaluated with different values.
+                trace!("variable: {}", str_ident);
+                let var_token = VarToken { idx_var: expr_ctx.add_var(str_ident), idx_expr: idx };
+                expr_ctx.queue_output.push_back(Token::Var(var_token));
+                opt_prev_token = Some(Token::Var(var_token));
+            }
+            len_token = str_ident.len();
         } else {
             let message = format!("at {}", idx);
             trace!("{:?} {}", ExprErrorKind::InvalidExpr, message);
@@ -756,24 +1567,165 @@ fn parse_expr(str_expr: &str) -> Result<ExprCtx, ExprError> {
 
         // Pop remaining tokens from op stack to the output queue.
         expr_ctx.pop_all_to_output_queue()?;
+        debug_assert!(expr_ctx.stack_jump.is_empty());
         Ok(expr_ctx)
     }
 }
 
-fn evaluate_expr(expr_ctx: &mut ExprCtx) -> Result<Number, ExprError> {
-    // Pop tokens from the output queue to an output stack and process them.
+fn get_var(expr_ctx: &ExprCtx, eval_ctx: &EvalContext, var_token: VarToken) -> Result<Number, ExprError> {
+    let str_var = &expr_ctx.vars[var_token.idx_var];
+    let opt_number = eval_ctx.var(str_var).map(|number| to_int_mode(number, eval_ctx.int_mode()));
+    opt_number.ok_or_else(|| {
+        let message = format!("'{}' at {}", str_var, var_token.idx_expr);
+        trace!("{:?} {}", ExprErrorKind::UndefinedVariable, message);
+        ExprError { idx_expr: var_token.idx_expr, kind: ExprErrorKind::UndefinedVariable, message }
+    })
+}
+
+fn assign_var(
+    expr_ctx: &ExprCtx,
+    eval_ctx: &mut EvalContext,
+    assign: &Assign,
+    number: Number,
+) -> Result<Number, ExprError> {
+    // For compound assignments, apply the operator to the variable's current value first.
+    let number = match assign.idx_oper {
+        Some(idx_oper) => {
+            let oper = &OPERS[idx_oper];
+            let parameters = [get_var(expr_ctx, eval_ctx, assign.var_token)?, number];
+            let number = to_int_mode((oper.evalfn)(assign.idx_expr, &parameters)?, eval_ctx.int_mode());
+            if oper.checked {
+                let evalfn = |params: &[Number]| (oper.evalfn)(assign.idx_expr, params);
+                check_overflow(eval_ctx, assign.idx_expr, oper.name, &parameters, number, evalfn)?
+            } else {
+                number
+            }
+        }
+        None => number,
+    };
+    eval_ctx.set_var(&expr_ctx.vars[assign.var_token.idx_var], number.clone());
+    Ok(number)
+}
+
+// In arbitrary-precision mode integers are big integers, otherwise they wrap at the width of their
+// type which in word mode is always the type of the word. Floats and booleans are left as is.
+fn to_int_mode(number: Number, int_mode: IntMode) -> Number {
+    match int_mode {
+        IntMode::Fixed => number.into_fixed(),
+        IntMode::Arbitrary => number.into_big(),
+        IntMode::Word(word_type) if matches!(number, Number::Int { .. }) => number.cast(word_type),
+        IntMode::Word(_) => number,
+    }
+}
+
+// Applies the overflow policy to the integer result of an operator or function. The exact result
+// is obtained by evaluating it again with arbitrary-precision integers.
+fn check_overflow<F>(
+    eval_ctx: &EvalContext,
+    idx_expr: usize,
+    name: &str,
+    parameters: &[Number],
+    number: Number,
+    evalfn: F,
+) -> Result<Number, ExprError>
+where
+    F: Fn(&[Number]) -> Result<Number, ExprError>,
+{
+    if eval_ctx.overflow() == Overflow::Wrapping
+        || eval_ctx.int_mode() == IntMode::Arbitrary
+        || !matches!(number, Number::Int { .. })
+    {
+        return Ok(number);
+    }
+
+    // Shifting left by the width of the type or more shifts out every bit, which overflows unless
+    // the integer is 0. The exact result isn't computed as it may be too large (e.g. "1 << 100000"),
+    // and it has the sign of the integer.
+    let is_negative = match parameters {
+        [lhs, rhs] if name == "<<" && rhs.to_u32().is_none_or(|shift| shift >= lhs.int_type().bits()) => {
+            if lhs.is_zero() {
+                return Ok(number);
+            }
+            lhs.is_negative()
+        }
+        _ => {
+            let big_params: Vec<Number> = parameters.iter().cloned().map(Number::into_big).collect();
+            let exact = evalfn(&big_params)?.to_big();
+            if exact == number.to_big() {
+                return Ok(number);
+            }
+            exact.sign() == Sign::Minus
+        }
+    };
+
+    let int_type = number.int_type();
+    if eval_ctx.overflow() == Overflow::Saturating {
+        let integer = if is_negative {
+            int_type.min_int()
+        } else {
+            int_type.max_int()
+        };
+        Ok(Number::from_int(integer, int_type))
+    } else {
+        let message = format!("due to {} overflow for '{}' at {}", int_type, name, idx_expr);
+        trace!("{:?} {}", ExprErrorKind::FailedEvaluation, message);
+        Err(ExprError { idx_expr, kind: ExprErrorKind::FailedEvaluation, message })
+    }
+}
+
+fn evaluate_expr(expr_ctx: &ExprCtx, eval_ctx: &mut EvalContext) -> Result<Number, ExprError> {
+    // Process tokens from the output queue using an output stack.
+    // We walk the queue by index rather than popping it since jumps may skip ahead.
     let mut stack_output: Vec<Number> = Vec::with_capacity(PRE_ALLOC_TOKENS);
-    while let Some(token) = expr_ctx.queue_output.pop_front() {
-        match token {
-            Token::Num(NumToken { number, .. }) => stack_output.push(number),
+    let mut idx_token = 0;
+    let int_mode = eval_ctx.int_mode();
+    while let Some(token) = expr_ctx.queue_output.get(idx_token) {
+        idx_token += 1;
+        match *token {
+            Token::Num(NumToken { ref number, .. }) => stack_output.push(to_int_mode(number.clone(), int_mode)),
+
+            Token::Var(var_token) => stack_output.push(get_var(expr_ctx, eval_ctx, var_token)?),
+
+            Token::Jump(JumpToken { cond, idx_target }) => {
+                // If the stack is empty, don't jump and let the operator owning the jump report
+                // the missing operand.
+                if let Some(top) = stack_output.last() {
+                    let is_jump = match cond {
+                        JumpCond::IfZero => top.is_zero(),
+                        JumpCond::IfNonZero => !top.is_zero(),
+                        JumpCond::Always => true,
+                    };
+                    if is_jump {
+                        debug_assert!(idx_target >= idx_token);
+                        stack_output.push(Number::from_bool(false));
+                        idx_token = idx_target;
+                    }
+                }
+            }
 
             Token::Oper(OperToken { idx_oper, idx_expr }) => {
                 debug_assert!(idx_oper < OPERS.len());
                 let oper = &OPERS[idx_oper];
-                if let Some(parameters) = expr_ctx.collect_params(oper.params as usize, &mut stack_output) {
+                if let Some(parameters) = ExprCtx::collect_params(oper.params as usize, &mut stack_output) {
                     debug_assert!(parameters.len() == oper.params as usize);
                     let res_expr = (oper.evalfn)(idx_expr, &parameters)?;
-                    stack_output.push(res_expr);
+                    // Casts produce fixed-width integers even in arbitrary-precision mode.
+                    if oper.kind == OperKind::Cast && int_mode == IntMode::Arbitrary {
+                        stack_output.push(res_expr);
+                    } else if oper.checked {
+                        let res_expr = to_int_mode(res_expr, int_mode);
+                        let evalfn = |params: &[Number]| (oper.evalfn)(idx_expr, params);
+                        stack_output.push(check_overflow(
+                            eval_ctx,
+                            idx_expr,
+                            oper.name,
+                            &parameters,
+                            res_expr,
+                            evalfn,
+                        )?);
+                    } else {
+                        stack_output.push(to_int_mode(res_expr, int_mode));
+                    }
                 } else {
                     let message = format!("for operator '{}' at {}", oper.name, idx_expr);
                     trace!("{:?} {}", ExprErrorKind::InvalidParamCount, message);
@@ -781,13 +1733,44 @@ fn evaluate_expr(expr_ctx: &mut ExprCtx) -> Result<Number, ExprError> {
                 }
             }
 
-            Token::Func(FuncToken { idx_func, idx_expr, params }) => {
-                debug_assert!(idx_func < FUNCS.len());
-                let function = &FUNCS[idx_func];
-                if let Some(parameters) = expr_ctx.collect_params(params as usize, &mut stack_output) {
+            Token::Func(FuncToken { idx_func, idx_expr, params, .. }) => {
+                debug_assert!(idx_func < expr_ctx.funcs.len());
+                let func = &expr_ctx.funcs[idx_func];
+                let function = match func {
+                    FuncRef::Builtin(function) => function,
+                    FuncRef::Registered(_) | FuncRef::User(_) => {
+                        let Some(parameters) = ExprCtx::collect_args(func, params, &mut stack_output) else {
+                            let message = format!("for function '{}' at {}", func.name(), idx_expr);
+                            trace!("{:?} {}", ExprErrorKind::InvalidParamCount, message);
+                            return Err(ExprError { idx_expr, kind: ExprErrorKind::InvalidParamCount, message });
+                        };
+                        // Operators and functions in the body of user-defined functions are subject to
+                        // the overflow policy on their own.
+                        let res_expr = match func {
+                            FuncRef::Registered(function) => function.evaluate(idx_expr, &parameters)?,
+                            FuncRef::User(user_func) => user_func.evaluate(eval_ctx, idx_expr, &parameters)?,
+                            FuncRef::Builtin(_) => unreachable!(),
+                        };
+                        stack_output.push(to_int_mode(res_expr, int_mode));
+                        continue;
+                    }
+                };
+                if let Some(parameters) = ExprCtx::collect_params(params as usize, &mut stack_output) {
                     debug_assert!(parameters.len() == params as usize);
-                    let res_expr = (function.evalfn)(function, idx_expr, &parameters)?;
-                    stack_output.push(res_expr);
+                    let res_expr = to_int_mode((function.evalfn)(function, idx_expr, &parameters)?, int_mode);
+                    if function.checked {
+                        let evalfn = |params: &[Number]| (function.evalfn)(function, idx_expr, params);
+                        stack_output.push(check_overflow(
+                            eval_ctx,
+                            idx_expr,
+                            function.name,
+                            &parameters,
+                            res_expr,
+                            evalfn,
+                        )?);
+                    } else {
+                        stack_output.push(res_expr);
+                    }
                 } else {
                     let message = format!("for function '{}' at {}", function.name, idx_expr);
                     trace!("{:?} {}", ExprErrorKind::InvalidParamCount, message);
@@ -798,7 +1781,10 @@ fn evaluate_expr(expr_ctx: &mut ExprCtx) -> Result<Number, ExprError> {
     }
 
     if let Some(n) = stack_output.pop() {
-        Ok(n)
+        match &expr_ctx.assign {
+            Some(assign) => assign_var(expr_ctx, eval_ctx, assign, n),
+            None => Ok(n),
+        }
     } else {
         let message = "evaluation failed".to_string();
         trace!("{}", message);
@@ -806,6 +1792,97 @@ fn evaluate_expr(expr_ctx: &mut ExprCtx) -> Result<Number, ExprError> {
     }
 }
 
+// Builds the expression tree by walking the output queue the same way it's evaluated, but pushing
+// nodes instead of numbers on the output stack.
+fn build_expr_tree(expr_ctx: &ExprCtx, str_expr: &str) -> Result<Expr, ExprError> {
+    let mut stack_output: Vec<Expr> = Vec::with_capacity(PRE_ALLOC_TOKENS);
+    for token in &expr_ctx.queue_output {
+        match *token {
+            Token::Num(NumToken { ref number, idx_expr, len }) => {
+                // Numbers may contain whitespace between digits (e.g. "0xffff 0000") and the parsed
+                // length includes whitespace following them.
+                let str_num = str_expr[idx_expr..idx_expr + len].trim_end();
+                let text = if str_num.starts_with(['\'', 'b']) {
+                    // Character literals and strings of bytes may contain spaces (e.g. "' '").
+                    str_num.to_string()
+                } else {
+                    str_num.chars().filter(|chr| !chr.is_whitespace()).collect()
+                };
+                let kind = ExprKind::Num { number: number.clone(), text };
+                stack_output.push(Expr { kind, span: Span::new(idx_expr, idx_expr + str_num.len()) });
+            }
+
+            Token::Var(VarToken { idx_var, idx_expr }) => {
+                let name = &expr_ctx.vars[idx_var];
+                let span = Span::new(idx_expr, idx_expr + name.len());
+                stack_output.push(Expr { kind: ExprKind::Var(name.clone()), span });
+            }
+
+            // Jumps only skip evaluation, the operands skipped are still in the output queue.
+            Token::Jump(_) => (),
+
+            Token::Oper(OperToken { idx_oper, idx_expr }) => {
+                let oper = &OPERS[idx_oper];
+                let Some(params) = ExprCtx::collect_params(oper.params as usize, &mut stack_output) else {
+                    let message = format!("for operator '{}' at {}", oper.name, idx_expr);
+                    trace!("{:?} {}", ExprErrorKind::InvalidParamCount, message);
+                    return Err(ExprError { idx_expr, kind: ExprErrorKind::InvalidParamCount, message });
+                };
+
+                // Casts like "x as u8" follow their operand.
+                let str_oper = &str_expr[idx_expr..];
+                let len_oper = if oper.kind == OperKind::Cast && str_oper.starts_with("as") {
+                    let str_type = str_oper["as".len()..].trim_start();
+                    str_oper.len() - str_type.len() + parse_ident(str_type).map_or(0, str::len)
+                } else {
+                    oper.name.len()
+                };
+                let span =
+                    params.iter().fold(Span::new(idx_expr, idx_expr + len_oper), |span, param| span.to(param.span));
+
+                let mut iter_params = params.into_iter().map(Box::new);
+                let mut next_param = || iter_params.next().unwrap();
+                let kind = match oper.params {
+                    1 => ExprKind::Unary { oper: oper.name, operand: next_param() },
+                    2 => ExprKind::Binary { oper: oper.name, lhs: next_param(), rhs: next_param() },
+                    _ => {
+                        debug_assert!(oper.kind == OperKind::TernaryElse);
+                        ExprKind::Ternary { cond: next_param(), if_true: next_param(), if_false: next_param() }
+                    }
+                };
+                stack_output.push(Expr { kind, span });
+            }
+
+            Token::Func(FuncToken { idx_func, idx_expr, params, idx_close_paren }) => {
+                let name = expr_ctx.funcs[idx_func].name();
+                let Some(args) = ExprCtx::collect_args(&expr_ctx.funcs[idx_func], params, &mut stack_output) else {
+                    let message = format!("for function '{}' at {}", name, idx_expr);
+                    trace!("{:?} {}", ExprErrorKind::InvalidParamCount, message);
+                    return Err(ExprError { idx_expr, kind: ExprErrorKind::InvalidParamCount, message });
+                };
+                let span = Span::new(idx_expr, idx_close_paren + 1);
+                stack_output.push(Expr { kind: ExprKind::Call { func: name.to_string(), args }, span });
+            }
+        }
+    }
+
+    let Some(expr) = stack_output.pop() else {
+        let message = "evaluation failed".to_string();
+        trace!("{}", message);
+        return Err(ExprError { idx_expr: 0, kind: ExprErrorKind::InvalidExpr, message });
+    };
+    match &expr_ctx.assign {
+        Some(assign) => {
+            let var_token = assign.var_token;
+            let span = Span::new(var_token.idx_expr, expr.span.end);
+            let var = expr_ctx.vars[var_token.idx_var].clone();
+            let oper = assign.idx_oper.map(|idx_oper| OPERS[idx_oper].name);
+            Ok(Expr { kind: ExprKind::Assign { var, oper, value: Box::new(expr) }, span })
+        }
+        None => Ok(expr),
+    }
+}
+
 pub const fn max_sub_expressions() -> usize {
     MAX_SUB_EXPRS as usize
 }
@@ -1,5 +1,8 @@
+use crate::constants::CONSTS;
+use crate::context::EvalContext;
 use crate::evaluator::{ExprErrorKind, evaluate_expr, parse_expr, parse_num};
 use crate::functions::{FUNCS, MAX_FN_PARAMS};
+use crate::number::Number;
 use crate::operators::{OPERS, OperAssoc, OperKind};
 
 #[test]
@@ -23,6 +26,17 @@ fn parse_invalid_nums() {
         "0..",
         "0x",
         "0xgff",
+        "0x_ff",
+        "_1",
+        "'1",
+        "1.K",
+        "340282366920938463463374607431768211455K",
+        "0x1.8",
+        "0x1.p",
+        "0x1.8p",
+        "0xp1",
+        "0x.p1",
+        "0x1p+",
         "0b",
         "0b210110",
         "0o",
@@ -33,15 +47,20 @@ fn parse_invalid_nums() {
         "2.5ee++4",
         "2.5e--5",
         "2..5",
-        "0b11111111111111111111111111111111111111111111111111111111111111111", // tests 1 more than 64 1's.
+        "0b111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111", // tests 1 more than 128 1's.
+        "0x1ffffffffffffffffffffffffffffffff", // tests 1 more than 128 bits.
     ];
     // Make sure we never parse operators as valid numbers.
-    for i in 0..OPERS.len() {
-        vec_nums.push(&OPERS[i].name);
+    for oper in &OPERS {
+        vec_nums.push(oper.name);
     }
     // Make sure we never parse FUNCS as valid numbers.
-    for i in 0..FUNCS.len() {
-        vec_nums.push(&FUNCS[i].name);
+    for func in &FUNCS {
+        vec_nums.push(func.name);
+    }
+    // Make sure we never parse CONSTS as valid numbers.
+    for constant in &CONSTS {
+        vec_nums.push(constant.name);
     }
     for num_res in vec_nums {
         let (number, len_str) = parse_num(num_res);
@@ -51,7 +70,7 @@ fn parse_invalid_nums() {
 }
 
 #[test]
-fn parse_valid_nums_u64() {
+fn parse_valid_nums_u128() {
     #[rustfmt::skip]
     let pair_int_result = vec![
         // 0-9
@@ -114,6 +133,9 @@ fn parse_valid_nums_u64() {
         ("0xfffffffffffffff7", 0xfffffffffffffff7),
         ("0xabcdefabcdefabcd", 0xabcdefabcdefabcd),
         ("0xFEDCBAFEDCBAFEDC", 0xfedcbafedcbafedc),
+        ("0x10000000000000000", 0x10000000000000000),
+        ("0xffffffffffffffffffffffffffffffff", 0xffffffffffffffffffffffffffffffff),
+        ("0x0123456789abcdef0123456789abcdef", 0x0123456789abcdef0123456789abcdef),
         // Binary prefix
         ("0b0",  0  ), ("0b1",  1  ), ("0b10", 2  ), ("0b11", 3  ), ("0b100", 4 ),
         ("0b11111111111111111111111111111111", 0xffffffff),
@@ -121,11 +143,14 @@ fn parse_valid_nums_u64() {
         ("0b0000000000000000000000000000000011111111111111111111111111111111", 0xffffffff),
         ("0b1111111111111111111111111111111100000000000000000000000000000000", 0xffffffff00000000),
         ("0b1010101010101010101010101010101010101010101010101010101010101010", 0xaaaaaaaaaaaaaaaa),
+        ("0b11111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111", u128::MAX),
         // Octal prefix.
         ("0o0",  0  ), ("0o1",  1  ), ("0o2",  2  ), ("0o3",  3  ), ("0o4",  4  ),
         ("0o5",  5  ), ("0o6",  6  ), ("0o7",  7  ), ("0o7",  7  ),
         ("0o10", 8  ), ("0o11", 9  ),
         ("0o77", 63 ), ("0o100", 64),
+        ("0o3777777777777777777777777777777777777777777", u128::MAX),
+        ("340282366920938463463374607431768211455", u128::MAX),
         // With whitespaces
         ("5 4 3 2 1", 54321),
         ("0xffff ffff ffff fff7", 0xfffffffffffffff7),
@@ -133,13 +158,83 @@ fn parse_valid_nums_u64() {
         ("0b 101 000 100", 324),
         ("0b 1 0 0", 4),
         ("0o 1 7 7 1", 1017),
+        // With digit separators
+        ("1_000_000", 1000000),
+        ("1'000'000", 1000000),
+        ("0_001", 1),
+        ("0xffff_8000_0000_0000", 0xffff800000000000),
+        ("0xdead'beef", 0xdeadbeef),
+        ("0b1010'1010", 0xaa),
+        ("0o7_7_7", 0o777),
+        ("0x0123_4567_89ab_cdef_0123_4567_89ab_cdef", 0x0123456789abcdef0123456789abcdef),
+        ("0b1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111_1111", u128::MAX),
     ];
     for int_res in pair_int_result {
         let (number, len_str) = parse_num(int_res.0);
         assert!(number.is_some(), "failed for ('{}', {})", int_res.0, int_res.1);
-        assert_eq!(number.unwrap().integer, int_res.1);
+        assert_eq!(number.unwrap().integer(), int_res.1);
         assert_eq!(len_str, int_res.0.len());
     }
+
+    // Whitespace followed by a word that isn't entirely digits ends the number (e.g. "0xff as u8").
+    #[rustfmt::skip]
+    let int_len_result = vec![
+        ("0xff as u8", 0xff,   5),
+        ("0x1 fa",     0x1fa,  6),
+        ("0xf bat",    0xf,    4),
+        ("0xf f0x",    0xf,    4),
+    ];
+    for int_res in int_len_result {
+        let (number, len_str) = parse_num(int_res.0);
+        assert!(number.is_some(), "failed for ('{}', {})", int_res.0, int_res.1);
+        assert_eq!(number.unwrap().integer(), int_res.1);
+        assert_eq!(len_str, int_res.2);
+    }
+
+    // Digit separators must be between digits, otherwise they end the number.
+    #[rustfmt::skip]
+    let int_len_result = vec![
+        ("1__0",       1,      1),
+        ("1_",         1,      1),
+        ("1'_2",       1,      1),
+        ("0xff _1",    0xff,   5),
+        ("0xff 'a'",   0xff,   5),
+        ("0xfg_1",     0xf,    3),
+    ];
+    for int_res in int_len_result {
+        let (number, len_str) = parse_num(int_res.0);
+        assert!(number.is_some(), "failed for ('{}', {})", int_res.0, int_res.1);
+        assert_eq!(number.unwrap().integer(), int_res.1);
+        assert_eq!(len_str, int_res.2);
+    }
+
+    // Size suffixes must directly follow the number and not be followed by other identifier characters.
+    #[rustfmt::skip]
+    let int_len_result = vec![
+        ("4K",           0x1000,              2),
+        ("0K",           0,                   2),
+        ("2MiB",         0x200000,            4),
+        ("1G",           0x40000000,          2),
+        ("3TiB",         0x30000000000,       4),
+        ("512KB",        512000,              5),
+        ("2MB",          2000000,             3),
+        ("1'000GB",      1000000000000,       7),
+        ("1.5K",         1536,                4),
+        ("16MiB-4KiB",   0x1000000,           5),
+        ("4 K",          4,                   2),
+        ("4k",           4,                   1),
+        ("4Kb",          4,                   1),
+        ("4K_",          4,                   1),
+        ("2KiBx",        2,                   1),
+        ("0x10P",        0x10,                4),
+        ("0x10K",        0x10,                4),
+    ];
+    for int_res in int_len_result {
+        let (number, len_str) = parse_num(int_res.0);
+        assert!(number.is_some(), "failed for ('{}', {})", int_res.0, int_res.1);
+        assert_eq!(number.unwrap().integer(), int_res.1);
+        assert_eq!(len_str, int_res.2);
+    }
 }
 
 #[test]
@@ -174,13 +269,37 @@ fn parse_valid_nums_f64() {
         ("2.5e+3"   , 2500.0),
         (".5e+2"    , 50.0  ),
         ("1234.5e-2", 12.345),
+        ("1.1K"     , 1126.4),
+        ("0x1.8p3"  , 12.0  ),
+        ("0x1p-2"   , 0.25  ),
+        ("0xA.8P+1" , 21.0  ),
+        ("0x.8p1"   , 1.0   ),
+        ("0x1_0.0p0", 16.0  ),
+        ("0x1.fffffffffffffp1023", f64::MAX),
+        ("0x1p-1074", 5e-324),
+        ("0x0.0000000000000000000000000000000000000001p160", 1.0),
+        ("0x10000000000000000000000000000000000000000p-160", 1.0),
     ];
     for float_res in pair_float_result {
         let (number, len_str) = parse_num(float_res.0);
         assert!(number.is_some(), "failed for ('{}', {})", float_res.0, float_res.1);
-        assert_eq!(number.unwrap().float, float_res.1);
+        assert_eq!(number.unwrap(), Number::from_f64(float_res.1));
         assert_eq!(len_str, float_res.0.len());
     }
+
+    // An exponent sign not directly following the exponent character ends the number.
+    #[rustfmt::skip]
+    let float_len_result = vec![
+        ("2.5e3+1",     2500.0,     5),
+        ("0x1p3+1",     8.0,        5),
+        ("0x1p3-0x1p2", 8.0,        5),
+    ];
+    for float_res in float_len_result {
+        let (number, len_str) = parse_num(float_res.0);
+        assert!(number.is_some(), "failed for ('{}', {})", float_res.0, float_res.1);
+        assert_eq!(number.unwrap(), Number::from_f64(float_res.1));
+        assert_eq!(len_str, float_res.2);
+    }
 }
 
 #[test]
@@ -190,7 +309,7 @@ fn is_oper_table_valid() {
     let mut param_sep_count = 0;
     for (idx, oper) in OPERS.iter().enumerate() {
         assert!(
-            oper.params < 3,
+            oper.params < 3 || oper.kind == OperKind::TernaryElse,
             "Oper '{}' at {} has {} parameters. \
                 Opers can have at most 2 parameters.",
             oper.name,
@@ -204,39 +323,34 @@ fn is_oper_table_valid() {
             idx
         );
         assert!(
-            oper.assoc != OperAssoc::Right || oper.params == 1,
-            "operator '{}' at {} must have only 1 parameter.",
+            oper.assoc != OperAssoc::Right || oper.params == 1 || (oper.params == 2 && oper.kind == OperKind::Regular),
+            "Right associative operator '{}' at {} must be a prefix or regular binary operator.",
             oper.name,
             idx
         );
 
-        assert_eq!(
-            oper.name.chars().all(|x| x.is_digit(10)),
-            false,
+        assert!(
+            !oper.name.chars().all(|x| x.is_ascii_digit()),
             "Oper '{}' invalid. Name cannot contain digits.",
             oper.name
         );
-        assert_eq!(
-            oper.name.chars().all(|x| x == '_'),
-            false,
+        assert!(
+            !oper.name.chars().all(|x| x == '_'),
             "Oper '{}' invalid. Name cannot contain '_' character.",
             oper.name
         );
-        assert_eq!(
-            oper.name.chars().all(|x| x == 'x'),
-            false,
+        assert!(
+            !oper.name.chars().all(|x| x == 'x'),
             "Oper '{}' invalid. Name cannot contain 'x' hexadecimal prefix character.",
             oper.name
         );
-        assert_eq!(
-            oper.name.chars().all(|x| x == 'n'),
-            false,
+        assert!(
+            !oper.name.chars().all(|x| x == 'n'),
             "Oper '{}' invalid. Name cannot contain 'n' binary prefix character.",
             oper.name
         );
-        assert_eq!(
-            oper.name.chars().all(|x| x == 'o'),
-            false,
+        assert!(
+            !oper.name.chars().all(|x| x == 'o'),
             "Oper '{}' invalid. Name cannot contain 'o' octal prefix character.",
             oper.name
         );
@@ -332,15 +446,14 @@ fn is_func_table_valid() {
             idx
         );
 
-        assert_eq!(func.name.is_empty(), false, "Function at {} invalid. Name cannot be 0 length.", idx);
-        assert_eq!(
-            func.name.chars().nth(0).unwrap().is_digit(10),
-            false,
+        assert!(!func.name.is_empty(), "Function at {} invalid. Name cannot be 0 length.", idx);
+        assert!(
+            !func.name.chars().next().unwrap().is_ascii_digit(),
             "Function '{}' invalid. Name cannot start with digits.",
             func.name
         );
         assert_ne!(
-            func.name.chars().nth(0).unwrap(),
+            func.name.chars().next().unwrap(),
             '_',
             "Function '{}' invalid. Name cannot start with an '_' character.",
             func.name
@@ -355,13 +468,51 @@ fn is_func_table_valid() {
     }
 }
 
+#[test]
+fn is_const_table_valid() {
+    for (idx, constant) in CONSTS.iter().enumerate() {
+        assert!(!constant.name.is_empty(), "Constant at {} invalid. Name cannot be 0 length.", idx);
+        assert!(
+            !constant.name.chars().next().unwrap().is_ascii_digit(),
+            "Constant '{}' invalid. Name cannot start with digits.",
+            constant.name
+        );
+        assert!(
+            constant.name.chars().all(|chr| chr.is_ascii_alphanumeric() || chr == '_'),
+            "Constant '{}' invalid. Name must be a valid identifier.",
+            constant.name
+        );
+
+        // Ensure constants don't shadow functions.
+        assert!(
+            !FUNCS.iter().any(|func| func.name == constant.name),
+            "Constant '{}' at {} has the same name as a function",
+            constant.name,
+            idx
+        );
+
+        // Ensure no duplicate CONSTS names.
+        for (idxcmp, constcmp) in CONSTS.iter().enumerate() {
+            if idxcmp != idx {
+                assert!(
+                    constant.name != constcmp.name,
+                    "Duplicate constant '{}' at {} and {}",
+                    constant.name,
+                    idx,
+                    idxcmp
+                );
+            }
+        }
+    }
+}
+
 #[inline(always)]
 fn test_valid_expr_but_eval_fail(str_expr: &str, expr_error_kind: ExprErrorKind) {
     // Parsing should succeed but evaluation must fail and match the specified error.
-    let res_parse = parse_expr(str_expr);
+    let res_parse = parse_expr(str_expr, &EvalContext::new());
     assert!(res_parse.is_ok(), "{} err={}", str_expr, res_parse.err().unwrap());
-    let mut expr_ctx = res_parse.unwrap();
-    let res_eval = evaluate_expr(&mut expr_ctx);
+    let expr_ctx = res_parse.unwrap();
+    let res_eval = evaluate_expr(&expr_ctx, &mut EvalContext::new());
     assert!(res_eval.is_err(), "{}", str_expr);
     assert_eq!(expr_error_kind, res_eval.err().unwrap().kind, "{}", str_expr);
 }
@@ -378,10 +529,26 @@ fn valid_exprs_eval_fail() {
         ("2/0", ExprErrorKind::FailedEvaluation),
         ("0xffffffffffffffff/0", ExprErrorKind::FailedEvaluation),
 
+        // Logical operators only skip the right-hand side when the result is already known.
+        ("1 && 1/0", ExprErrorKind::FailedEvaluation),
+        ("0 || 1/0", ExprErrorKind::FailedEvaluation),
+        ("1/0 && 0", ExprErrorKind::FailedEvaluation),
+        ("() && 1", ExprErrorKind::InvalidParamCount),
+
+        // Ternary conditional and if only evaluate the selected expression.
+        ("1 ? 1/0 : 2", ExprErrorKind::FailedEvaluation),
+        ("0 ? 2 : 1/0", ExprErrorKind::FailedEvaluation),
+        ("1/0 ? 1 : 2", ExprErrorKind::FailedEvaluation),
+        ("() ? 1 : 2", ExprErrorKind::InvalidParamCount),
+        ("if(1, 1/0, 2)", ExprErrorKind::FailedEvaluation),
+        ("if(0, 2, 1/0)", ExprErrorKind::FailedEvaluation),
+        ("if(1/0, 1, 2)", ExprErrorKind::FailedEvaluation),
+
         // Functions
         // bit
         ("bit(-1)", ExprErrorKind::FailedEvaluation),
-        ("bit(64)", ExprErrorKind::FailedEvaluation),
+        ("bit(128)", ExprErrorKind::FailedEvaluation),
+        ("bit(0x100000000)", ExprErrorKind::FailedEvaluation),
         ("bit(~0)", ExprErrorKind::FailedEvaluation),
         ("bit(0xffffffffffffffff)", ExprErrorKind::FailedEvaluation),
         ("bit(0x7fffffffffffffff)", ExprErrorKind::FailedEvaluation),
@@ -389,11 +556,11 @@ fn valid_exprs_eval_fail() {
         // bits
         ("bits(0,-1)", ExprErrorKind::FailedEvaluation),
         ("bits(-1,-1)", ExprErrorKind::FailedEvaluation),
-        ("bits(64,0)", ExprErrorKind::FailedEvaluation),
-        ("bits(0,64)", ExprErrorKind::FailedEvaluation),
+        ("bits(128,0)", ExprErrorKind::FailedEvaluation),
+        ("bits(0,128)", ExprErrorKind::FailedEvaluation),
         ("bits(~0,0)", ExprErrorKind::FailedEvaluation),
     ];
     for expr_res in expr_results {
-        test_valid_expr_but_eval_fail(&expr_res.0, expr_res.1);
+        test_valid_expr_but_eval_fail(expr_res.0, expr_res.1);
     }
 }
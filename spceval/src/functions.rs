@@ -1,302 +1,1197 @@
-use crate::{ExprError, ExprErrorKind, Number};
-use std::convert::TryFrom;
+use crate::constants::{GIB, KIB, MIB, PAGE_SIZE, PIB, TIB, constants};
+use crate::evaluator::is_ident;
+use crate::number::MAX_BIG_BITS;
+use crate::{ExprError, ExprErrorKind, IntType, Number};
+use num_bigint::{BigInt, Sign};
+use num_traits::{Signed, Zero};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::ops::Range;
-
-const KB: u64 = 0x400;
-const MB: u64 = 0x100000;
-const GB: u64 = 0x40000000;
-const TB: u64 = 0x10000000000;
-const PB: u64 = 4000000000000;
+use std::sync::Arc;
 
 pub const MAX_FN_PARAMS: u8 = u8::MAX;
 #[rustfmt::skip]
-pub static FUNCS: [Func<'static>; 22] = [
+pub static FUNCS: [Func<'static>; 87] = [
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "abs",
+        params:  Range { start: 1, end: 2 },
+        checked: true,
+        syntax:  "<n>",
+        help:    "Absolute value",
+        evalfn:  func_abs,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "acos",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Arc cosine (radians)",
+        evalfn:  func_acos,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "align_down",
+        params:  Range { start: 2, end: 3 },
+        checked: false,
+        syntax:  "<n>,<align>",
+        help:    "Align down to power of two",
+        evalfn:  func_align_down,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "align_up",
+        params:  Range { start: 2, end: 3 },
+        checked: false,
+        syntax:  "<n>,<align>",
+        help:    "Align up to power of two",
+        evalfn:  func_align_up,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "asin",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Arc sine (radians)",
+        evalfn:  func_asin,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "atan",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Arc tangent (radians)",
+        evalfn:  func_atan,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "atan2",
+        params:  Range { start: 2, end: 3 },
+        checked: false,
+        syntax:  "<y>,<x>",
+        help:    "Arc tangent of y/x (radians)",
+        evalfn:  func_atan2,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "avg",
+        params:  Range { start: 2, end: MAX_FN_PARAMS },
+        checked: true,
+        syntax:  "<n1>,<n2>[,<n3>...<nX>]",
+        help:    "Average",
+        evalfn:  func_avg,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "b2gb",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Bytes to gigabytes",
+        evalfn:  func_b2gb,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "b2kb",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Bytes to kilobytes",
+        evalfn:  func_b2kb,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "b2mb",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Bytes to megabytes",
+        evalfn:  func_b2mb,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "b2pb",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Bytes to petabytes",
+        evalfn:  func_b2pb,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "b2tb",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Bytes to terabytes",
+        evalfn:  func_b2tb,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bf16bits",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Bits of bfloat16 (rounded to nearest)",
+        evalfn:  func_bf16bits,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bit",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Set nth bit (n is [0..127])",
+        evalfn:  func_bit,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bitrev",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<width>]",
+        help:    "Reverse bits",
+        evalfn:  func_bitrev,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bits",
+        params:  Range { start: 2, end: 3 },
+        checked: false,
+        syntax:  "<n1>,<n2>",
+        help:    "Set bits from [n1..n2]",
+        evalfn:  func_bits,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bits2bf16",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Float of bfloat16 bits",
+        evalfn:  func_bits2bf16,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bits2f16",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Float of IEEE-754 half precision bits",
+        evalfn:  func_bits2f16,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bits2f32",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Float of IEEE-754 single precision bits",
+        evalfn:  func_bits2f32,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bits2f64",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Float of IEEE-754 double precision bits",
+        evalfn:  func_bits2f64,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bsf",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<width>]",
+        help:    "Index of lowest set bit",
+        evalfn:  func_bsf,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bsr",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<width>]",
+        help:    "Index of highest set bit",
+        evalfn:  func_bsr,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bswap16",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,16]",
+        help:    "Swap bytes of 16-bit integer",
+        evalfn:  func_bswap16,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bswap32",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,32]",
+        help:    "Swap bytes of 32-bit integer",
+        evalfn:  func_bswap32,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "bswap64",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,64]",
+        help:    "Swap bytes of 64-bit integer",
+        evalfn:  func_bswap64,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "cbrt",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Cube root",
+        evalfn:  func_cbrt,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "ceil",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Round float up",
+        evalfn:  func_ceil,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "cel2far",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n1>",
+        help:    "Celcius to fahrenheit",
+        evalfn:  func_cel2far,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "clamp",
+        params:  Range { start: 3, end: 4 },
+        checked: false,
+        syntax:  "<n>,<min>,<max>",
+        help:    "Clamp to range",
+        evalfn:  func_clamp,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "clz",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<width>]",
+        help:    "Count leading zero bits",
+        evalfn:  func_clz,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "cos",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Cosine (radians)",
+        evalfn:  func_cos,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "ctz",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<width>]",
+        help:    "Count trailing zero bits",
+        evalfn:  func_ctz,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "div_ceil",
+        params:  Range { start: 2, end: 3 },
+        checked: true,
+        syntax:  "<n>,<divisor>",
+        help:    "Divide rounding up",
+        evalfn:  func_div_ceil,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "div_round",
+        params:  Range { start: 2, end: 3 },
+        checked: true,
+        syntax:  "<n>,<divisor>",
+        help:    "Divide rounding to nearest (half away from zero)",
+        evalfn:  func_div_round,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "exp",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "e raised to n",
+        evalfn:  func_exp,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "extract",
+        params:  Range { start: 3, end: 4 },
+        checked: false,
+        syntax:  "<n>,<hi>,<lo>",
+        help:    "Extract bit field (hi, lo are within width of n)",
+        evalfn:  func_extract,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "f16bits",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Bits of IEEE-754 half precision float (rounded to nearest)",
+        evalfn:  func_f16bits,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "f32bits",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Bits of IEEE-754 single precision float (rounded to nearest)",
+        evalfn:  func_f32bits,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "f64bits",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Bits of IEEE-754 double precision float",
+        evalfn:  func_f64bits,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "far2cel",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n1>",
+        help:    "Fahrenheit to celcius",
+        evalfn:  func_far2cel,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "floor",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Round float down",
+        evalfn:  func_floor,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "fpow",
+        params:  Range { start: 2, end: 3 },
+        checked: false,
+        syntax:  "<n>,<exp>",
+        help:    "Float power",
+        evalfn:  func_fpow,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "gb2b",
+        params:  Range { start: 1, end: 2 },
+        checked: true,
+        syntax:  "<n>",
+        help:    "Gigabytes to bytes",
+        evalfn:  func_gb2b,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "gcd",
+        params:  Range { start: 2, end: MAX_FN_PARAMS },
+        checked: true,
+        syntax:  "<n1>,<n2>[,<n3>...<nX>]",
+        help:    "Greatest common divisor",
+        evalfn:  func_gcd,
+    },
+    Func {
+        kind:    FuncKind::Cond,
+        name:    "if",
+        params:  Range { start: 3, end: 4 },
+        checked: false,
+        syntax:  "<cond>,<n1>,<n2>",
+        help:    "<n1> if <cond> is true, otherwise <n2>",
+        evalfn:  func_if,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "ilog10",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Integer base 10 logarithm (rounded down)",
+        evalfn:  func_ilog10,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "ilog2",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Integer base 2 logarithm (rounded down)",
+        evalfn:  func_ilog2,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "insert",
+        params:  Range { start: 4, end: 5 },
+        checked: false,
+        syntax:  "<n>,<field>,<hi>,<lo>",
+        help:    "Insert bit field (hi, lo are within width of n)",
+        evalfn:  func_insert,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "is_aligned",
+        params:  Range { start: 2, end: 3 },
+        checked: false,
+        syntax:  "<n>,<align>",
+        help:    "Is aligned to power of two",
+        evalfn:  func_is_aligned,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "is_pow_of_two",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Is power of 2",
+        evalfn:  func_is_pow_of_two,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "isqrt",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Integer square root (rounded down)",
+        evalfn:  func_isqrt,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "kb2b",
+        params:  Range { start: 1, end: 2 },
+        checked: true,
+        syntax:  "<n>",
+        help:    "Kilobytes to bytes",
+        evalfn:  func_kb2b,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "lcm",
+        params:  Range { start: 2, end: MAX_FN_PARAMS },
+        checked: true,
+        syntax:  "<n1>,<n2>[,<n3>...<nX>]",
+        help:    "Least common multiple",
+        evalfn:  func_lcm,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "ln",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Natural logarithm",
+        evalfn:  func_ln,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "log",
+        params:  Range { start: 2, end: 3 },
+        checked: false,
+        syntax:  "<n>,<base>",
+        help:    "Logarithm",
+        evalfn:  func_log,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "log10",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Base 10 logarithm",
+        evalfn:  func_log10,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "log2",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Base 2 logarithm",
+        evalfn:  func_log2,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "lzcnt",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<width>]",
+        help:    "Count leading zero bits (same as clz)",
+        evalfn:  func_clz,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "max",
+        params:  Range { start: 2, end: MAX_FN_PARAMS },
+        checked: false,
+        syntax:  "<n1>,<n2>[,<n3>...<nX>]",
+        help:    "Maximum",
+        evalfn:  func_max,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "mb2b",
+        params:  Range { start: 1, end: 2 },
+        checked: true,
+        syntax:  "<n>",
+        help:    "Megabytes to bytes",
+        evalfn:  func_mb2b,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "mb2kb",
+        params:  Range { start: 1, end: 2 },
+        checked: true,
+        syntax:  "<n>",
+        help:    "Megabytes to kilobytes",
+        evalfn:  func_mb2kb,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "mb2gb",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Megabytes to gigabytes",
+        evalfn:  func_mb2gb,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "mb2tb",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Megabytes to terabytes",
+        evalfn:  func_mb2tb,
+    },
     Func {
-        name:   "avg",
-        params: Range { start: 2, end: MAX_FN_PARAMS },
-        syntax: "<n1>,<n2>[,<n3>...<nX>]",
-        help:   "Average",
-        evalfn: func_avg,
+        kind:    FuncKind::Regular,
+        name:    "mb2pb",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Megabytes to petabytes",
+        evalfn:  func_mb2pb,
     },
     Func {
-        name:   "b2gb",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Bytes to gigabytes",
-        evalfn: func_b2gb,
+        kind:    FuncKind::Regular,
+        name:    "min",
+        params:  Range { start: 2, end: MAX_FN_PARAMS },
+        checked: false,
+        syntax:  "<n1>,<n2>[,<n3>...<nX>]",
+        help:    "Minimum",
+        evalfn:  func_min,
     },
     Func {
-        name:   "b2kb",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Bytes to kilobytes",
-        evalfn: func_b2kb,
+        kind:    FuncKind::Regular,
+        name:    "page_base",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<page_size>]",
+        help:    "Base of page (page size defaults to 4 KiB)",
+        evalfn:  func_page_base,
     },
     Func {
-        name:   "b2mb",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Bytes to megabytes",
-        evalfn: func_b2mb,
+        kind:    FuncKind::Regular,
+        name:    "page_offset",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<page_size>]",
+        help:    "Offset in page (page size defaults to 4 KiB)",
+        evalfn:  func_page_offset,
     },
     Func {
-        name:   "b2pb",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Bytes to petabytes",
-        evalfn: func_b2pb,
+        kind:    FuncKind::Regular,
+        name:    "pages",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<size>[,<page_size>]",
+        help:    "Pages spanned by size (page size defaults to 4 KiB)",
+        evalfn:  func_pages,
     },
     Func {
-        name:   "b2tb",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Bytes to terabytes",
-        evalfn: func_b2tb,
+        kind:    FuncKind::Regular,
+        name:    "parity",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<width>]",
+        help:    "1 if odd number of bits set, else 0",
+        evalfn:  func_parity,
     },
     Func {
-        name:   "bit",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Set nth bit (n is [0..63])",
-        evalfn: func_bit,
+        kind:    FuncKind::Regular,
+        name:    "pb2b",
+        params:  Range { start: 1, end: 2 },
+        checked: true,
+        syntax:  "<n>",
+        help:    "Petabytes to bytes",
+        evalfn:  func_pb2b,
     },
     Func {
-        name:   "bits",
-        params: Range { start: 2, end: 3 },
-        syntax: "<n1>,<n2>",
-        help:   "Set bits from [n1..n2]",
-        evalfn: func_bits,
+        kind:    FuncKind::Regular,
+        name:    "pdep",
+        params:  Range { start: 2, end: 4 },
+        checked: false,
+        syntax:  "<n>,<mask>[,<width>]",
+        help:    "Deposit low bits at set bits of mask",
+        evalfn:  func_pdep,
     },
     Func {
-        name:   "cel2far",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n1>",
-        help:   "Celcius to fahrenheit",
-        evalfn: func_cel2far,
+        kind:    FuncKind::Regular,
+        name:    "pext",
+        params:  Range { start: 2, end: 4 },
+        checked: false,
+        syntax:  "<n>,<mask>[,<width>]",
+        help:    "Extract bits at set bits of mask",
+        evalfn:  func_pext,
     },
     Func {
-        name:   "far2cel",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n1>",
-        help:   "Fahrenheit to celcius",
-        evalfn: func_far2cel,
+        kind:    FuncKind::Regular,
+        name:    "popcnt",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<width>]",
+        help:    "Count set bits",
+        evalfn:  func_popcnt,
     },
     Func {
-        name:   "gb2b",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Gigabytes to bytes",
-        evalfn: func_gb2b,
+        kind:    FuncKind::Regular,
+        name:    "pow",
+        params:  Range { start: 2, end: 3 },
+        checked: false,
+        syntax:  "<n1>,<n2>",
+        help:    "Raise <n1> to power of <n2>",
+        evalfn:  func_pow,
     },
     Func {
-        name:   "is_pow_of_two",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Is power of 2",
-        evalfn: func_is_pow_of_two,
+        kind:    FuncKind::Regular,
+        name:    "rol",
+        params:  Range { start: 2, end: 4 },
+        checked: false,
+        syntax:  "<n>,<shift>[,<width>]",
+        help:    "Rotate left (width is 8, 16, 32, 64 or 128)",
+        evalfn:  func_rol,
     },
     Func {
-        name:   "kb2b",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Kilobytes to bytes",
-        evalfn: func_kb2b,
+        kind:    FuncKind::Regular,
+        name:    "ror",
+        params:  Range { start: 2, end: 4 },
+        checked: false,
+        syntax:  "<n>,<shift>[,<width>]",
+        help:    "Rotate right (width is 8, 16, 32, 64 or 128)",
+        evalfn:  func_ror,
     },
     Func {
-        name:   "mb2b",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Megabytes to bytes",
-        evalfn: func_mb2b,
+        kind:    FuncKind::Regular,
+        name:    "round",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Round float to nearest (half away from zero)",
+        evalfn:  func_round,
     },
     Func {
-        name:   "mb2kb",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Megabytes to kilobytes",
-        evalfn: func_mb2kb,
+        kind:    FuncKind::Regular,
+        name:    "sext",
+        params:  Range { start: 2, end: 3 },
+        checked: false,
+        syntax:  "<n>,<from_bits>",
+        help:    "Sign-extend from bits (from_bits is [1..128])",
+        evalfn:  func_sext,
     },
     Func {
-        name:   "mb2gb",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Megabytes to gigabytes",
-        evalfn: func_mb2gb,
+        kind:    FuncKind::Regular,
+        name:    "sin",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Sine (radians)",
+        evalfn:  func_sin,
     },
     Func {
-        name:   "mb2tb",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Megabytes to terabytes",
-        evalfn: func_mb2tb,
+        kind:    FuncKind::Regular,
+        name:    "sqrt",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Square root",
+        evalfn:  func_sqrt,
     },
     Func {
-        name:   "mb2pb",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Megabytes to petabytes",
-        evalfn: func_mb2pb,
+        kind:    FuncKind::Regular,
+        name:    "sum",
+        params:  Range { start: 2, end: MAX_FN_PARAMS },
+        checked: true,
+        syntax:  "<n1>,<n2>[,<n3>..<nX>]",
+        help:    "Sum",
+        evalfn:  func_sum,
     },
     Func {
-        name:   "pb2b",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Petabytes to bytes",
-        evalfn: func_pb2b,
+        kind:    FuncKind::Regular,
+        name:    "tan",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Tangent (radians)",
+        evalfn:  func_tan,
     },
     Func {
-        name:   "pow",
-        params: Range { start: 2, end: 3 },
-        syntax: "<n1>,<n2>",
-        help:   "Raise <n1> to power of <n2>",
-        evalfn: func_pow,
+        kind:    FuncKind::Regular,
+        name:    "tb2b",
+        params:  Range { start: 1, end: 2 },
+        checked: true,
+        syntax:  "<n>",
+        help:    "Terabytes to bytes",
+        evalfn:  func_tb2b,
     },
     Func {
-        name:   "sum",
-        params: Range { start: 2, end: MAX_FN_PARAMS },
-        syntax: "<n1>,<n2>[,<n3>..<nX>]",
-        help:   "Sum",
-        evalfn: func_sum,
+        kind:    FuncKind::Regular,
+        name:    "trunc",
+        params:  Range { start: 1, end: 2 },
+        checked: false,
+        syntax:  "<n>",
+        help:    "Round float towards zero",
+        evalfn:  func_trunc,
     },
     Func {
-        name:   "tb2b",
-        params: Range { start: 1, end: 2 },
-        syntax: "<n>",
-        help:   "Terabytes to bytes",
-        evalfn: func_tb2b,
+        kind:    FuncKind::Regular,
+        name:    "tzcnt",
+        params:  Range { start: 1, end: 3 },
+        checked: false,
+        syntax:  "<n>[,<width>]",
+        help:    "Count trailing zero bits (same as ctz)",
+        evalfn:  func_ctz,
+    },
+    Func {
+        kind:    FuncKind::Regular,
+        name:    "zext",
+        params:  Range { start: 2, end: 3 },
+        checked: false,
+        syntax:  "<n>,<from_bits>",
+        help:    "Zero-extend from bits (from_bits is [1..128])",
+        evalfn:  func_zext,
     },
 ];
 
 type PfnFunc = fn(func: &Func, idx_expr: usize, &[Number]) -> Result<Number, ExprError>;
 
+#[derive(Eq, PartialEq, Debug)]
+pub enum FuncKind {
+    Regular,
+    // Only the parameter selected by the first parameter is evaluated.
+    Cond,
+}
+
 pub struct Func<'a> {
+    pub kind: FuncKind,
     pub name: &'a str,
     pub params: Range<u8>,
+    // Whether the integer result is subject to the overflow policy (it wraps otherwise).
+    pub checked: bool,
     pub syntax: &'a str,
     pub help: &'a str,
     pub evalfn: PfnFunc,
 }
 
+// A function that applications embedding the library can make available to expressions, see
+// FuncRegistry. Parameters are passed in the integer mode of the evaluation context and the result is
+// converted to it.
+pub trait Function: Send + Sync {
+    fn name(&self) -> &str;
+    // Range of the number of parameters, e.g. 1..2 for exactly one parameter.
+    fn params(&self) -> Range<u8>;
+    fn syntax(&self) -> &str;
+    fn help(&self) -> &str;
+    // 'idx_expr' is the position of the function in the expression, for reporting errors.
+    fn evaluate(&self, idx_expr: usize, params: &[Number]) -> Result<Number, ExprError>;
+}
+
+impl Function for Func<'_> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn params(&self) -> Range<u8> {
+        self.params.clone()
+    }
+
+    fn syntax(&self) -> &str {
+        self.syntax
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn evaluate(&self, idx_expr: usize, params: &[Number]) -> Result<Number, ExprError> {
+        (self.evalfn)(self, idx_expr, params)
+    }
+}
+
+// Built-in functions that are part of the expression syntax rather than ordinary functions, i.e.
+// "if()" only evaluates the selected parameter and "bytes()" takes a string, so they can't be
+// replaced.
+static SYNTAX_FUNCS: [&str; 2] = ["if", "bytes"];
+
+// Functions that expressions can call. By default these are the built-in functions, to which
+// applications can add their own. Registered functions take precedence over built-in functions
+// of the same name, so any built-in function other than "if()" and "bytes()" can be replaced.
+#[derive(Clone)]
+pub struct FuncRegistry {
+    funcs: BTreeMap<String, Arc<dyn Function>>,
+    has_builtins: bool,
+}
+
+impl Default for FuncRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for FuncRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter().map(|func| func.name())).finish()
+    }
+}
+
+impl FuncRegistry {
+    pub fn new() -> Self {
+        Self { funcs: BTreeMap::new(), has_builtins: true }
+    }
+
+    pub fn without_builtins() -> Self {
+        Self { funcs: BTreeMap::new(), has_builtins: false }
+    }
+
+    // Functions must be named like variables and accept a non-empty range of parameter counts. They
+    // can't have the name of a constant or of "if()" and "bytes()", which are part of the syntax.
+    pub fn register<F: Function + 'static>(&mut self, func: F) -> Result<(), ExprError> {
+        let name = func.name();
+        let message = if !is_ident(name) {
+            format!("of function '{}' due to invalid name", name)
+        } else if constants().any(|(const_name, ..)| const_name == name) {
+            format!("of function '{}' due to constant with the same name", name)
+        } else if SYNTAX_FUNCS.contains(&name) {
+            format!("of built-in '{}' which can't be replaced", name)
+        } else if func.params().is_empty() {
+            format!("of function '{}' due to empty parameter range {:?}", name, func.params())
+        } else {
+            self.funcs.insert(name.to_string(), Arc::new(func));
+            return Ok(());
+        };
+        Err(ExprError::new(0, ExprErrorKind::InvalidDefinition, message))
+    }
+
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.funcs.remove(name).is_some()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.registered(name).is_some() || self.builtin(name).is_some()
+    }
+
+    pub(crate) fn registered(&self, name: &str) -> Option<&Arc<dyn Function>> {
+        self.funcs.get(name)
+    }
+
+    pub(crate) fn builtin(&self, name: &str) -> Option<&'static Func<'static>> {
+        // Todo: Sort and use binary search if function table grows.
+        FUNCS.iter().find(|func| self.has_builtins && func.name == name && !self.funcs.contains_key(name))
+    }
+
+    // Registered functions in order of their names, followed by the built-in functions they don't
+    // replace.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Function> {
+        let iter_builtins = FUNCS.iter().filter(|func| self.builtin(func.name).is_some());
+        self.funcs.values().map(|func| func.as_ref()).chain(iter_builtins.map(|func| func as &dyn Function))
+    }
+}
+
+// Integer-only functions (e.g. bit manipulation) can't be applied to floats.
+fn check_ints(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<(), ExprError> {
+    if nums.iter().any(Number::is_float) {
+        let message = format!("for function '{}' at {} due to float parameter", func.name, idx_expr);
+        Err(ExprError::new(idx_expr, ExprErrorKind::InvalidParamType, message))
+    } else {
+        Ok(())
+    }
+}
+
+// The type integers are operated on in by functions with an optional width parameter, e.g.
+// "rol(x, 1, 16)". Without a width it's the given type, otherwise the integers are converted to an
+// unsigned type of that width (or kept as the given type if it has that width).
+fn width_type(
+    func: &Func,
+    idx_expr: usize,
+    int_type: IntType,
+    opt_width: Option<&Number>,
+) -> Result<IntType, ExprError> {
+    let Some(width) = opt_width else {
+        return Ok(int_type);
+    };
+    match width.to_u32() {
+        Some(bits) if bits == int_type.bits() => Ok(int_type),
+        Some(bits @ (8 | 16 | 32 | 64 | 128)) => Ok(IntType::from_name(&format!("u{}", bits)).unwrap()),
+        _ => {
+            let message = format!(
+                "for function '{}' at {} due to invalid width {} (must be 8, 16, 32, 64 or 128)",
+                func.name,
+                idx_expr,
+                width.to_big()
+            );
+            Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+        }
+    }
+}
+
+// Integers wider than 128 bits (in arbitrary-precision mode) have no type to operate in, so a
+// width must be given to operate on their low bits.
+fn check_width(func: &Func, idx_expr: usize, ints: &[Number], opt_width: Option<&Number>) -> Result<(), ExprError> {
+    if opt_width.is_some() || !ints.iter().any(Number::is_truncated) {
+        Ok(())
+    } else {
+        let message =
+            format!("for function '{}' at {} due to integer wider than 128 bits without a width", func.name, idx_expr);
+        Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+    }
+}
+
+// The first parameter of a function taking an integer and an optional width (e.g. "popcnt(x, 8)"),
+// truncated to the width.
+fn width_int(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<(u128, IntType), ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    check_width(func, idx_expr, &nums[..1], nums.get(1))?;
+    let int_type = width_type(func, idx_expr, nums[0].int_type(), nums.get(1))?;
+    Ok((int_type.wrap(nums[0].extended()), int_type))
+}
+
+// The type integer parameters are converted to, like the operands of binary operators.
+fn common_type(nums: &[Number]) -> IntType {
+    nums.iter().fold(nums[0].int_type(), |int_type, arg| int_type.common(arg.int_type()))
+}
+
+// The exact integers of the parameters after converting them to their common type, unless they're
+// arbitrary-precision integers.
+fn common_bigs(nums: &[Number]) -> Vec<BigInt> {
+    if nums.iter().any(|arg| arg.as_big().is_some()) {
+        nums.iter().map(Number::to_big).collect()
+    } else {
+        let int_type = common_type(nums);
+        nums.iter().map(|arg| arg.cast(int_type).to_big()).collect()
+    }
+}
+
+// The exact result of an integer function wrapped to the common type of its parameters (see
+// common_bigs()), unless they're arbitrary-precision integers.
+fn int_res(nums: &[Number], big: BigInt) -> Number {
+    if nums.iter().any(|arg| arg.as_big().is_some()) {
+        Number::from_big(big)
+    } else {
+        Number::from_big(big).cast(common_type(nums))
+    }
+}
+
+// The sum is a float if any parameter is a float, otherwise an integer.
 fn func_sum__(nums: &[Number]) -> Result<Number, ExprError> {
-    let mut res = Number { integer: 0u64, float: 0f64 };
-    for arg in nums {
-        res.integer = res.integer.wrapping_add(arg.integer);
-        res.float += arg.float;
+    if nums.iter().any(Number::is_float) {
+        return Ok(Number::from_f64(nums.iter().map(|arg| arg.to_f64()).sum()));
+    }
+    if nums.iter().any(|arg| arg.as_big().is_some()) {
+        return Ok(Number::from_big(nums.iter().map(Number::to_big).sum()));
     }
-    Ok(res)
+    let int_type = common_type(nums);
+    let integer = nums.iter().fold(0u128, |integer, arg| integer.wrapping_add(int_type.wrap(arg.extended())));
+    Ok(Number::from_int(integer, int_type))
 }
 
 fn func_sum(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
     func_sum__(nums)
 }
 
+// The average of integers always fits their common type, so it's computed from the exact sum
+// rather than a sum that may have wrapped (e.g. "avg(I64_MAX, I64_MAX)" is "I64_MAX").
 fn func_avg(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let mut res = func_sum__(nums)?;
-    res.integer /= nums.len() as u64;
-    res.float /= nums.len() as f64;
-    Ok(res)
+    if nums.iter().any(Number::is_float) {
+        return Ok(Number::from_f64(nums.iter().map(|arg| arg.to_f64()).sum::<f64>() / nums.len() as f64));
+    }
+    let sum: BigInt = common_bigs(nums).into_iter().sum();
+    Ok(int_res(nums, sum / nums.len()))
+}
+
+// Size conversions keep the type of the size being converted, unless converting to a larger unit
+// leaves a fraction, which is kept as a float like a fractional size suffix (e.g. "b2kb(1536)" is
+// 1.5 like "1.5K" is 1536).
+fn size_mul(num: &Number, unit: u128) -> Number {
+    match num {
+        Number::Float(float) => Number::from_f64(float * unit as f64),
+        Number::Int { big: Some(big), .. } => Number::from_big(big * unit),
+        _ => {
+            let (integer, int_type) = num.int();
+            Number::from_int(integer.wrapping_mul(unit), int_type)
+        }
+    }
+}
+
+fn size_div(num: &Number, unit: u128) -> Number {
+    match num {
+        Number::Float(float) => Number::from_f64(float / unit as f64),
+        _ if !(num.to_big() % unit).is_zero() => Number::from_f64(num.to_f64() / unit as f64),
+        Number::Int { big: Some(big), .. } => Number::from_big(big / unit),
+        _ => {
+            let (integer, int_type) = num.int();
+            let integer = if int_type.is_signed() {
+                (int_type.extend(integer) as i128 / unit as i128) as u128
+            } else {
+                integer / unit
+            };
+            Number::from_int(integer, int_type)
+        }
+    }
 }
 
 fn func_b2kb(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_div(KB);
-    let float = nums[0].float / KB as f64;
-    Ok(Number { integer, float })
+    Ok(size_div(&nums[0], KIB))
 }
 
 fn func_kb2b(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_mul(KB);
-    let float = nums[0].float * KB as f64;
-    Ok(Number { integer, float })
+    Ok(size_mul(&nums[0], KIB))
 }
 
 fn func_b2mb(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_div(MB);
-    let float = nums[0].float / MB as f64;
-    Ok(Number { integer, float })
+    Ok(size_div(&nums[0], MIB))
 }
 
 fn func_mb2b(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_mul(MB);
-    let float = nums[0].float * MB as f64;
-    Ok(Number { integer, float })
+    Ok(size_mul(&nums[0], MIB))
 }
 
 fn func_mb2kb(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_mul(KB);
-    let float = nums[0].float * MB as f64;
-    Ok(Number { integer, float })
+    Ok(size_mul(&nums[0], KIB))
 }
 
 fn func_mb2gb(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_div(KB);
-    let float = nums[0].float / KB as f64;
-    Ok(Number { integer, float })
+    Ok(size_div(&nums[0], KIB))
 }
 
 fn func_mb2tb(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_div(MB);
-    let float = nums[0].float / MB as f64;
-    Ok(Number { integer, float })
+    Ok(size_div(&nums[0], MIB))
 }
 
 fn func_mb2pb(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_div(GB);
-    let float = nums[0].float / GB as f64;
-    Ok(Number { integer, float })
+    Ok(size_div(&nums[0], GIB))
 }
 
 fn func_b2gb(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_div(GB);
-    let float = nums[0].float / GB as f64;
-    Ok(Number { integer, float })
+    Ok(size_div(&nums[0], GIB))
 }
 
 fn func_gb2b(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_mul(GB);
-    let float = nums[0].float * GB as f64;
-    Ok(Number { integer, float })
+    Ok(size_mul(&nums[0], GIB))
 }
 
 fn func_b2tb(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_div(TB);
-    let float = nums[0].float / TB as f64;
-    Ok(Number { integer, float })
+    Ok(size_div(&nums[0], TIB))
 }
 
 fn func_tb2b(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_mul(TB);
-    let float = nums[0].float * TB as f64;
-    Ok(Number { integer, float })
+    Ok(size_mul(&nums[0], TIB))
 }
 
 fn func_b2pb(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_div(PB);
-    let float = nums[0].float / PB as f64;
-    Ok(Number { integer, float })
+    Ok(size_div(&nums[0], PIB))
 }
 
 fn func_pb2b(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let integer = nums[0].integer.wrapping_mul(PB);
-    let float = nums[0].float * PB as f64;
-    Ok(Number { integer, float })
+    Ok(size_mul(&nums[0], PIB))
 }
 
+// Unlike "**", powers of i64 integers are widened like literals (e.g. "pow(2, 63)" is u64, "pow(2, 64)"
+// and "pow(-2, 64)" are i128), so they're computed in a 128-bit type and narrowed back.
 fn func_pow(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    if u32::try_from(nums[1].integer).is_ok() {
-        match u64::checked_pow(nums[0].integer, nums[1].integer as u32) {
-            Some(integer) => Ok(Number { integer, float: integer as f64 }),
-            None => {
+    check_ints(func, idx_expr, nums)?;
+    let what = format!("function '{}'", func.name);
+    if nums[0].int_type() != IntType::I64 || nums[0].as_big().is_some() || nums[1].as_big().is_some() {
+        return pow_ints(&what, idx_expr, &nums[0], &nums[1]);
+    }
+    let int_type = if nums[0].is_negative() {
+        IntType::I128
+    } else {
+        IntType::U128
+    };
+    let number = pow_ints(&what, idx_expr, &nums[0].cast(int_type), &nums[1])?;
+    let integer = number.integer();
+    if !number.is_negative() {
+        Ok(Number::from_u128(integer))
+    } else if IntType::I64.extend(IntType::I64.wrap(integer)) == integer {
+        Ok(Number::from_int(integer, IntType::I64))
+    } else {
+        Ok(number)
+    }
+}
+
+// Integer power of "pow()" and the "**" operator, which fails rather than wraps on overflow. The
+// caller is described by 'what' in error messages.
+pub(crate) fn pow_ints(what: &str, idx_expr: usize, base: &Number, num_exp: &Number) -> Result<Number, ExprError> {
+    if let Some(exp) = num_exp.to_u32() {
+        if base.as_big().is_some() || num_exp.as_big().is_some() {
+            // The result has at most 'exp' times the bits of the base, which must be bounded.
+            let base_big = base.to_big();
+            return if base_big.bits().saturating_sub(1) * exp as u64 <= MAX_BIG_BITS {
+                Ok(Number::from_big(base_big.pow(exp)))
+            } else {
                 let message =
-                    format!("for function '{}', {} power {} overflowed", func.name, nums[0].integer, nums[1].integer);
+                    format!("for {}, {} power {} overflowed (must be <= {} bits)", what, base_big, exp, MAX_BIG_BITS);
+                Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+            };
+        }
+        // The result keeps the type of the base, like the result of "<<" keeps the type of the
+        // shifted integer, and must fit in it (e.g. "2 ** 63" and "(i8)2 ** 7" overflow).
+        let int_type = base.int_type();
+        let opt_number = if base.is_negative() {
+            (base.extended() as i128).checked_pow(exp).map(|integer| integer as u128)
+        } else {
+            base.integer().checked_pow(exp)
+        }
+        .filter(|&integer| int_type.extend(int_type.wrap(integer)) == integer)
+        .map(|integer| Number::from_int(integer, int_type));
+        match opt_number {
+            Some(number) => Ok(number),
+            None => {
+                let message = format!("for {}, {} power {} overflowed {}", what, base, num_exp, int_type);
                 Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
             }
         }
     } else {
         let message = format!(
-            "for function '{}', {} power {}, exponent overflowed (must be <= {})",
-            func.name,
-            nums[0].integer,
-            nums[1].integer,
+            "for {}, {} power {}, exponent overflowed (must be <= {})",
+            what,
+            base.to_big(),
+            num_exp.to_big(),
             u32::MAX
         );
         Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
@@ -304,58 +1199,729 @@ fn func_pow(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, Exp
 }
 
 fn func_bit(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let shift = nums[0].integer as u32;
-    if (0..u64::BITS).contains(&shift) {
-        let integer = 1_u64.wrapping_shl(nums[0].integer as u32);
-        let float = integer as f64;
-        Ok(Number { integer, float })
+    check_ints(func, idx_expr, nums)?;
+    let shift = nums[0].to_u32().unwrap_or(u32::MAX);
+    if (0..u128::BITS).contains(&shift) {
+        let integer = 1_u128.wrapping_shl(shift);
+        Ok(Number::from_u128(integer))
+    } else {
+        let message = format!(
+            "for function '{}' at {} due to invalid shift {} (must be 0..127)",
+            func.name,
+            idx_expr,
+            nums[0].to_big()
+        );
+        Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+    }
+}
+
+// The lowest and highest bit of a range given by its first and last bit in either order, which
+// must both be within the given number of bits.
+fn bit_range(func: &Func, idx_expr: usize, first: &Number, last: &Number, bits: u32) -> Result<(u32, u32), ExprError> {
+    let first_bit = first.to_u32().unwrap_or(u32::MAX);
+    let last_bit = last.to_u32().unwrap_or(u32::MAX);
+    let min = std::cmp::min(first_bit, last_bit);
+    let max = std::cmp::max(first_bit, last_bit);
+    if max < bits {
+        Ok((min, max))
     } else {
         let message = format!(
-            "for function '{}' at {} due to invalid shift {} (must be 0..63)",
-            func.name, idx_expr, nums[0].integer as i64
+            "for function '{}' at {} due to invalid bit range ({}, {}) (must be 0..{})",
+            func.name,
+            idx_expr,
+            first.to_big(),
+            last.to_big(),
+            bits - 1
         );
         Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
     }
 }
 
+// The mask of the bits from min to max, both inclusive.
+fn bit_mask(min: u32, max: u32) -> u128 {
+    (u128::MAX >> (u128::BITS - 1 - (max - min))) << min
+}
+
 fn func_bits(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let min = std::cmp::min(nums[0].integer, nums[1].integer) as u32;
-    let max = std::cmp::max(nums[0].integer, nums[1].integer) as u32;
-    if (0..u64::BITS).contains(&min) && (0..u64::BITS).contains(&max) {
-        let mut integer: u64 = 0;
-        for n in min..max + 1 {
-            integer |= 1_u64.wrapping_shl(n);
+    check_ints(func, idx_expr, nums)?;
+    let (min, max) = bit_range(func, idx_expr, &nums[0], &nums[1], u128::BITS)?;
+    Ok(Number::from_u128(bit_mask(min, max)))
+}
+
+// Bit fields keep the type of the integer they're extracted from or inserted into, and must lie
+// within its width, e.g. "extract((u16)x, 15, 8)" is the high byte but "extract((u8)x, 15, 8)"
+// fails.
+fn func_extract(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let (min, max) = bit_range(func, idx_expr, &nums[1], &nums[2], nums[0].int_type().bits())?;
+    let integer = (nums[0].extended() & bit_mask(min, max)) >> min;
+    Ok(Number::from_int(integer, nums[0].int_type()))
+}
+
+// Fields that don't fit in the bit range are truncated.
+fn func_insert(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let (min, max) = bit_range(func, idx_expr, &nums[2], &nums[3], nums[0].int_type().bits())?;
+    let mask = bit_mask(min, max);
+    let integer = (nums[0].extended() & !mask) | ((nums[1].extended() << min) & mask);
+    Ok(Number::from_int(integer, nums[0].int_type()))
+}
+
+// The number of low bits of an integer that sext and zext extend from.
+fn from_bits(func: &Func, idx_expr: usize, num: &Number) -> Result<u32, ExprError> {
+    match num.to_u32() {
+        Some(bits @ 1..=u128::BITS) => Ok(bits),
+        _ => {
+            let message = format!(
+                "for function '{}' at {} due to invalid bit count {} (must be 1..128)",
+                func.name,
+                idx_expr,
+                num.to_big()
+            );
+            Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
         }
-        let float = integer as f64;
-        Ok(Number { integer, float })
+    }
+}
+
+// The width integers are extended to, which is at least 64 bits so that narrow integers have room
+// to be extended (e.g. "sext((u8)0x80, 8)" is -128) and 128 bits when extending from more bits.
+fn ext_width(int_type: IntType, bits: u32) -> u32 {
+    if int_type.bits() == u128::BITS || bits > u64::BITS {
+        u128::BITS
     } else {
+        u64::BITS
+    }
+}
+
+// Extends the low bits of an integer to a signed integer, e.g. "sext(0x800000000000, 48)" is the
+// canonical address 0xffff800000000000.
+fn func_sext(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let bits = from_bits(func, idx_expr, &nums[1])?;
+    let int_type = if ext_width(nums[0].int_type(), bits) == u128::BITS {
+        IntType::I128
+    } else {
+        IntType::I64
+    };
+    let shift = u128::BITS - bits;
+    let integer = (((nums[0].extended() << shift) as i128) >> shift) as u128;
+    Ok(Number::from_int(integer, int_type))
+}
+
+// Extends the low bits of an integer to an unsigned integer, e.g. "zext((i8)-1, 16)" is 0xffff.
+fn func_zext(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let bits = from_bits(func, idx_expr, &nums[1])?;
+    let int_type = if ext_width(nums[0].int_type(), bits) == u128::BITS {
+        IntType::U128
+    } else {
+        IntType::U64
+    };
+    let shift = u128::BITS - bits;
+    Ok(Number::from_int((nums[0].extended() << shift) >> shift, int_type))
+}
+
+fn func_rol(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    check_width(func, idx_expr, &nums[..1], nums.get(2))?;
+    let int_type = width_type(func, idx_expr, nums[0].int_type(), nums.get(2))?;
+    Ok(Number::from_int(int_type.rotate_left(nums[0].extended(), nums[1].extended()), int_type))
+}
+
+fn func_ror(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    check_width(func, idx_expr, &nums[..1], nums.get(2))?;
+    let int_type = width_type(func, idx_expr, nums[0].int_type(), nums.get(2))?;
+    Ok(Number::from_int(int_type.rotate_left(nums[0].extended(), nums[1].extended().wrapping_neg()), int_type))
+}
+
+fn func_popcnt(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let (integer, _) = width_int(func, idx_expr, nums)?;
+    Ok(Number::from_u128(integer.count_ones() as u128))
+}
+
+fn func_parity(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let (integer, _) = width_int(func, idx_expr, nums)?;
+    Ok(Number::from_u128(integer.count_ones() as u128 & 1))
+}
+
+// Counts of leading and trailing zeros are the width of the type for 0, like lzcnt and tzcnt.
+fn func_clz(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let (integer, int_type) = width_int(func, idx_expr, nums)?;
+    Ok(Number::from_u128((integer.leading_zeros() - (u128::BITS - int_type.bits())) as u128))
+}
+
+fn func_ctz(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let (integer, int_type) = width_int(func, idx_expr, nums)?;
+    Ok(Number::from_u128(integer.trailing_zeros().min(int_type.bits()) as u128))
+}
+
+// Bit scans of 0 are undefined (like bsf and bsr), so they fail rather than return some index.
+fn scan_int(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<u128, ExprError> {
+    let (integer, _) = width_int(func, idx_expr, nums)?;
+    if integer != 0 {
+        Ok(integer)
+    } else {
+        let message = format!("for function '{}' at {} due to no bits being set", func.name, idx_expr);
+        Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+    }
+}
+
+fn func_bsf(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let integer = scan_int(func, idx_expr, nums)?;
+    Ok(Number::from_u128(integer.trailing_zeros() as u128))
+}
+
+fn func_bsr(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let integer = scan_int(func, idx_expr, nums)?;
+    Ok(Number::from_u128((u128::BITS - 1 - integer.leading_zeros()) as u128))
+}
+
+fn func_bitrev(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let (integer, int_type) = width_int(func, idx_expr, nums)?;
+    Ok(Number::from_int(integer.reverse_bits() >> (u128::BITS - int_type.bits()), int_type))
+}
+
+// Byte swaps operate on the width in their name, so the optional width like other bit functions
+// take must be that width (e.g. "bswap16(x, 16)"). The swapped bytes are a bit pattern, so the
+// result is always unsigned (e.g. "bswap16(-1)" is "(u16)0xffff").
+fn bswap(func: &Func, idx_expr: usize, nums: &[Number], int_type: IntType) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    if let Some(width) = nums.get(1).filter(|width| width.to_u32() != Some(int_type.bits())) {
         let message = format!(
-            "for function '{}' at {} due to invalid bit range ({}, {}) (must be 0..63)",
-            func.name, idx_expr, nums[0].integer as i64, nums[1].integer as i64
+            "for function '{}' at {} due to invalid width {} (must be {})",
+            func.name,
+            idx_expr,
+            width.to_big(),
+            int_type.bits()
         );
+        return Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message));
+    }
+    let integer = int_type.wrap(nums[0].extended());
+    Ok(Number::from_int(integer.swap_bytes() >> (u128::BITS - int_type.bits()), int_type))
+}
+
+fn func_bswap16(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    bswap(func, idx_expr, nums, IntType::U16)
+}
+
+fn func_bswap32(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    bswap(func, idx_expr, nums, IntType::U32)
+}
+
+fn func_bswap64(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    bswap(func, idx_expr, nums, IntType::U64)
+}
+
+// Without a width, pdep and pext operate in the common type of the integer and the mask.
+fn pdep_pext_ints(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<(u128, u128, IntType), ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    check_width(func, idx_expr, &nums[..2], nums.get(2))?;
+    let int_type = width_type(func, idx_expr, nums[0].int_type().common(nums[1].int_type()), nums.get(2))?;
+    Ok((int_type.wrap(nums[0].extended()), int_type.wrap(nums[1].extended()), int_type))
+}
+
+// Deposits the low bits of the integer at the positions of the set bits of the mask, lowest first.
+fn func_pdep(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let (mut integer, mut mask, int_type) = pdep_pext_ints(func, idx_expr, nums)?;
+    let mut result = 0;
+    while mask != 0 {
+        let lowest = mask & mask.wrapping_neg();
+        if integer & 1 != 0 {
+            result |= lowest;
+        }
+        integer >>= 1;
+        mask &= !lowest;
+    }
+    Ok(Number::from_int(result, int_type))
+}
+
+// Extracts the bits of the integer at the positions of the set bits of the mask into the low bits.
+fn func_pext(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let (integer, mut mask, int_type) = pdep_pext_ints(func, idx_expr, nums)?;
+    let mut result = 0;
+    let mut bit = 1;
+    while mask != 0 {
+        let lowest = mask & mask.wrapping_neg();
+        if integer & lowest != 0 {
+            result |= bit;
+        }
+        bit <<= 1;
+        mask &= !lowest;
+    }
+    Ok(Number::from_int(result, int_type))
+}
+
+// Integers are compared after converting them to their common type, like the comparison operators
+// compare their operands, e.g. "min(-1, (u64)0)" is 0.
+fn min_max(nums: &[Number], ordering: Ordering) -> Number {
+    if nums.iter().any(Number::is_float) {
+        let floats = nums.iter().map(|arg| arg.to_f64());
+        let float = if ordering == Ordering::Less {
+            floats.fold(f64::INFINITY, f64::min)
+        } else {
+            floats.fold(f64::NEG_INFINITY, f64::max)
+        };
+        return Number::from_f64(float);
+    }
+    let big = common_bigs(nums).into_iter().reduce(|acc, big| if big.cmp(&acc) == ordering { big } else { acc });
+    int_res(nums, big.unwrap())
+}
+
+fn func_min(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(min_max(nums, Ordering::Less))
+}
+
+fn func_max(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(min_max(nums, Ordering::Greater))
+}
+
+fn func_clamp(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let opt_res = if nums.iter().any(Number::is_float) {
+        let (min, max) = (nums[1].to_f64(), nums[2].to_f64());
+        (min <= max).then(|| Number::from_f64(nums[0].to_f64().max(min).min(max)))
+    } else {
+        let [big, min, max] = <[BigInt; 3]>::try_from(common_bigs(nums)).unwrap();
+        (min <= max).then(|| int_res(nums, big.clamp(min, max)))
+    };
+    opt_res.ok_or_else(|| {
+        let message = format!("for function '{}' at {} due to minimum greater than maximum", func.name, idx_expr);
+        ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message)
+    })
+}
+
+fn func_abs(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    if nums[0].is_float() {
+        Ok(Number::from_f64(nums[0].to_f64().abs()))
+    } else {
+        Ok(int_res(nums, nums[0].to_big().abs()))
+    }
+}
+
+fn gcd(lhs: BigInt, rhs: BigInt) -> BigInt {
+    let (mut lhs, mut rhs) = (lhs.abs(), rhs.abs());
+    while !rhs.is_zero() {
+        let rem = &lhs % &rhs;
+        lhs = rhs;
+        rhs = rem;
+    }
+    lhs
+}
+
+fn func_gcd(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let big = common_bigs(nums).into_iter().reduce(gcd);
+    Ok(int_res(nums, big.unwrap()))
+}
+
+fn func_lcm(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let big = common_bigs(nums).into_iter().reduce(|lhs, rhs| {
+        if lhs.is_zero() || rhs.is_zero() {
+            BigInt::zero()
+        } else {
+            (&lhs * &rhs).abs() / gcd(lhs, rhs)
+        }
+    });
+    Ok(int_res(nums, big.unwrap()))
+}
+
+fn div_by_zero(func: &Func, idx_expr: usize) -> ExprError {
+    let message = format!("for function '{}' at {} due to division by 0", func.name, idx_expr);
+    ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message)
+}
+
+fn div_float(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<f64, ExprError> {
+    if nums[1].to_f64() != 0.0 {
+        Ok(nums[0].to_f64() / nums[1].to_f64())
+    } else {
+        Err(div_by_zero(func, idx_expr))
+    }
+}
+
+// The truncated quotient, remainder and divisor of dividing the exact integers of the parameters
+// (see common_bigs()), for divisions that round the quotient differently.
+fn div_rem(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<(BigInt, BigInt, BigInt), ExprError> {
+    let [dividend, divisor] = <[BigInt; 2]>::try_from(common_bigs(nums)).unwrap();
+    if divisor.is_zero() {
+        return Err(div_by_zero(func, idx_expr));
+    }
+    Ok((&dividend / &divisor, &dividend % &divisor, divisor))
+}
+
+// Truncating already rounds negative quotients up, so only positive quotients with a remainder are
+// adjusted. E.g. the number of 64-byte cache lines spanned by a size is "div_ceil(size, 64)".
+fn func_div_ceil(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    if nums.iter().any(Number::is_float) {
+        return Ok(Number::from_f64(div_float(func, idx_expr, nums)?.ceil()));
+    }
+    let (quot, rem, divisor) = div_rem(func, idx_expr, nums)?;
+    let quot = if !rem.is_zero() && rem.sign() == divisor.sign() {
+        quot + 1
+    } else {
+        quot
+    };
+    Ok(int_res(nums, quot))
+}
+
+fn func_div_round(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    if nums.iter().any(Number::is_float) {
+        return Ok(Number::from_f64(div_float(func, idx_expr, nums)?.round()));
+    }
+    let (quot, rem, divisor) = div_rem(func, idx_expr, nums)?;
+    let quot = if (&rem * 2u32).abs() < divisor.abs() {
+        quot
+    } else if rem.sign() == divisor.sign() {
+        quot + 1
+    } else {
+        quot - 1
+    };
+    Ok(int_res(nums, quot))
+}
+
+fn func_isqrt(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let big = nums[0].to_big();
+    if big.is_negative() {
+        let message = format!("for function '{}' at {} due to negative parameter", func.name, idx_expr);
+        return Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message));
+    }
+    Ok(int_res(nums, big.sqrt()))
+}
+
+fn log_int(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<BigInt, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let big = nums[0].to_big();
+    if big.is_positive() {
+        Ok(big)
+    } else {
+        let message = format!("for function '{}' at {} due to parameter not being positive", func.name, idx_expr);
         Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
     }
 }
 
+fn func_ilog2(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let big = log_int(func, idx_expr, nums)?;
+    Ok(Number::from_u128((big.bits() - 1) as u128))
+}
+
+fn func_ilog10(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let big = log_int(func, idx_expr, nums)?;
+    Ok(Number::from_u128((big.to_string().len() - 1) as u128))
+}
+
 fn func_cel2far(_func: &Func, _idx_expr: usize, num: &[Number]) -> Result<Number, ExprError> {
-    let float = num[0].float * (9.0 / 5.0) + 32.0;
-    let integer = float as u64;
-    Ok(Number { integer, float })
+    Ok(Number::from_f64(num[0].to_f64() * (9.0 / 5.0) + 32.0))
 }
 
 fn func_far2cel(_func: &Func, _idx_expr: usize, num: &[Number]) -> Result<Number, ExprError> {
-    let float = (num[0].float - 32.0) / (9.0 / 5.0);
-    let integer = float as u64;
-    Ok(Number { integer, float })
+    Ok(Number::from_f64((num[0].to_f64() - 32.0) / (9.0 / 5.0)))
+}
+
+fn is_pow_of_two(num: &Number) -> bool {
+    match num {
+        Number::Int { big: Some(big), .. } => big.sign() == Sign::Plus && big.magnitude().count_ones() == 1,
+        _ => !num.is_negative() && num.integer().is_power_of_two(),
+    }
+}
+
+fn func_is_pow_of_two(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    Ok(Number::from_bool(is_pow_of_two(&nums[0])))
+}
+
+// Splits an integer into the multiple of a power-of-two alignment at or below it and the offset from
+// that multiple, e.g. 0x1234 aligned to 0x1000 is 0x1000 and 0x234. Both are in the common type of
+// the integer and the alignment.
+fn align_split(func: &Func, idx_expr: usize, num: &Number, align: &Number) -> Result<(Number, Number), ExprError> {
+    if !is_pow_of_two(align) {
+        let message = format!(
+            "for function '{}' at {} due to alignment {} not being a power of two",
+            func.name,
+            idx_expr,
+            align.to_big()
+        );
+        return Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message));
+    }
+    if num.as_big().is_some() || align.as_big().is_some() {
+        let (big, big_align) = (num.to_big(), align.to_big());
+        let offset = ((&big % &big_align) + &big_align) % &big_align;
+        Ok((Number::from_big(big - &offset), Number::from_big(offset)))
+    } else {
+        let int_type = num.int_type().common(align.int_type());
+        let integer = int_type.wrap(num.extended());
+        let mask = int_type.wrap(align.extended()) - 1;
+        Ok((Number::from_int(integer & !mask, int_type), Number::from_int(integer & mask, int_type)))
+    }
+}
+
+fn func_align_down(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let (base, _) = align_split(func, idx_expr, &nums[0], &nums[1])?;
+    Ok(base)
+}
+
+fn func_align_up(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let (base, offset) = align_split(func, idx_expr, &nums[0], &nums[1])?;
+    if offset.is_zero() {
+        Ok(base)
+    } else if base.as_big().is_some() {
+        Ok(Number::from_big(base.to_big() + nums[1].to_big()))
+    } else {
+        // Like "**", rounding up past the largest integer of the type fails regardless of the
+        // overflow policy, as neither a wrapped nor a saturated result would be aligned.
+        let number = Number::from_int(base.integer().wrapping_add(nums[1].extended()), base.int_type());
+        if number.to_big() == base.to_big() + nums[1].to_big() {
+            Ok(number)
+        } else {
+            let message = format!(
+                "for function '{}' at {}, {} aligned up overflowed {}",
+                func.name,
+                idx_expr,
+                nums[0],
+                base.int_type()
+            );
+            Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+        }
+    }
+}
+
+fn func_is_aligned(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let (_, offset) = align_split(func, idx_expr, &nums[0], &nums[1])?;
+    Ok(Number::from_bool(offset.is_zero()))
+}
+
+// The page size of page functions, 4 KiB unless given.
+fn page_size(nums: &[Number]) -> Number {
+    nums.get(1).cloned().unwrap_or(Number::from_int(PAGE_SIZE, IntType::I64))
+}
+
+fn func_page_base(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let (base, _) = align_split(func, idx_expr, &nums[0], &page_size(nums))?;
+    Ok(base)
+}
+
+fn func_page_offset(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let (_, offset) = align_split(func, idx_expr, &nums[0], &page_size(nums))?;
+    Ok(offset)
+}
+
+// The number of pages a size spans, i.e. the size divided by the page size rounded up. Unlike
+// aligning the size up first, this can't overflow.
+fn func_pages(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    let page_size = page_size(nums);
+    let (base, offset) = align_split(func, idx_expr, &nums[0], &page_size)?;
+    let pages = if base.as_big().is_some() {
+        Number::from_big(base.to_big() / page_size.to_big())
+    } else {
+        size_div(&base, base.int_type().wrap(page_size.extended()))
+    };
+    if offset.is_zero() {
+        Ok(pages)
+    } else if let Some(big) = pages.as_big() {
+        Ok(Number::from_big(big + 1))
+    } else {
+        Ok(Number::from_int(pages.integer().wrapping_add(1), pages.int_type()))
+    }
+}
+
+// Float functions operate on the float of their parameters, so integers are converted to floats.
+// Results that aren't finite for finite parameters (e.g. "sqrt(-1)" or "ln(0)") are domain errors,
+// like division by 0.
+fn float_res(func: &Func, idx_expr: usize, nums: &[Number], float: f64) -> Result<Number, ExprError> {
+    if float.is_finite() || nums.iter().any(|num| !num.to_f64().is_finite()) {
+        Ok(Number::from_f64(float))
+    } else {
+        let message = format!("for function '{}' at {} due to result {} out of range", func.name, idx_expr, float);
+        Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+    }
+}
+
+fn func_sqrt(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().sqrt())
+}
+
+fn func_cbrt(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().cbrt())
+}
+
+fn func_exp(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().exp())
+}
+
+fn func_fpow(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().powf(nums[1].to_f64()))
+}
+
+fn func_ln(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().ln())
+}
+
+fn func_log2(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().log2())
+}
+
+fn func_log10(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().log10())
+}
+
+fn func_log(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().log(nums[1].to_f64()))
+}
+
+fn func_sin(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().sin())
+}
+
+fn func_cos(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().cos())
+}
+
+fn func_tan(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().tan())
+}
+
+fn func_asin(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().asin())
+}
+
+fn func_acos(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().acos())
+}
+
+fn func_atan(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().atan())
+}
+
+fn func_atan2(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    float_res(func, idx_expr, nums, nums[0].to_f64().atan2(nums[1].to_f64()))
 }
 
-fn func_is_pow_of_two(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let val = nums[0].integer;
-    let integer = if val > 0 {
-        (val & (val - 1) == 0) as u64
+// Rounding only applies to floats, integers are already whole numbers and are left as is.
+fn round_float(num: &Number, round: fn(f64) -> f64) -> Number {
+    match num {
+        Number::Float(float) => Number::from_f64(round(*float)),
+        _ => num.clone(),
+    }
+}
+
+fn func_floor(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(round_float(&nums[0], f64::floor))
+}
+
+fn func_ceil(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(round_float(&nums[0], f64::ceil))
+}
+
+fn func_round(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(round_float(&nums[0], f64::round))
+}
+
+fn func_trunc(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(round_float(&nums[0], f64::trunc))
+}
+
+// Bits of a float narrower than f32 with the given exponent and mantissa bits (e.g. IEEE-754 half
+// precision or bfloat16), rounded to nearest even from the f64 so there's no double rounding.
+fn narrow_float_bits(float: f64, exp_bits: u32, man_bits: u32) -> u128 {
+    let sign = (float.is_sign_negative() as u128) << (exp_bits + man_bits);
+    let exp_max = (1u128 << exp_bits) - 1;
+    if float.is_nan() {
+        return sign | (exp_max << man_bits) | (1 << (man_bits - 1));
+    }
+    let bits = float.abs().to_bits();
+    let exp = (bits >> 52) as i32;
+    if exp == 0 {
+        // Zero or an f64 subnormal, which is far too small for any narrower float.
+        return sign;
+    }
+    let man = (bits & ((1 << 52) - 1)) | (1 << 52);
+    let bias = (1 << (exp_bits - 1)) - 1;
+    let exp_narrow = exp - 1023 + bias;
+
+    // Normal floats have an implicit leading bit, subnormals are shifted right by the exponent deficit.
+    let shift = (52 - man_bits as i32 + 1 - exp_narrow.min(1)) as u32;
+    let mut narrow = if shift < u64::BITS { man >> shift } else { 0 };
+    if shift < u64::BITS {
+        let rem = man & ((1 << shift) - 1);
+        let half = 1 << (shift - 1);
+        if rem > half || (rem == half && narrow & 1 == 1) {
+            narrow += 1;
+        }
+    }
+    // A carry out of the mantissa increments the exponent, possibly up to infinity.
+    let narrow = narrow as u128 + (((exp_narrow.max(1) - 1) as u128) << man_bits);
+    sign | narrow.min(exp_max << man_bits)
+}
+
+fn narrow_float(bits: u128, exp_bits: u32, man_bits: u32) -> f64 {
+    let sign = if (bits >> (exp_bits + man_bits)) & 1 == 1 {
+        -1.0
     } else {
-        0
+        1.0
     };
-    let float = integer as f64;
-    Ok(Number { integer, float })
+    let exp = ((bits >> man_bits) & ((1 << exp_bits) - 1)) as i32;
+    let man = (bits & ((1 << man_bits) - 1)) as f64;
+    let bias = (1 << (exp_bits - 1)) - 1;
+    let float = if exp == (1 << exp_bits) - 1 {
+        if man == 0.0 { f64::INFINITY } else { f64::NAN }
+    } else if exp == 0 {
+        man * 2f64.powi(1 - bias - man_bits as i32)
+    } else {
+        (man + 2f64.powi(man_bits as i32)) * 2f64.powi(exp - bias - man_bits as i32)
+    };
+    sign * float
+}
+
+// Integers are converted to floats before getting their bits, e.g. "f32bits(1)" is "0x3f800000".
+fn func_f16bits(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(Number::from_int(narrow_float_bits(nums[0].to_f64(), 5, 10), IntType::U16))
+}
+
+fn func_bf16bits(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(Number::from_int(narrow_float_bits(nums[0].to_f64(), 8, 7), IntType::U16))
+}
+
+fn func_f32bits(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(Number::from_int((nums[0].to_f64() as f32).to_bits() as u128, IntType::U32))
+}
+
+fn func_f64bits(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(Number::from_int(nums[0].to_f64().to_bits() as u128, IntType::U64))
+}
+
+// The bits are the low bits of an integer, and NaNs and infinities are valid results.
+fn float_bits(func: &Func, idx_expr: usize, nums: &[Number], int_type: IntType) -> Result<u128, ExprError> {
+    check_ints(func, idx_expr, nums)?;
+    Ok(int_type.wrap(nums[0].extended()))
+}
+
+fn func_bits2f16(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(Number::from_f64(narrow_float(float_bits(func, idx_expr, nums, IntType::U16)?, 5, 10)))
+}
+
+fn func_bits2bf16(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(Number::from_f64(narrow_float(float_bits(func, idx_expr, nums, IntType::U16)?, 8, 7)))
+}
+
+fn func_bits2f32(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(Number::from_f64(f32::from_bits(float_bits(func, idx_expr, nums, IntType::U32)? as u32) as f64))
+}
+
+fn func_bits2f64(func: &Func, idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(Number::from_f64(f64::from_bits(float_bits(func, idx_expr, nums, IntType::U64)? as u64)))
+}
+
+fn func_if(_func: &Func, _idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    // Only the selected parameter is evaluated, the other one is a placeholder.
+    if !nums[0].is_zero() {
+        Ok(nums[1].clone())
+    } else {
+        Ok(nums[2].clone())
+    }
 }
@@ -1,5 +1,17 @@
+mod ast;
+mod constants;
+mod context;
 mod evaluator;
 mod functions;
+mod number;
 mod operators;
 
-pub use evaluator::{ExprError, ExprErrorKind, Number, evaluate, max_sub_expressions};
+pub use ast::{Expr, ExprKind, Span};
+pub use constants::constants;
+pub use context::{EvalContext, IntMode, Overflow};
+pub use evaluator::{
+    CompiledExpr, ExprError, ExprErrorKind, compile, compile_with, define, evaluate, evaluate_with,
+    max_sub_expressions, parse,
+};
+pub use functions::{FuncRegistry, Function};
+pub use number::{IntType, Number};
@@ -0,0 +1,356 @@
+use num_bigint::{BigInt, Sign};
+use num_traits::{ToPrimitive, Zero};
+use std::fmt;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum IntType {
+    I8,
+    I16,
+    I32,
+    #[default]
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+}
+
+static INT_TYPES: [IntType; 10] = [
+    IntType::I8,
+    IntType::I16,
+    IntType::I32,
+    IntType::I64,
+    IntType::I128,
+    IntType::U8,
+    IntType::U16,
+    IntType::U32,
+    IntType::U64,
+    IntType::U128,
+];
+
+impl IntType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        INT_TYPES.iter().copied().find(|int_type| int_type.name() == name)
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            IntType::I8 => "i8",
+            IntType::I16 => "i16",
+            IntType::I32 => "i32",
+            IntType::I64 => "i64",
+            IntType::I128 => "i128",
+            IntType::U8 => "u8",
+            IntType::U16 => "u16",
+            IntType::U32 => "u32",
+            IntType::U64 => "u64",
+            IntType::U128 => "u128",
+        }
+    }
+
+    pub const fn bits(self) -> u32 {
+        match self {
+            IntType::I8 | IntType::U8 => u8::BITS,
+            IntType::I16 | IntType::U16 => u16::BITS,
+            IntType::I32 | IntType::U32 => u32::BITS,
+            IntType::I64 | IntType::U64 => u64::BITS,
+            IntType::I128 | IntType::U128 => u128::BITS,
+        }
+    }
+
+    pub const fn is_signed(self) -> bool {
+        matches!(self, IntType::I8 | IntType::I16 | IntType::I32 | IntType::I64 | IntType::I128)
+    }
+
+    // Truncates the integer to the width of this type.
+    // Integers are always kept truncated this way, i.e. only the bits of its type are ever set.
+    pub const fn wrap(self, integer: u128) -> u128 {
+        integer & (u128::MAX >> (u128::BITS - self.bits()))
+    }
+
+    // Sign or zero-extends the integer from the width of this type to 128 bits.
+    // This is required before converting the integer to a wider type or interpreting it as signed.
+    pub const fn extend(self, integer: u128) -> u128 {
+        let shift = u128::BITS - self.bits();
+        if self.is_signed() {
+            (((integer << shift) as i128) >> shift) as u128
+        } else {
+            integer
+        }
+    }
+
+    // The type both operands of a binary operator are converted to before it's applied.
+    // Like C, the wider type wins and if both are the same width, the unsigned type wins.
+    pub const fn common(self, other: IntType) -> IntType {
+        if self.bits() > other.bits() {
+            self
+        } else if self.bits() < other.bits() || self.is_signed() {
+            other
+        } else {
+            self
+        }
+    }
+
+    // The smallest and largest integers of this type, truncated to its width.
+    pub const fn min_int(self) -> u128 {
+        if self.is_signed() {
+            self.wrap(1 << (self.bits() - 1))
+        } else {
+            0
+        }
+    }
+
+    pub const fn max_int(self) -> u128 {
+        if self.is_signed() {
+            (1 << (self.bits() - 1)) - 1
+        } else {
+            self.wrap(u128::MAX)
+        }
+    }
+
+    // Rotates the integer left within the width of this type. Shifts are modulo the width, so a
+    // negative shift (i.e. a wrapped one) rotates right.
+    pub const fn rotate_left(self, integer: u128, shift: u128) -> u128 {
+        let bits = self.bits();
+        let shift = (shift % bits as u128) as u32;
+        let integer = self.wrap(integer);
+        if shift == 0 {
+            integer
+        } else {
+            self.wrap((integer << shift) | (integer >> (bits - shift)))
+        }
+    }
+
+    pub const fn to_f64(self, integer: u128) -> f64 {
+        if self.is_signed() {
+            self.extend(integer) as i128 as f64
+        } else {
+            integer as f64
+        }
+    }
+}
+
+impl fmt::Display for IntType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Largest integer allowed in arbitrary-precision mode, to keep e.g. "pow(3, U32_MAX)" from
+// exhausting memory.
+pub const MAX_BIG_BITS: u64 = 1 << 16;
+
+// A number is an integer of a specific type, a float or a boolean. Booleans are used as the i64
+// 0 or 1 where an integer is needed (see as_int()), and integers and booleans are promoted to
+// floats where a float is needed (see to_f64()).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Number {
+    // Only the bits of its type are ever set (see IntType::wrap()). In arbitrary-precision mode,
+    // 'big' is the exact integer and if it doesn't fit in 128 bits, 'integer' only holds its low
+    // 128 bits (see is_truncated()).
+    Int { integer: u128, int_type: IntType, big: Option<BigInt> },
+    Float(f64),
+    // Results of comparisons and logical operators.
+    Bool(bool),
+}
+
+// The exact value of a fixed-width integer.
+fn int_to_big(integer: u128, int_type: IntType) -> BigInt {
+    if int_type.is_signed() {
+        BigInt::from(int_type.extend(integer) as i128)
+    } else {
+        BigInt::from(integer)
+    }
+}
+
+impl Number {
+    pub const fn from_int(integer: u128, int_type: IntType) -> Self {
+        Self::Int { integer: int_type.wrap(integer), int_type, big: None }
+    }
+
+    pub const fn from_f64(float: f64) -> Self {
+        Self::Float(float)
+    }
+
+    // Integers are typed the same way as integer literals, i.e. the first of i64, u64, i128
+    // and u128 that it fits in.
+    pub const fn from_u128(integer: u128) -> Self {
+        let int_type = if integer <= i64::MAX as u128 {
+            IntType::I64
+        } else if integer <= u64::MAX as u128 {
+            IntType::U64
+        } else if integer <= i128::MAX as u128 {
+            IntType::I128
+        } else {
+            IntType::U128
+        };
+        Self::from_int(integer, int_type)
+    }
+
+    pub const fn from_bool(value: bool) -> Self {
+        Self::Bool(value)
+    }
+
+    // Non-negative integers are typed like literals and negative ones are i64 or i128. Integers
+    // that don't fit in 128 bits keep their low 128 bits as an i128 or u128.
+    pub fn from_big(big: BigInt) -> Self {
+        let (integer, int_type) = if let Some(integer) = big.to_u128() {
+            Self::from_u128(integer).int()
+        } else if let Some(integer) = big.to_i64() {
+            (integer as u128, IntType::I64)
+        } else if let Some(integer) = big.to_i128() {
+            (integer as u128, IntType::I128)
+        } else {
+            // The magnitude is stored in 64-bit digits, least significant first.
+            let low_bits = big.iter_u64_digits().take(2).rev().fold(0u128, |acc, digit| (acc << 64) | digit as u128);
+            if big.sign() == Sign::Minus {
+                (low_bits.wrapping_neg(), IntType::I128)
+            } else {
+                (low_bits, IntType::U128)
+            }
+        };
+        Self::Int { integer: int_type.wrap(integer), int_type, big: Some(big) }
+    }
+
+    pub const fn is_float(&self) -> bool {
+        matches!(self, Self::Float(_))
+    }
+
+    // The integer and its type. Booleans are the i64 0 or 1, and floats have no integer until
+    // they're cast to an integer type (see cast()).
+    pub const fn as_int(&self) -> Option<(u128, IntType)> {
+        match self {
+            Self::Int { integer, int_type, .. } => Some((*integer, *int_type)),
+            Self::Float(_) => None,
+            Self::Bool(value) => Some((*value as u128, IntType::I64)),
+        }
+    }
+
+    // The exact integer in arbitrary-precision mode.
+    pub const fn as_big(&self) -> Option<&BigInt> {
+        match self {
+            Self::Int { big: Some(big), .. } => Some(big),
+            _ => None,
+        }
+    }
+
+    // The number promoted to a float, e.g. for an operator with a float operand.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Self::Int { big: Some(big), .. } => big.to_f64().unwrap_or(f64::NAN),
+            Self::Int { integer, int_type, .. } => int_type.to_f64(*integer),
+            Self::Float(float) => *float,
+            Self::Bool(value) => *value as u8 as f64,
+        }
+    }
+
+    // The integer and its type of a number that's known not to be a float, e.g. an operand of an
+    // integer-only operator.
+    pub(crate) fn int(&self) -> (u128, IntType) {
+        self.as_int().expect("float used as an integer")
+    }
+
+    pub(crate) fn integer(&self) -> u128 {
+        self.int().0
+    }
+
+    pub(crate) fn int_type(&self) -> IntType {
+        self.int().1
+    }
+
+    // The integer sign or zero-extended to 128 bits.
+    pub(crate) fn extended(&self) -> u128 {
+        let (integer, int_type) = self.int();
+        int_type.extend(integer)
+    }
+
+    // The exact integer, whether or not this number is in arbitrary-precision mode. Floats are
+    // cast to an i128 first (see cast()).
+    pub fn to_big(&self) -> BigInt {
+        match self {
+            Self::Int { big: Some(big), .. } => big.clone(),
+            Self::Float(_) => self.cast(IntType::I128).to_big(),
+            _ => {
+                let (integer, int_type) = self.int();
+                int_to_big(integer, int_type)
+            }
+        }
+    }
+
+    // Switches an integer to arbitrary-precision mode without changing its type.
+    pub fn into_big(self) -> Self {
+        match self {
+            Self::Int { integer, int_type, big: None } => {
+                Self::Int { integer, int_type, big: Some(int_to_big(integer, int_type)) }
+            }
+            _ => self,
+        }
+    }
+
+    // Switches an integer out of arbitrary-precision mode, keeping its low bits.
+    pub fn into_fixed(self) -> Self {
+        match self {
+            Self::Int { integer, int_type, .. } => Self::Int { integer, int_type, big: None },
+            _ => self,
+        }
+    }
+
+    // Whether the exact integer doesn't fit in 128 bits and thus has no fixed-width type.
+    pub fn is_truncated(&self) -> bool {
+        self.as_big().is_some_and(|big| big.to_u128().is_none() && big.to_i128().is_none())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Self::Int { big: Some(big), .. } => big.is_zero(),
+            Self::Int { integer, .. } => *integer == 0,
+            Self::Float(float) => *float == 0.0,
+            Self::Bool(value) => !value,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Self::Int { big: Some(big), .. } => big.sign() == Sign::Minus,
+            Self::Int { integer, int_type, .. } => int_type.is_signed() && (int_type.extend(*integer) as i128) < 0,
+            Self::Float(float) => *float < 0.0,
+            Self::Bool(_) => false,
+        }
+    }
+
+    // The integer as a count (e.g. of bits or an exponent), if it's non-negative and fits in a u32.
+    pub fn to_u32(&self) -> Option<u32> {
+        match self {
+            Self::Int { big: Some(big), .. } => big.to_u32(),
+            Self::Float(_) => None,
+            _ if self.is_negative() => None,
+            _ => u32::try_from(self.integer()).ok(),
+        }
+    }
+
+    // Floats are truncated towards zero (saturating at the limits of an i128) before wrapping.
+    pub const fn cast(&self, int_type: IntType) -> Self {
+        match self {
+            Self::Int { integer, int_type: from_type, .. } => Self::from_int(from_type.extend(*integer), int_type),
+            Self::Float(float) => Self::from_int(*float as i128 as u128, int_type),
+            Self::Bool(value) => Self::from_int(*value as u128, int_type),
+        }
+    }
+}
+
+// Integers are formatted in decimal, e.g. "-1" for "(i8)0xff".
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int { big: Some(big), .. } => fmt::Display::fmt(big, f),
+            Self::Int { integer, int_type, .. } if int_type.is_signed() => {
+                fmt::Display::fmt(&(int_type.extend(*integer) as i128), f)
+            }
+            Self::Int { integer, .. } => fmt::Display::fmt(integer, f),
+            Self::Float(float) => fmt::Display::fmt(float, f),
+            Self::Bool(value) => fmt::Display::fmt(value, f),
+        }
+    }
+}
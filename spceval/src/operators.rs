@@ -1,45 +1,66 @@
-use super::{ExprError, ExprErrorKind, Number};
+use super::{ExprError, ExprErrorKind, IntType, Number};
+use crate::functions::pow_ints;
+use crate::number::MAX_BIG_BITS;
+use num_bigint::BigInt;
 use std::cmp::Ordering;
 
 #[rustfmt::skip]
-pub static OPERS: [Oper<'static>; 24] = [
+pub static OPERS: [Oper<'static>; 39] = [
     // Precedence 1 (highest priority)
-    Oper { kind: OperKind::OpenParen,  prec: 1,  params: 0, assoc: OperAssoc::Nil,   evalfn: oper_nop,         name: "(",  syntax: "(<expr>",            help: "Begin expression.",       },
-    Oper { kind: OperKind::CloseParen, prec: 1,  params: 0, assoc: OperAssoc::Nil,   evalfn: oper_nop,         name: ")",  syntax: "<expr>)",            help: "End expression.",         },
+    Oper { kind: OperKind::OpenParen,   prec: 1,  params: 0, assoc: OperAssoc::Nil,   checked: false, evalfn: oper_nop,         name: "(",      syntax: "(<expr>",                  help: "Begin expression.",         },
+    Oper { kind: OperKind::CloseParen,  prec: 1,  params: 0, assoc: OperAssoc::Nil,   checked: false, evalfn: oper_nop,         name: ")",      syntax: "<expr>)",                  help: "End expression.",           },
     // Precendence 4 (appears in array before 2 because of parsing logic with unary operators)
-    Oper { kind: OperKind::Regular,    prec: 4,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_add,         name: "+",  syntax: "<expr> + <expr>",    help: "Addition.",               },
-    Oper { kind: OperKind::Regular,    prec: 4,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_sub,         name: "-",  syntax: "<expr> - <expr>",    help: "Subtraction.",            },
+    Oper { kind: OperKind::Regular,     prec: 4,  params: 2, assoc: OperAssoc::Left,  checked: true,  evalfn: oper_add,         name: "+",      syntax: "<expr> + <expr>",          help: "Addition.",                 },
+    Oper { kind: OperKind::Regular,     prec: 4,  params: 2, assoc: OperAssoc::Left,  checked: true,  evalfn: oper_sub,         name: "-",      syntax: "<expr> - <expr>",          help: "Subtraction.",              },
     // Precedence 2
-    Oper { kind: OperKind::Regular,    prec: 2,  params: 1, assoc: OperAssoc::Right, evalfn: oper_unary_minus, name: "-",  syntax: "-<expr>",            help: "Unary minus.",            },
-    Oper { kind: OperKind::Regular,    prec: 2,  params: 1, assoc: OperAssoc::Right, evalfn: oper_logical_not, name: "!",  syntax: "!<expr>",            help: "Logical NOT.",            },
-    Oper { kind: OperKind::Regular,    prec: 2,  params: 1, assoc: OperAssoc::Right, evalfn: oper_bit_not,     name: "~",  syntax: "~<expr>",            help: "Bitwise NOT.",            },
+    Oper { kind: OperKind::Regular,     prec: 2,  params: 1, assoc: OperAssoc::Right, checked: true,  evalfn: oper_unary_minus, name: "-",      syntax: "-<expr>",                  help: "Unary minus.",              },
+    Oper { kind: OperKind::Regular,     prec: 2,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_logical_not, name: "!",      syntax: "!<expr>",                  help: "Logical NOT.",              },
+    Oper { kind: OperKind::Regular,     prec: 2,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_bit_not,     name: "~",      syntax: "~<expr>",                  help: "Bitwise NOT.",              },
+    // Precedence 1 (casts bind tighter than "**", i.e. "(i8)2 ** 7" is "((i8)2) ** 7")
+    Oper { kind: OperKind::Cast,        prec: 1,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_cast_i8,     name: "(i8)",   syntax: "(i8)<expr>",               help: "Cast to i8.",               },
+    Oper { kind: OperKind::Cast,        prec: 1,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_cast_i16,    name: "(i16)",  syntax: "(i16)<expr>",              help: "Cast to i16.",              },
+    Oper { kind: OperKind::Cast,        prec: 1,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_cast_i32,    name: "(i32)",  syntax: "(i32)<expr>",              help: "Cast to i32.",              },
+    Oper { kind: OperKind::Cast,        prec: 1,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_cast_i64,    name: "(i64)",  syntax: "(i64)<expr>",              help: "Cast to i64.",              },
+    Oper { kind: OperKind::Cast,        prec: 1,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_cast_i128,   name: "(i128)", syntax: "(i128)<expr>",             help: "Cast to i128.",             },
+    Oper { kind: OperKind::Cast,        prec: 1,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_cast_u8,     name: "(u8)",   syntax: "(u8)<expr>",               help: "Cast to u8.",               },
+    Oper { kind: OperKind::Cast,        prec: 1,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_cast_u16,    name: "(u16)",  syntax: "(u16)<expr>",              help: "Cast to u16.",              },
+    Oper { kind: OperKind::Cast,        prec: 1,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_cast_u32,    name: "(u32)",  syntax: "(u32)<expr>",              help: "Cast to u32.",              },
+    Oper { kind: OperKind::Cast,        prec: 1,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_cast_u64,    name: "(u64)",  syntax: "(u64)<expr>",              help: "Cast to u64.",              },
+    Oper { kind: OperKind::Cast,        prec: 1,  params: 1, assoc: OperAssoc::Right, checked: false, evalfn: oper_cast_u128,   name: "(u128)", syntax: "(u128)<expr>",             help: "Cast to u128.",             },
+    // Precedence 2 (binds tighter than the other prefix operators on its left, i.e. "-2 ** 2" is "-(2 ** 2)")
+    Oper { kind: OperKind::Regular,     prec: 2,  params: 2, assoc: OperAssoc::Right, checked: false, evalfn: oper_pow,         name: "**",     syntax: "<expr> ** <expr>",         help: "Power, fails on overflow.", },
     // Precedence 3
-    Oper { kind: OperKind::Regular,    prec: 3,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_mul,         name: "*",  syntax: "<expr> * <expr>",    help: "Multiplication.",         },
-    Oper { kind: OperKind::Regular,    prec: 3,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_div,         name: "/",  syntax: "<expr> / <expr>",    help: "Division.",               },
-    Oper { kind: OperKind::Regular,    prec: 3,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_rem,         name: "%",  syntax: "<expr> % <expr>",    help: "Remainder.",              },
+    Oper { kind: OperKind::Regular,     prec: 3,  params: 2, assoc: OperAssoc::Left,  checked: true,  evalfn: oper_mul,         name: "*",      syntax: "<expr> * <expr>",          help: "Multiplication.",           },
+    Oper { kind: OperKind::Regular,     prec: 3,  params: 2, assoc: OperAssoc::Left,  checked: true,  evalfn: oper_div,         name: "/",      syntax: "<expr> / <expr>",          help: "Division.",                 },
+    Oper { kind: OperKind::Regular,     prec: 3,  params: 2, assoc: OperAssoc::Left,  checked: true,  evalfn: oper_rem,         name: "%",      syntax: "<expr> % <expr>",          help: "Remainder.",                },
     // Precedence 5
-    Oper { kind: OperKind::Regular,    prec: 5,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_bit_lshift,  name: "<<", syntax: "<expr> << <expr>",   help: "Bitwise left-shift.",     },
-    Oper { kind: OperKind::Regular,    prec: 5,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_bit_rshift,  name: ">>", syntax: "<expr> >> <expr>",   help: "Bitwise right-shift.",    },
+    Oper { kind: OperKind::Regular,     prec: 5,  params: 2, assoc: OperAssoc::Left,  checked: true,  evalfn: oper_bit_lshift,  name: "<<",     syntax: "<expr> << <expr>",         help: "Bitwise left-shift.",       },
+    Oper { kind: OperKind::Regular,     prec: 5,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_bit_rshift,  name: ">>",     syntax: "<expr> >> <expr>",         help: "Bitwise right-shift.",      },
+    Oper { kind: OperKind::Regular,     prec: 5,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_bit_rotl,    name: "<<<",    syntax: "<expr> <<< <expr>",        help: "Bitwise rotate-left.",      },
+    Oper { kind: OperKind::Regular,     prec: 5,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_bit_rotr,    name: ">>>",    syntax: "<expr> >>> <expr>",        help: "Bitwise rotate-right.",     },
     // Precedence 6
-    Oper { kind: OperKind::Regular,    prec: 6,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_lt,          name: "<",  syntax: "<expr> < <expr>",    help: "Less-than.",              },
-    Oper { kind: OperKind::Regular,    prec: 6,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_lte,         name: "<=", syntax: "<expr> <= <expr>",   help: "Less-than-or-equals.",    },
-    Oper { kind: OperKind::Regular,    prec: 6,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_gt,          name: ">",  syntax: "<expr> > <expr>",    help: "Greater-than.",           },
-    Oper { kind: OperKind::Regular,    prec: 6,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_gte,         name: ">=", syntax: "<expr> >= <expr>",   help: "Greater-than-or-equals.", },
+    Oper { kind: OperKind::Regular,     prec: 6,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_lt,          name: "<",      syntax: "<expr> < <expr>",          help: "Less-than.",                },
+    Oper { kind: OperKind::Regular,     prec: 6,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_lte,         name: "<=",     syntax: "<expr> <= <expr>",         help: "Less-than-or-equals.",      },
+    Oper { kind: OperKind::Regular,     prec: 6,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_gt,          name: ">",      syntax: "<expr> > <expr>",          help: "Greater-than.",             },
+    Oper { kind: OperKind::Regular,     prec: 6,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_gte,         name: ">=",     syntax: "<expr> >= <expr>",         help: "Greater-than-or-equals.",   },
     // Precedence 7
-    Oper { kind: OperKind::Regular,    prec: 7,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_eq,          name: "==", syntax: "<expr> == <expr>",   help: "Equals.",                 },
-    Oper { kind: OperKind::Regular,    prec: 7,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_ne,          name: "!=", syntax: "<expr> != <expr>",   help: "Not-equals.",             },
+    Oper { kind: OperKind::Regular,     prec: 7,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_eq,          name: "==",     syntax: "<expr> == <expr>",         help: "Equals.",                   },
+    Oper { kind: OperKind::Regular,     prec: 7,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_ne,          name: "!=",     syntax: "<expr> != <expr>",         help: "Not-equals.",               },
     // Precedence 8
-    Oper { kind: OperKind::Regular,    prec: 8,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_bit_and,     name: "&",  syntax: "<expr> & <expr>",    help: "Bitwise AND.",            },
+    Oper { kind: OperKind::Regular,     prec: 8,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_bit_and,     name: "&",      syntax: "<expr> & <expr>",          help: "Bitwise AND.",              },
     // Precedence 9
-    Oper { kind: OperKind::Regular,    prec: 9,  params: 2, assoc: OperAssoc::Left,  evalfn: oper_bit_xor,     name: "^",  syntax: "<expr> ^ <expr>",    help: "Bitwise XOR.",            },
+    Oper { kind: OperKind::Regular,     prec: 9,  params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_bit_xor,     name: "^",      syntax: "<expr> ^ <expr>",          help: "Bitwise XOR.",              },
     // Precedence 10
-    Oper { kind: OperKind::Regular,    prec: 10, params: 2, assoc: OperAssoc::Left,  evalfn: oper_bit_or,      name: "|",  syntax: "<expr> | <expr>",    help: "Bitwise OR." ,            },
+    Oper { kind: OperKind::Regular,     prec: 10, params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_bit_or,      name: "|",      syntax: "<expr> | <expr>",          help: "Bitwise OR.",               },
     // Precedence 11
-    Oper { kind: OperKind::Regular,    prec: 11, params: 2, assoc: OperAssoc::Left,  evalfn: oper_nop,         name: "&&", syntax: "<expr> && <expr>",   help: "Logical AND.",            },
+    Oper { kind: OperKind::LogicalAnd,  prec: 11, params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_logical_and, name: "&&",     syntax: "<expr> && <expr>",         help: "Logical AND.",              },
     // Precedence 12
-    Oper { kind: OperKind::Regular,    prec: 12, params: 2, assoc: OperAssoc::Left,  evalfn: oper_nop,         name: "||", syntax: "<expr> || <expr>",   help: "Logical OR." ,            },
+    Oper { kind: OperKind::LogicalOr,   prec: 12, params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_logical_or,  name: "||",     syntax: "<expr> || <expr>",         help: "Logical OR.",               },
     // Precedence 13
-    Oper { kind: OperKind::ParamSep,   prec: 13, params: 2, assoc: OperAssoc::Left,  evalfn: oper_nop,         name: ",",  syntax: "<param1>, <param2>", help: "Parameter separator.",    },
+    Oper { kind: OperKind::TernaryCond, prec: 13, params: 0, assoc: OperAssoc::Left,  checked: false, evalfn: oper_nop,         name: "?",      syntax: "<cond> ? <expr> : <expr>", help: "Ternary conditional.",      },
+    Oper { kind: OperKind::TernaryElse, prec: 13, params: 3, assoc: OperAssoc::Left,  checked: false, evalfn: oper_ternary,     name: ":",      syntax: "<cond> ? <expr> : <expr>", help: "Ternary conditional else.", },
+    // Precedence 14
+    Oper { kind: OperKind::ParamSep,    prec: 14, params: 2, assoc: OperAssoc::Left,  checked: false, evalfn: oper_nop,         name: ",",      syntax: "<param1>, <param2>",       help: "Parameter separator.",      },
 ];
 
 type PfnOper = fn(idx_expr: usize, &[Number]) -> Result<Number, ExprError>;
@@ -54,6 +75,11 @@ pub enum OperAssoc {
 #[derive(Eq, PartialEq, Ord, PartialOrd, Debug)]
 pub enum OperKind {
     Regular,
+    Cast,
+    LogicalAnd,
+    LogicalOr,
+    TernaryCond,
+    TernaryElse,
     OpenParen,
     CloseParen,
     ParamSep,
@@ -64,12 +90,22 @@ pub struct Oper<'a> {
     pub prec: u8,
     pub params: u8,
     pub assoc: OperAssoc,
+    // Whether the integer result is subject to the overflow policy (it wraps otherwise).
+    pub checked: bool,
     pub evalfn: PfnOper,
     pub name: &'a str,
     pub syntax: &'a str,
     pub help: &'a str,
 }
 
+impl Oper<'_> {
+    // Prefix operators (e.g. unary minus and casts) precede their operand. All other operators,
+    // including right associative binary operators like "**", follow an operand.
+    pub fn is_prefix(&self) -> bool {
+        self.assoc == OperAssoc::Right && self.params == 1
+    }
+}
+
 // Eq specifies that the equality relationship defined by PartialEq is a total equality.
 impl Eq for Oper<'_> {}
 
@@ -111,61 +147,162 @@ fn cmp_eq_f64(a: f64, b: f64) -> bool {
 }
 
 fn oper_nop(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    Ok(nums[0])
+    Ok(nums[0].clone())
+}
+
+// Converts the integers of both operands of a binary operator to their common type.
+fn common_ints(lhs: &Number, rhs: &Number) -> (u128, u128, IntType) {
+    let int_type = lhs.int_type().common(rhs.int_type());
+    (int_type.wrap(lhs.extended()), int_type.wrap(rhs.extended()), int_type)
+}
+
+// The exact integers of both operands of a binary operator if either is in arbitrary-precision mode.
+fn big_ints(lhs: &Number, rhs: &Number) -> Option<(BigInt, BigInt)> {
+    if lhs.as_big().is_some() || rhs.as_big().is_some() {
+        Some((lhs.to_big(), rhs.to_big()))
+    } else {
+        None
+    }
+}
+
+// Integer-only operators (e.g. bitwise operators) can't be applied to floats.
+fn check_ints(idx_expr: usize, nums: &[Number]) -> Result<(), ExprError> {
+    if nums.iter().any(Number::is_float) {
+        let message = format!("due to float operand for operator at {}", idx_expr);
+        Err(ExprError::new(idx_expr, ExprErrorKind::InvalidParamType, message))
+    } else {
+        Ok(())
+    }
+}
+
+// Binary operators are applied to floats if either operand is a float, otherwise to integers.
+fn is_float_oper(lhs: &Number, rhs: &Number) -> bool {
+    lhs.is_float() || rhs.is_float()
 }
 
 fn oper_add(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = lhs.integer.wrapping_add(rhs.integer);
-    let float = lhs.float + rhs.float;
-    Ok(Number { integer, float })
+    let lhs = &nums[0];
+    let rhs = &nums[1];
+    if is_float_oper(lhs, rhs) {
+        return Ok(Number::from_f64(lhs.to_f64() + rhs.to_f64()));
+    }
+    if let Some((lhs_big, rhs_big)) = big_ints(lhs, rhs) {
+        return Ok(Number::from_big(lhs_big + rhs_big));
+    }
+    let (lhs_int, rhs_int, int_type) = common_ints(lhs, rhs);
+    Ok(Number::from_int(lhs_int.wrapping_add(rhs_int), int_type))
 }
 
 fn oper_sub(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = lhs.integer.wrapping_sub(rhs.integer);
-    let float = lhs.float - rhs.float;
-    Ok(Number { integer, float })
+    let lhs = &nums[0];
+    let rhs = &nums[1];
+    if is_float_oper(lhs, rhs) {
+        return Ok(Number::from_f64(lhs.to_f64() - rhs.to_f64()));
+    }
+    if let Some((lhs_big, rhs_big)) = big_ints(lhs, rhs) {
+        return Ok(Number::from_big(lhs_big - rhs_big));
+    }
+    let (lhs_int, rhs_int, int_type) = common_ints(lhs, rhs);
+    Ok(Number::from_int(lhs_int.wrapping_sub(rhs_int), int_type))
 }
 
 fn oper_unary_minus(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let rhs = nums[0];
-    let integer = rhs.integer.wrapping_neg();
-    let float = -rhs.float;
-    Ok(Number { integer, float })
+    match &nums[0] {
+        Number::Float(float) => Ok(Number::from_f64(-float)),
+        Number::Int { big: Some(big), .. } => Ok(Number::from_big(-big)),
+        rhs => {
+            let (integer, int_type) = rhs.int();
+            Ok(Number::from_int(integer.wrapping_neg(), int_type))
+        }
+    }
 }
 
 fn oper_logical_not(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let rhs = nums[0];
-    let integer = (rhs.integer == 0) as u64;
-    let float = integer as f64;
-    Ok(Number { integer, float })
+    Ok(Number::from_bool(nums[0].is_zero()))
+}
+
+fn oper_bit_not(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(idx_expr, nums)?;
+    match &nums[0] {
+        Number::Int { big: Some(big), .. } => Ok(Number::from_big(!big)),
+        rhs => {
+            let (integer, int_type) = rhs.int();
+            Ok(Number::from_int(!integer, int_type))
+        }
+    }
 }
 
-fn oper_bit_not(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let rhs = nums[0];
-    let integer = !rhs.integer;
-    let float = integer as f64;
-    Ok(Number { integer, float })
+fn oper_cast_i8(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(nums[0].cast(IntType::I8))
+}
+
+fn oper_cast_i16(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(nums[0].cast(IntType::I16))
+}
+
+fn oper_cast_i32(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(nums[0].cast(IntType::I32))
+}
+
+fn oper_cast_i64(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(nums[0].cast(IntType::I64))
+}
+
+fn oper_cast_i128(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(nums[0].cast(IntType::I128))
+}
+
+fn oper_cast_u8(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(nums[0].cast(IntType::U8))
+}
+
+fn oper_cast_u16(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(nums[0].cast(IntType::U16))
+}
+
+fn oper_cast_u32(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(nums[0].cast(IntType::U32))
+}
+
+fn oper_cast_u64(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(nums[0].cast(IntType::U64))
+}
+
+fn oper_cast_u128(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(nums[0].cast(IntType::U128))
 }
 
 fn oper_mul(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = lhs.integer.wrapping_mul(rhs.integer);
-    let float = lhs.float * rhs.float;
-    Ok(Number { integer, float })
+    let lhs = &nums[0];
+    let rhs = &nums[1];
+    if is_float_oper(lhs, rhs) {
+        return Ok(Number::from_f64(lhs.to_f64() * rhs.to_f64()));
+    }
+    if let Some((lhs_big, rhs_big)) = big_ints(lhs, rhs) {
+        return Ok(Number::from_big(lhs_big * rhs_big));
+    }
+    let (lhs_int, rhs_int, int_type) = common_ints(lhs, rhs);
+    Ok(Number::from_int(lhs_int.wrapping_mul(rhs_int), int_type))
 }
 
 fn oper_div(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    if rhs.integer != 0 && !cmp_eq_f64(rhs.float, 0f64) {
-        let integer = lhs.integer.wrapping_div(rhs.integer);
-        let float = lhs.float / rhs.float;
-        Ok(Number { integer, float })
+    let lhs = &nums[0];
+    let rhs = &nums[1];
+    if !rhs.is_zero() {
+        if is_float_oper(lhs, rhs) {
+            return Ok(Number::from_f64(lhs.to_f64() / rhs.to_f64()));
+        }
+        // Division of big integers truncates towards zero like signed integer division.
+        if let Some((lhs_big, rhs_big)) = big_ints(lhs, rhs) {
+            return Ok(Number::from_big(lhs_big / rhs_big));
+        }
+        let (lhs_int, rhs_int, int_type) = common_ints(lhs, rhs);
+        let integer = if int_type.is_signed() {
+            (int_type.extend(lhs_int) as i128).wrapping_div(int_type.extend(rhs_int) as i128) as u128
+        } else {
+            lhs_int.wrapping_div(rhs_int)
+        };
+        Ok(Number::from_int(integer, int_type))
     } else {
         let message = format!("due to division by 0 for operator at {}", idx_expr);
         Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
@@ -173,102 +310,201 @@ fn oper_div(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
 }
 
 fn oper_rem(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let rhs = nums[1];
-    if rhs.integer != 0 && !cmp_eq_f64(rhs.float, 0f64) {
-        let lhs = nums[0];
-        let integer = lhs.integer.wrapping_rem(rhs.integer);
-        let float = lhs.float % rhs.float;
-        Ok(Number { integer, float })
+    let lhs = &nums[0];
+    let rhs = &nums[1];
+    if !rhs.is_zero() {
+        if is_float_oper(lhs, rhs) {
+            return Ok(Number::from_f64(lhs.to_f64() % rhs.to_f64()));
+        }
+        if let Some((lhs_big, rhs_big)) = big_ints(lhs, rhs) {
+            return Ok(Number::from_big(lhs_big % rhs_big));
+        }
+        let (lhs_int, rhs_int, int_type) = common_ints(lhs, rhs);
+        let integer = if int_type.is_signed() {
+            (int_type.extend(lhs_int) as i128).wrapping_rem(int_type.extend(rhs_int) as i128) as u128
+        } else {
+            lhs_int.wrapping_rem(rhs_int)
+        };
+        Ok(Number::from_int(integer, int_type))
     } else {
         let message = format!("due to remainder by 0 for operator at {}", idx_expr);
-        Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))    
+        Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+    }
+}
+
+// Shift counts aren't reduced modulo the width of the type, so shifting by the width or more
+// shifts out all bits, e.g. "1 << 64" is 0 and "-8 >> 64" is -1. Negative counts are invalid.
+fn check_shift(idx_expr: usize, rhs: &Number) -> Result<u32, ExprError> {
+    if rhs.is_negative() {
+        let message = format!("due to negative shift {} for operator at {}", rhs, idx_expr);
+        Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+    } else {
+        Ok(rhs.to_u32().unwrap_or(u32::MAX))
     }
 }
 
-fn oper_bit_lshift(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = lhs.integer.wrapping_shl(rhs.integer as u32);
-    let float = integer as f64;
-    Ok(Number { integer, float })
+fn oper_bit_lshift(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    // The result has the type of the left operand.
+    check_ints(idx_expr, nums)?;
+    let lhs = &nums[0];
+    let shift = check_shift(idx_expr, &nums[1])?;
+    if let Some((lhs_big, _)) = big_ints(lhs, &nums[1]) {
+        // Shifting 0 by any amount is 0, so only the bits of non-zero integers are bounded.
+        if lhs_big.bits() > 0 && lhs_big.bits() + shift as u64 > MAX_BIG_BITS {
+            let message = format!(
+                "due to invalid shift {} for operator at {} (result must be <= {} bits)",
+                nums[1], idx_expr, MAX_BIG_BITS
+            );
+            return Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message));
+        }
+        return Ok(Number::from_big(lhs_big << shift));
+    }
+    let (integer, int_type) = lhs.int();
+    Ok(Number::from_int(integer.checked_shl(shift).unwrap_or(0), int_type))
 }
 
-fn oper_bit_rshift(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = lhs.integer.wrapping_shr(rhs.integer as u32);
-    let float = integer as f64;
-    Ok(Number { integer, float })
+fn oper_bit_rshift(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    // The result has the type of the left operand. Signed integers are shifted arithmetically.
+    check_ints(idx_expr, nums)?;
+    let lhs = &nums[0];
+    let shift = check_shift(idx_expr, &nums[1])?;
+    if let Some((lhs_big, _)) = big_ints(lhs, &nums[1]) {
+        // Shifting out all bits yields 0 or -1, so larger shifts are clamped.
+        return Ok(Number::from_big(lhs_big >> shift.min(MAX_BIG_BITS as u32)));
+    }
+    let (integer, int_type) = lhs.int();
+    let integer = if int_type.is_signed() {
+        (int_type.extend(integer) as i128 >> shift.min(u128::BITS - 1)) as u128
+    } else {
+        integer.checked_shr(shift).unwrap_or(0)
+    };
+    Ok(Number::from_int(integer, int_type))
+}
+
+// Integer powers fail rather than wrap on overflow, like "pow()", regardless of the overflow policy
+// (i.e. they don't saturate either). Float powers are used if either operand is a float, e.g.
+// "2 ** 0.5".
+fn oper_pow(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let (base, exp) = (&nums[0], &nums[1]);
+    if !is_float_oper(base, exp) {
+        return pow_ints(&format!("operator '{}' at {}", "**", idx_expr), idx_expr, base, exp);
+    }
+    let float = base.to_f64().powf(exp.to_f64());
+    if float.is_finite() || !base.to_f64().is_finite() || !exp.to_f64().is_finite() {
+        Ok(Number::from_f64(float))
+    } else {
+        let message = format!("due to result {} out of range for operator at {}", float, idx_expr);
+        Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+    }
+}
+
+// Rotations are within the width of the left operand's type, e.g. "(u8)0x81 <<< 1" is 0x03.
+// Integers wider than 128 bits (in arbitrary-precision mode) have no width to rotate within.
+fn check_rotate(idx_expr: usize, nums: &[Number]) -> Result<IntType, ExprError> {
+    check_ints(idx_expr, nums)?;
+    if nums[0].is_truncated() {
+        let message = format!("due to integer wider than 128 bits for operator at {}", idx_expr);
+        Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, message))
+    } else {
+        Ok(nums[0].int_type())
+    }
+}
+
+fn oper_bit_rotl(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let int_type = check_rotate(idx_expr, nums)?;
+    Ok(Number::from_int(int_type.rotate_left(nums[0].integer(), nums[1].extended()), int_type))
+}
+
+fn oper_bit_rotr(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    let int_type = check_rotate(idx_expr, nums)?;
+    Ok(Number::from_int(int_type.rotate_left(nums[0].integer(), nums[1].extended().wrapping_neg()), int_type))
+}
+
+// Floats that are unordered (i.e. NaN) compare as unequal to everything.
+fn cmp_nums(lhs: &Number, rhs: &Number) -> Option<Ordering> {
+    if is_float_oper(lhs, rhs) {
+        return if cmp_eq_f64(lhs.to_f64(), rhs.to_f64()) {
+            Some(Ordering::Equal)
+        } else {
+            lhs.to_f64().partial_cmp(&rhs.to_f64())
+        };
+    }
+    if let Some((lhs_big, rhs_big)) = big_ints(lhs, rhs) {
+        return Some(lhs_big.cmp(&rhs_big));
+    }
+    let (lhs_int, rhs_int, int_type) = common_ints(lhs, rhs);
+    if int_type.is_signed() {
+        Some((int_type.extend(lhs_int) as i128).cmp(&(int_type.extend(rhs_int) as i128)))
+    } else {
+        Some(lhs_int.cmp(&rhs_int))
+    }
 }
 
 fn oper_lt(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = (lhs.integer < rhs.integer) as u64;
-    let float = (lhs.float < rhs.float) as u64 as f64;
-    Ok(Number { integer, float })
+    Ok(Number::from_bool(cmp_nums(&nums[0], &nums[1]).is_some_and(Ordering::is_lt)))
 }
 
 fn oper_lte(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = (lhs.integer <= rhs.integer) as u64;
-    let float = (lhs.float <= rhs.float) as u64 as f64;
-    Ok(Number { integer, float })
+    Ok(Number::from_bool(cmp_nums(&nums[0], &nums[1]).is_some_and(Ordering::is_le)))
 }
 
 fn oper_gt(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = (lhs.integer > rhs.integer) as u64;
-    let float = (lhs.float > rhs.float) as u64 as f64;
-    Ok(Number { integer, float })
+    Ok(Number::from_bool(cmp_nums(&nums[0], &nums[1]).is_some_and(Ordering::is_gt)))
 }
 
 fn oper_gte(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = (lhs.integer >= rhs.integer) as u64;
-    let float = (lhs.float >= rhs.float) as u64 as f64;
-    Ok(Number { integer, float })
+    Ok(Number::from_bool(cmp_nums(&nums[0], &nums[1]).is_some_and(Ordering::is_ge)))
 }
 
 fn oper_eq(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = (lhs.integer == rhs.integer) as u64;
-    let float = cmp_eq_f64(lhs.float, rhs.float) as u64 as f64;
-    Ok(Number { integer, float })
+    Ok(Number::from_bool(cmp_nums(&nums[0], &nums[1]).is_some_and(Ordering::is_eq)))
 }
 
 fn oper_ne(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = (lhs.integer != rhs.integer) as u64;
-    let float = !cmp_eq_f64(lhs.float, rhs.float) as u64 as f64;
-    Ok(Number { integer, float })
-}
-
-fn oper_bit_and(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = lhs.integer & rhs.integer;
-    let float = integer as f64;
-    Ok(Number { integer, float })
-}
-
-fn oper_bit_xor(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = lhs.integer ^ rhs.integer;
-    let float = integer as f64;
-    Ok(Number { integer, float })
-}
-
-fn oper_bit_or(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
-    let lhs = nums[0];
-    let rhs = nums[1];
-    let integer = lhs.integer | rhs.integer;
-    let float = integer as f64;
-    Ok(Number { integer, float })
+    Ok(Number::from_bool(!cmp_nums(&nums[0], &nums[1]).is_some_and(Ordering::is_eq)))
+}
+
+// Bitwise operators on big integers behave as if negative integers had infinitely many sign bits.
+fn oper_bit_and(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(idx_expr, nums)?;
+    if let Some((lhs_big, rhs_big)) = big_ints(&nums[0], &nums[1]) {
+        return Ok(Number::from_big(lhs_big & rhs_big));
+    }
+    let (lhs_int, rhs_int, int_type) = common_ints(&nums[0], &nums[1]);
+    Ok(Number::from_int(lhs_int & rhs_int, int_type))
+}
+
+fn oper_bit_xor(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(idx_expr, nums)?;
+    if let Some((lhs_big, rhs_big)) = big_ints(&nums[0], &nums[1]) {
+        return Ok(Number::from_big(lhs_big ^ rhs_big));
+    }
+    let (lhs_int, rhs_int, int_type) = common_ints(&nums[0], &nums[1]);
+    Ok(Number::from_int(lhs_int ^ rhs_int, int_type))
+}
+
+fn oper_bit_or(idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    check_ints(idx_expr, nums)?;
+    if let Some((lhs_big, rhs_big)) = big_ints(&nums[0], &nums[1]) {
+        return Ok(Number::from_big(lhs_big | rhs_big));
+    }
+    let (lhs_int, rhs_int, int_type) = common_ints(&nums[0], &nums[1]);
+    Ok(Number::from_int(lhs_int | rhs_int, int_type))
+}
+
+fn oper_logical_and(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(Number::from_bool(!nums[0].is_zero() && !nums[1].is_zero()))
+}
+
+fn oper_logical_or(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    Ok(Number::from_bool(!nums[0].is_zero() || !nums[1].is_zero()))
+}
+
+fn oper_ternary(_idx_expr: usize, nums: &[Number]) -> Result<Number, ExprError> {
+    // Only the selected expression is evaluated, the other one is a placeholder.
+    if !nums[0].is_zero() {
+        Ok(nums[1].clone())
+    } else {
+        Ok(nums[2].clone())
+    }
 }
@@ -1,59 +1,80 @@
-use spceval::{ExprErrorKind, Number};
+use spceval::{
+    EvalContext, ExprError, ExprErrorKind, ExprKind, FuncRegistry, Function, IntMode, IntType, Number, Overflow, Span,
+};
+use std::ops::Range;
 
 #[inline(always)]
-fn test_valid_expr(str_expr: &str, num_expected: &Number) {
-    let res_eval = spceval::evaluate(str_expr);
+fn test_valid_expr_with(eval_ctx: &mut EvalContext, str_expr: &str, num_expected: &Number) {
+    let res_eval = spceval::evaluate_with(eval_ctx, str_expr);
     assert!(res_eval.is_ok(), "{} err={}", str_expr, res_eval.err().unwrap());
     let num_computed = res_eval.unwrap();
-    assert_eq!(num_expected.integer, num_computed.integer, "{}", str_expr);
-    let epsilon = f64::EPSILON;
-    assert!((num_expected.float - num_computed.float) < epsilon, "{}", str_expr);
+    match (num_expected, &num_computed) {
+        (Number::Float(float_expected), Number::Float(float_computed)) => {
+            let epsilon = f64::EPSILON;
+            assert!((float_expected - float_computed).abs() < epsilon, "{} = {}", str_expr, float_computed);
+        }
+        // Integers in arbitrary-precision mode are compared by their low bits and type.
+        (Number::Int { .. }, Number::Int { .. }) | (Number::Bool(_), Number::Bool(_)) => {
+            assert_eq!(num_expected.as_int(), num_computed.as_int(), "{}", str_expr);
+        }
+        _ => panic!("{} = {:?}, expected {:?}", str_expr, num_computed, num_expected),
+    }
 }
 
 #[inline(always)]
-fn test_invalid_expr(str_expr: &str, expr_error_kind: ExprErrorKind) {
-    let res_eval = spceval::evaluate(str_expr);
+fn test_valid_expr(str_expr: &str, num_expected: &Number) {
+    test_valid_expr_with(&mut EvalContext::new(), str_expr, num_expected);
+}
+
+#[inline(always)]
+fn test_invalid_expr_with(eval_ctx: &mut EvalContext, str_expr: &str, expr_error_kind: ExprErrorKind) {
+    let res_eval = spceval::evaluate_with(eval_ctx, str_expr);
     assert!(res_eval.is_err(), "{}", str_expr);
     assert_eq!(expr_error_kind, res_eval.err().unwrap().kind(), "{}", str_expr);
 }
 
+#[inline(always)]
+fn test_invalid_expr(str_expr: &str, expr_error_kind: ExprErrorKind) {
+    test_invalid_expr_with(&mut EvalContext::new(), str_expr, expr_error_kind);
+}
+
 #[test]
 fn valid_exprs_unary_opers() {
     #[rustfmt::skip]
     let expr_results = vec![
         // Unary minus
-        ("-0", Number { integer: 0, float: 0.0 }),
-        ("-1", Number { integer: -1i64 as u64, float: -1.0 }),
-        ("-120", Number { integer: -120i64 as u64, float: -120.0 }),
-        ("-(0)", Number { integer: 0, float: 0.0 }),
-        ("-(1)", Number { integer: -1i64 as u64, float: -1.0 }),
-        ("-(120)", Number { integer: -120i64 as u64, float: -120.0 }),
+        ("-0", Number::from_int(0, IntType::I64)),
+        ("-1", Number::from_int(-1i64 as u64 as u128, IntType::I64)),
+        ("-120", Number::from_int(-120i64 as u64 as u128, IntType::I64)),
+        ("-(0)", Number::from_int(0, IntType::I64)),
+        ("-(1)", Number::from_int(-1i64 as u64 as u128, IntType::I64)),
+        ("-(120)", Number::from_int(-120i64 as u64 as u128, IntType::I64)),
         // Logical NOT.
-        ("!0", Number { integer: 1, float: 1.0 }),
-        ("!1", Number { integer: 0, float: 0.0 }),
-        ("!2", Number { integer: 0, float: 0.0 }),
-        ("!123", Number { integer: 0, float: 0.0 }),
-        ("!(0)", Number { integer: 1, float: 1.0 }),
-        ("!(1)", Number { integer: 0, float: 0.0 }),
-        ("!(123)", Number { integer: 0, float: 0.0 }),
-        ("!(-1)", Number { integer: 0, float: 0.0 }),
-        ("!(-2)", Number { integer: 0, float: 0.0 }),
-        ("!(-123)", Number { integer: 0, float: 0.0 }),
+        ("!0", Number::from_bool(true)),
+        ("!1", Number::from_bool(false)),
+        ("!2", Number::from_bool(false)),
+        ("!123", Number::from_bool(false)),
+        ("!(0)", Number::from_bool(true)),
+        ("!(1)", Number::from_bool(false)),
+        ("!(123)", Number::from_bool(false)),
+        ("!(-1)", Number::from_bool(false)),
+        ("!(-2)", Number::from_bool(false)),
+        ("!(-123)", Number::from_bool(false)),
         // Bitwise NOT.
-        ("~0", Number { integer: !0u64, float: !0u64 as f64 }),
-        ("~1", Number { integer: !1u64, float: !1u64 as f64 }),
-        ("~2", Number { integer: !2u64, float: !2u64 as f64 }),
-        ("~145", Number { integer: !145u64, float: !145u64 as f64 }),
-        ("~(0)", Number { integer: !0u64, float: !0u64 as f64 }),
-        ("~(1)", Number { integer: !1u64, float: !1u64 as f64 }),
-        ("~(2)", Number { integer: !2u64, float: !2u64 as f64 }),
-        ("~(145)", Number { integer: !145u64, float: !145u64 as f64 }),
-        ("~(-1)", Number { integer: !-1i64 as u64, float: !-1i64 as u64 as f64 }),
-        ("~(-2)", Number { integer: !-2i64 as u64, float: !-2i64 as u64 as f64 }),
-        ("~(-145)", Number { integer: !-145i64 as u64, float: !-145i64 as u64 as f64 }),
+        ("~0", Number::from_int(!0u64 as u128, IntType::I64)),
+        ("~1", Number::from_int(!1u64 as u128, IntType::I64)),
+        ("~2", Number::from_int(!2u64 as u128, IntType::I64)),
+        ("~145", Number::from_int(!145u64 as u128, IntType::I64)),
+        ("~(0)", Number::from_int(!0u64 as u128, IntType::I64)),
+        ("~(1)", Number::from_int(!1u64 as u128, IntType::I64)),
+        ("~(2)", Number::from_int(!2u64 as u128, IntType::I64)),
+        ("~(145)", Number::from_int(!145u64 as u128, IntType::I64)),
+        ("~(-1)", Number::from_int(!-1i64 as u64 as u128, IntType::I64)),
+        ("~(-2)", Number::from_int(!-2i64 as u64 as u128, IntType::I64)),
+        ("~(-145)", Number::from_int(!-145i64 as u64 as u128, IntType::I64)),
     ];
     for expr_res in expr_results {
-        test_valid_expr(&expr_res.0, &expr_res.1);
+        test_valid_expr(expr_res.0, &expr_res.1);
     }
 }
 
@@ -62,163 +83,146 @@ fn valid_exprs_binary_opers() {
     #[rustfmt::skip]
     let expr_results = vec![
         // Add
-        ("0+0", Number { integer: 0, float: 0.0 }),
-        ("0+1", Number { integer: 1, float: 1.0 }),
-        ("1+1", Number { integer: 2, float: 2.0 }),
-        ("2+2", Number { integer: 4, float: 4.0 }),
-        ("132+132", Number { integer: 132+132, float: (132+132) as f64 }),
-        ("0xf0f0f0f0+0", Number { integer: 0xf0f0f0f0, float: 0xf0f0f0f0u64 as f64 }),
-        ("0xf0f0f0f0+1", Number { integer: 0xf0f0f0f0u64.wrapping_add(1), float: (0xf0f0f0f0u64) as f64 + 1.0 }),
+        ("0+0", Number::from_int(0, IntType::I64)),
+        ("0+1", Number::from_int(1, IntType::I64)),
+        ("1+1", Number::from_int(2, IntType::I64)),
+        ("2+2", Number::from_int(4, IntType::I64)),
+        ("132+132", Number::from_int(132+132, IntType::I64)),
+        ("0xf0f0f0f0+0", Number::from_int(0xf0f0f0f0, IntType::I64)),
+        ("0xf0f0f0f0+1", Number::from_int(0xf0f0f0f0u64.wrapping_add(1) as u128, IntType::I64)),
         ("0xf0f0f0f0+0xf0f0f0f0",
-            Number { integer: 0xf0f0f0f0u64.wrapping_add(0xf0f0f0f0),
-                     float: (0xf0f0f0f0u64.wrapping_add(0xf0f0f0f0)) as f64 }),
+            Number::from_int(0xf0f0f0f0u64.wrapping_add(0xf0f0f0f0) as u128, IntType::I64)),
         ("0xf0f0f0f0f0f0f0f0+0",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_add(0),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_add(0) as u128, IntType::U64)),
         ("0xf0f0f0f0f0f0f0f0+1",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_add(1),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 + 1.0 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_add(1) as u128, IntType::U64)),
         ("0xf0f0f0f0f0f0f0f0+0xf0f0f0f0f0f0f0f0",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_add(0xf0f0f0f0f0f0f0f0),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 + 0xf0f0f0f0f0f0f0f0u64 as f64 }),
-        ("0xffffffff+0", Number { integer: 0xffffffff, float: 0xffffffffu64 as f64 }),
-        ("0xffffffff+1", Number { integer: 0xffffffff+1, float: (0xffffffffu64+1u64) as f64 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_add(0xf0f0f0f0f0f0f0f0) as u128, IntType::U64)),
+        ("0xffffffff+0", Number::from_int(0xffffffff, IntType::I64)),
+        ("0xffffffff+1", Number::from_int(0xffffffff+1, IntType::I64)),
         ("0xffffffff+0xffffffff",
-            Number { integer: 0xffffffffu64.wrapping_add(0xffffffff),
-                     float: (0xffffffffu64.wrapping_add(0xffffffff)) as f64 }),
+            Number::from_int(0xffffffffu64.wrapping_add(0xffffffff) as u128, IntType::I64)),
         ("0xffffffffffffffff+0",
-            Number { integer: 0xffffffffffffffffu64.wrapping_add(0),
-                     float: 0xffffffffffffffffu64 as f64 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_add(0) as u128, IntType::U64)),
         ("0xffffffffffffffff+1",
-            Number { integer: 0xffffffffffffffffu64.wrapping_add(1),
-                     float: 0xffffffffffffffffu64 as f64 + 1.0 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_add(1) as u128, IntType::U64)),
         ("0xffffffffffffffff+0xffffffffffffffff",
-            Number { integer: 0xffffffffffffffffu64.wrapping_add(0xffffffffffffffff),
-                     float: 0xffffffffffffffffu64 as f64 + 0xffffffffffffffffu64 as f64 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_add(0xffffffffffffffff) as u128, IntType::U64)),
 
         // Subtract
-        ("0-0", Number { integer: 0, float: 0.0 }),
-        ("0-1", Number { integer: 0u64.wrapping_sub(1), float: -1.0 }),
-        ("1-1", Number { integer: 0, float: 0.0 }),
-        ("12-2", Number { integer: 10, float: 10.0 }),
-        ("132-100", Number { integer: 132-100, float: (132-100) as f64 }),
-
-        ("0xf0f0f0f0-0", Number { integer: 0xf0f0f0f0, float: 0xf0f0f0f0u64 as f64 }),
-        ("0xf0f0f0f0-1", Number { integer: 0xf0f0f0f0u64.wrapping_sub(1), float: 0xf0f0f0f0u64 as f64 - 1.0 }),
+        ("0-0", Number::from_int(0, IntType::I64)),
+        ("0-1", Number::from_int(0u64.wrapping_sub(1) as u128, IntType::I64)),
+        ("1-1", Number::from_int(0, IntType::I64)),
+        ("12-2", Number::from_int(10, IntType::I64)),
+        ("132-100", Number::from_int(132-100, IntType::I64)),
+
+        ("0xf0f0f0f0-0", Number::from_int(0xf0f0f0f0, IntType::I64)),
+        ("0xf0f0f0f0-1", Number::from_int(0xf0f0f0f0u64.wrapping_sub(1) as u128, IntType::I64)),
         ("0xf0f0f0f0-0xf0f0f0f0",
-            Number { integer: 0xf0f0f0f0u64.wrapping_sub(0xf0f0f0f0),
-                     float: 0xf0f0f0f0u64 as f64 - 0xf0f0f0f0u64 as f64 }),
+            Number::from_int(0xf0f0f0f0u64.wrapping_sub(0xf0f0f0f0) as u128, IntType::I64)),
         ("0xf0f0f0f0f0f0f0f0-0",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_sub(0),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_sub(0) as u128, IntType::U64)),
         ("0xf0f0f0f0f0f0f0f0-1",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_sub(1),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 - 1.0 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_sub(1) as u128, IntType::U64)),
         ("0xf0f0f0f0f0f0f0f0-0xf0f0f0f0f0f0f0f0",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_sub(0xf0f0f0f0f0f0f0f0),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 - 0xf0f0f0f0f0f0f0f0u64 as f64 }),
-        ("0xffffffff-0", Number { integer: 0xffffffff, float: 0xffffffffu64 as f64 }),
-        ("0xffffffff-1", Number { integer: 0xffffffff-1, float: 0xffffffffu64 as f64 - 1.0 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_sub(0xf0f0f0f0f0f0f0f0) as u128, IntType::U64)),
+        ("0xffffffff-0", Number::from_int(0xffffffff, IntType::I64)),
+        ("0xffffffff-1", Number::from_int(0xffffffff-1, IntType::I64)),
         ("0xffffffff-0xffffffff",
-            Number { integer: 0xffffffffu64.wrapping_sub(0xffffffff),
-                     float: (0xffffffffu64-0xffffffffu64) as f64 }),
+            Number::from_int(0xffffffffu64.wrapping_sub(0xffffffff) as u128, IntType::I64)),
         ("0xffffffffffffffff-0",
-            Number { integer: 0xffffffffffffffffu64.wrapping_sub(0),
-                     float: 0xffffffffffffffffu64 as f64 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_sub(0) as u128, IntType::U64)),
         ("0xffffffffffffffff-1",
-            Number { integer: 0xffffffffffffffffu64.wrapping_sub(1),
-                     float: 0xffffffffffffffffu64 as f64 - 1.0 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_sub(1) as u128, IntType::U64)),
         ("0xffffffffffffffff-0xffffffffffffffff",
-            Number { integer: 0xffffffffffffffffu64.wrapping_sub(0xffffffffffffffff),
-                     float: 0xffffffffffffffffu64 as f64 - 0xffffffffffffffffu64 as f64 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_sub(0xffffffffffffffff) as u128, IntType::U64)),
 
         // Multiply
-        ("0*0", Number { integer: 0, float: 0.0 }),
-        ("0*1", Number { integer: 0u64.wrapping_mul(1), float: 0.0 }),
-        ("1*1", Number { integer: 1, float: 1.0 }),
-        ("12*12", Number { integer: 12u64.wrapping_mul(12), float: 144.0 }),
-        ("132*100", Number { integer: 13200, float: 13200.0 }),
-
-        ("0xf0f0f0f0*0", Number { integer: 0, float: 0.0 }),
-        ("0xf0f0f0f0*1", Number { integer: 0xf0f0f0f0, float: 0xf0f0f0f0u64 as f64 * 1.0 }),
+        ("0*0", Number::from_int(0, IntType::I64)),
+        ("0*1", Number::from_int(0u64.wrapping_mul(1) as u128, IntType::I64)),
+        ("1*1", Number::from_int(1, IntType::I64)),
+        ("12*12", Number::from_int(12u64.wrapping_mul(12) as u128, IntType::I64)),
+        ("132*100", Number::from_int(13200, IntType::I64)),
+
+        ("0xf0f0f0f0*0", Number::from_int(0, IntType::I64)),
+        ("0xf0f0f0f0*1", Number::from_int(0xf0f0f0f0, IntType::I64)),
         ("0xf0f0f0f0*0xf0f0f0f0",
-            Number { integer: 0xf0f0f0f0u64.wrapping_mul(0xf0f0f0f0),
-                     float: 0xf0f0f0f0u64 as f64 * 0xf0f0f0f0u64 as f64 }),
-        ("0xf0f0f0f0f0f0f0f0*0", Number { integer: 0, float: 0.0 }),
+            Number::from_int(0xf0f0f0f0u64.wrapping_mul(0xf0f0f0f0) as u128, IntType::I64)),
+        ("0xf0f0f0f0f0f0f0f0*0", Number::from_int(0, IntType::U64)),
         ("0xf0f0f0f0f0f0f0f0*1",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_mul(1),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 * 1.0 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_mul(1) as u128, IntType::U64)),
         ("0xf0f0f0f0f0f0f0f0*0xf0f0f0f0f0f0f0f0",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_mul(0xf0f0f0f0f0f0f0f0),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 * 0xf0f0f0f0f0f0f0f0u64 as f64 }),
-        ("0xffffffff*0", Number { integer: 0, float: 0.0 }),
-        ("0xffffffff*1", Number { integer: 0xffffffff, float: 0xffffffffu64 as f64 * 1.0 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_mul(0xf0f0f0f0f0f0f0f0) as u128, IntType::U64)),
+        ("0xffffffff*0", Number::from_int(0, IntType::I64)),
+        ("0xffffffff*1", Number::from_int(0xffffffff, IntType::I64)),
         ("0xffffffff*0xffffffff",
-            Number { integer: 0xffffffffu64.wrapping_mul(0xffffffff),
-                     float: 0xffffffffu64 as f64 * 0xffffffffu64 as f64 }),
+            Number::from_int(0xffffffffu64.wrapping_mul(0xffffffff) as u128, IntType::I64)),
         ("0xffffffffffffffff*0",
-            Number { integer: 0, float: 0.0 }),
+            Number::from_int(0, IntType::U64)),
         ("0xffffffffffffffff*1",
-            Number { integer: 0xffffffffffffffffu64.wrapping_mul(1),
-                     float: 0xffffffffffffffffu64 as f64 * 1.0 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_mul(1) as u128, IntType::U64)),
         ("0xffffffffffffffff*0xffffffffffffffff",
-            Number { integer: 0xffffffffffffffffu64.wrapping_mul(0xffffffffffffffff),
-                     float: 0xffffffffffffffffu64 as f64 * 0xffffffffffffffffu64 as f64 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_mul(0xffffffffffffffff) as u128, IntType::U64)),
 
         // Divide
-        ("1/1", Number { integer: 1, float: 1.0 }),
-        ("12/6", Number { integer: 12u64.wrapping_div(6), float: 2.0 }),
-        ("132/100", Number { integer: 132u64.wrapping_div(100), float: 132.0 / 100.0 }),
+        ("1/1", Number::from_int(1, IntType::I64)),
+        ("12/6", Number::from_int(12u64.wrapping_div(6) as u128, IntType::I64)),
+        ("132/100", Number::from_int(132u64.wrapping_div(100) as u128, IntType::I64)),
 
-        ("0xf0f0f0f0/1", Number { integer: 0xf0f0f0f0, float: 0xf0f0f0f0u64 as f64 / 1.0 }),
+        ("0xf0f0f0f0/1", Number::from_int(0xf0f0f0f0, IntType::I64)),
         ("0xf0f0f0f0/0xf0f0f0f0",
-            Number { integer: 0xf0f0f0f0u64.wrapping_div(0xf0f0f0f0),
-                     float: 0xf0f0f0f0u64 as f64 / 0xf0f0f0f0u64 as f64 }),
+            Number::from_int(0xf0f0f0f0u64.wrapping_div(0xf0f0f0f0) as u128, IntType::I64)),
         ("0xf0f0f0f0f0f0f0f0/1",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_div(1),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 / 1.0 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_div(1) as u128, IntType::U64)),
         ("0xf0f0f0f0f0f0f0f0/0xf0f0f0f0f0f0f0f0",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_div(0xf0f0f0f0f0f0f0f0),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 / 0xf0f0f0f0f0f0f0f0u64 as f64 }),
-        ("0xffffffff/32", Number { integer: 0xffffffffu64 / 32u64, float: 0xffffffffu64 as f64 / 32.0 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_div(0xf0f0f0f0f0f0f0f0) as u128, IntType::U64)),
+        ("0xffffffff/32", Number::from_int((0xffffffffu64 / 32u64) as u128, IntType::I64)),
         ("0xffffffff/0xffffffff",
-            Number { integer: 0xffffffffu64.wrapping_div(0xffffffff),
-                     float: 0xffffffffu64 as f64 / 0xffffffffu64 as f64 }),
+            Number::from_int(0xffffffffu64.wrapping_div(0xffffffff) as u128, IntType::I64)),
         ("0xffffffffffffffff/1",
-            Number { integer: 0xffffffffffffffffu64.wrapping_div(1),
-                     float: 0xffffffffffffffffu64 as f64 / 1.0 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_div(1) as u128, IntType::U64)),
         ("0xffffffffffffffff/0xffffffffffffffff",
-            Number { integer: 0xffffffffffffffffu64.wrapping_div(0xffffffffffffffff),
-                     float: 0xffffffffffffffffu64 as f64 / 0xffffffffffffffffu64 as f64 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_div(0xffffffffffffffff) as u128, IntType::U64)),
 
         // Remainder
-        ("1%1", Number { integer: 0, float: 0.0 }),
-        ("12%6", Number { integer: 12u64.wrapping_rem(6), float: 12.0 % 6.0 }),
-        ("132%100", Number { integer: 132u64.wrapping_rem(100), float: 132.0 % 100.0 }),
+        ("1%1", Number::from_int(0, IntType::I64)),
+        ("12%6", Number::from_int(12u64.wrapping_rem(6) as u128, IntType::I64)),
+        ("132%100", Number::from_int(132u64.wrapping_rem(100) as u128, IntType::I64)),
 
-        ("0xf0f0f0f0%1", Number { integer: 0xf0f0f0f0u64.wrapping_rem(1), float: 0xf0f0f0f0u64 as f64 % 1.0 }),
+        ("0xf0f0f0f0%1", Number::from_int(0xf0f0f0f0u64.wrapping_rem(1) as u128, IntType::I64)),
         ("0xf0f0f0f0%0xf0f0f0f0",
-            Number { integer: 0xf0f0f0f0u64.wrapping_rem(0xf0f0f0f0),
-                     float: 0xf0f0f0f0u64 as f64 % 0xf0f0f0f0u64 as f64 }),
+            Number::from_int(0xf0f0f0f0u64.wrapping_rem(0xf0f0f0f0) as u128, IntType::I64)),
         ("0xf0f0f0f0f0f0f0f0%3",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_rem(3),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 % 3.0 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_rem(3) as u128, IntType::U64)),
         ("0xf0f0f0f0f0f0f0f0%0xf1f2f3f4f5f6f7f8",
-            Number { integer: 0xf0f0f0f0f0f0f0f0u64.wrapping_rem(0xf1f2f3f4f5f6f7f8),
-                     float: 0xf0f0f0f0f0f0f0f0u64 as f64 % 0xf1f2f3f4f5f6f7f8u64 as f64 }),
-        ("0xffffffff%255", Number { integer: 0xffffffffu64.wrapping_rem(255), float: 0xffffffffu64 as f64 % 255.0 }),
+            Number::from_int(0xf0f0f0f0f0f0f0f0u64.wrapping_rem(0xf1f2f3f4f5f6f7f8) as u128, IntType::U64)),
+        ("0xffffffff%255", Number::from_int(0xffffffffu64.wrapping_rem(255) as u128, IntType::I64)),
         ("0xffffffff%0xffffffff",
-            Number { integer: 0xffffffffu64.wrapping_rem(0xffffffff),
-                     float: 0xffffffffu64 as f64 % 0xffffffffu64 as f64 }),
+            Number::from_int(0xffffffffu64.wrapping_rem(0xffffffff) as u128, IntType::I64)),
         ("0xffffffffffffffff%1",
-            Number { integer: 0xffffffffffffffffu64.wrapping_rem(1),
-                     float: 0xffffffffffffffffu64 as f64 % 1.0 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_rem(1) as u128, IntType::U64)),
         ("0xffffffffffffffff%0xf0f0f0f0f0f0f0f0",
-            Number { integer: 0xffffffffffffffffu64.wrapping_rem(0xf0f0f0f0f0f0f0f0),
-                     float: 0xffffffffffffffffu64 as f64 % 0xf0f0f0f0f0f0f0f0u64 as f64 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_rem(0xf0f0f0f0f0f0f0f0) as u128, IntType::U64)),
         ("0xffffffffffffffff%0xffffffffffffffff",
-            Number { integer: 0xffffffffffffffffu64.wrapping_rem(0xffffffffffffffff),
-                     float: 0xffffffffffffffffu64 as f64 % 0xffffffffffffffffu64 as f64 }),
+            Number::from_int(0xffffffffffffffffu64.wrapping_rem(0xffffffffffffffff) as u128, IntType::U64)),
+
+        // Exponentiation
+        ("2**10", Number::from_int(1024, IntType::I64)),
+        ("2 ** 3 ** 2", Number::from_int(512, IntType::I64)),
+        ("(2 ** 3) ** 2", Number::from_int(64, IntType::I64)),
+        ("3 * 2 ** 4 + 1", Number::from_int(49, IntType::I64)),
+        ("-2 ** 2", Number::from_int(-4i64 as u64 as u128, IntType::I64)),
+        ("(0 - 2) ** 3", Number::from_int(-8i64 as u64 as u128, IntType::I64)),
+        ("2 ** 62", Number::from_int(1 << 62, IntType::I64)),
+        ("(0 - 2) ** 63", Number::from_int(i64::MIN as u64 as u128, IntType::I64)),
+        ("(u64)2 ** 63", Number::from_int(1 << 63, IntType::U64)),
+        ("2 ** (u8)10", Number::from_int(1024, IntType::I64)),
+        ("(u8)2 ** 7", Number::from_int(128, IntType::U8)),
+        ("-(i8)2 ** 2", Number::from_int(-4i8 as u8 as u128, IntType::I8)),
+        ("(u16)2 ** 2 ** 3", Number::from_int(256, IntType::U16)),
+        ("2 ** 0.5", Number::from_f64(2f64.sqrt())),
+        ("2.0 ** 3 ** 2", Number::from_f64(512.0)),
+        ("10 ** 3 as u16", Number::from_int(1000, IntType::I64)),
 
         // Left shift
         // Right shift
@@ -232,10 +236,52 @@ fn valid_exprs_binary_opers() {
         // Bitwise XOR
         // Bitwise OR
         // Logical AND
+        ("0&&0", Number::from_bool(false)),
+        ("0&&1", Number::from_bool(false)),
+        ("1&&0", Number::from_bool(false)),
+        ("1&&1", Number::from_bool(true)),
+        ("5&&0xf0", Number::from_bool(true)),
+        ("0 && 1/0", Number::from_bool(false)),
+        ("0 != 0 && 100 / 0 > 2", Number::from_bool(false)),
+        ("5 != 0 && 100 / 5 > 2", Number::from_bool(true)),
+        ("(0 && 1/0) + 7", Number::from_int(7, IntType::I64)),
+        ("1 && 0 && 1/0", Number::from_bool(false)),
+
         // Logical OR
+        ("0||0", Number::from_bool(false)),
+        ("0||1", Number::from_bool(true)),
+        ("1||0", Number::from_bool(true)),
+        ("1||1", Number::from_bool(true)),
+        ("0||0xf0", Number::from_bool(true)),
+        ("1 || 1/0", Number::from_bool(true)),
+        ("0 || 1 || 1/0", Number::from_bool(true)),
+        ("0 && 1/0 || 2", Number::from_bool(true)),
+        ("1 || 1/0 && 1/0", Number::from_bool(true)),
+        ("sum(1 || 1/0, 0 && 1/0, 5)", Number::from_int(6, IntType::I64)),
+
+        // Ternary conditional
+        ("1 ? 2 : 3", Number::from_int(2, IntType::I64)),
+        ("0 ? 2 : 3", Number::from_int(3, IntType::I64)),
+        ("0xf0 ? 0x10 : 0x20", Number::from_int(0x10, IntType::I64)),
+        ("1 ? 4 : 1/0", Number::from_int(4, IntType::I64)),
+        ("0 ? 1/0 : 5", Number::from_int(5, IntType::I64)),
+        ("1 + 1 ? 2 + 3 : 4 * 5", Number::from_int(5, IntType::I64)),
+        ("1 - 1 ? 2 + 3 : 4 * 5", Number::from_int(20, IntType::I64)),
+        ("0 || 1 ? 2 : 3", Number::from_int(2, IntType::I64)),
+        ("1 ? 0 : 1 || 1", Number::from_int(0, IntType::I64)),
+        ("0 ? 1 : 0 ? 2 : 3", Number::from_int(3, IntType::I64)),
+        ("0 ? 1 : 1 ? 2 : 3", Number::from_int(2, IntType::I64)),
+        ("1 ? 0 ? 5 : 6 : 7", Number::from_int(6, IntType::I64)),
+        ("1 ? 2 ? 5 : 6 : 7", Number::from_int(5, IntType::I64)),
+        ("0 ? 2 ? 5 : 6 : 7", Number::from_int(7, IntType::I64)),
+        ("(1 ? 2 : 3) + 1", Number::from_int(3, IntType::I64)),
+        ("(0 ? 1 : 2) ? 3 : 4", Number::from_int(3, IntType::I64)),
+        ("0x1003 & 0xfff ? (0x1003 + 0xfff) & ~0xfff : 0x1003", Number::from_int(0x2000, IntType::I64)),
+        ("0x2000 & 0xfff ? (0x2000 + 0xfff) & ~0xfff : 0x2000", Number::from_int(0x2000, IntType::I64)),
+        ("avg(1 ? 2 : 3, 0 ? 1/0 : 4)", Number::from_int(3, IntType::I64)),
     ];
     for expr_res in expr_results {
-        test_valid_expr(&expr_res.0, &expr_res.1);
+        test_valid_expr(expr_res.0, &expr_res.1);
     }
 }
 
@@ -244,71 +290,81 @@ fn valid_exprs_funcs() {
     #[rustfmt::skip]
     let expr_results = vec![
         // avg
-        ("avg(0,0)", Number { integer: 0, float: 0 as f64 }),
-        ("avg(1,3)", Number { integer: 2, float: 2 as f64 }),
-        ("avg(0xf,0x1e,0x2d)", Number { integer: 0x1e, float: 0x1e as f64 }),
-        ("avg(1,2,3,4,5,6,7,8,9,10)", Number { integer: 5, float: 5.5f64 }),
-        ("avg(35,75,125,25,45) + avg(1,2,3)", Number { integer: 63, float: 63.0 }),
+        ("avg(0,0)", Number::from_int(0, IntType::I64)),
+        ("avg(1,3)", Number::from_int(2, IntType::I64)),
+        ("avg(0xf,0x1e,0x2d)", Number::from_int(0x1e, IntType::I64)),
+        ("avg(1,2,3,4,5,6,7,8,9,10)", Number::from_int(5, IntType::I64)),
+        ("avg(35,75,125,25,45) + avg(1,2,3)", Number::from_int(63, IntType::I64)),
 
         // bit
-        ("bit(0)", Number { integer: 1_u64.wrapping_shl(0), float: 1_u64.wrapping_shl(0) as f64 }),
-        ("bit(1)", Number { integer: 1_u64.wrapping_shl(1), float: 1_u64.wrapping_shl(1) as f64 }),
-        ("bit(31)", Number { integer: 1_u64.wrapping_shl(31), float: 1_u64.wrapping_shl(31) as f64 }),
-        ("bit(63)", Number { integer: 1_u64.wrapping_shl(63), float: 1_u64.wrapping_shl(63) as f64 }),
-        ("bit(0) | bit(1) | bit(2)", Number { integer: 7, float: 7.0 }),
+        ("bit(0)", Number::from_int(1_u64.wrapping_shl(0) as u128, IntType::I64)),
+        ("bit(1)", Number::from_int(1_u64.wrapping_shl(1) as u128, IntType::I64)),
+        ("bit(31)", Number::from_int(1_u64.wrapping_shl(31) as u128, IntType::I64)),
+        ("bit(63)", Number::from_int(1_u64.wrapping_shl(63) as u128, IntType::U64)),
+        ("bit(0) | bit(1) | bit(2)", Number::from_int(7, IntType::I64)),
 
         // bits
-        ("bits(0,0)", Number { integer: 1, float: 1.0 }),
-        ("bits(0,1)", Number { integer: 3, float: 3.0 }),
-        ("bits(1,0)", Number { integer: 3, float: 3.0 }),
-        ("bits(1,1)", Number { integer: 2, float: 2.0 }),
-        ("bits(0,2)", Number { integer: 7, float: 7.0 }),
-        ("bits(2,0)", Number { integer: 7, float: 7.0 }),
-        ("bits(0,63)", Number { integer: 0xffffffffffffffffu64, float: 0xffffffffffffffffu64 as f64 }),
-        ("bits(63,0)", Number { integer: 0xffffffffffffffffu64, float: 0xffffffffffffffffu64 as f64 }),
-        ("bits(0,31)", Number { integer: 0xffffffffu64, float: 0xffffffffu64 as f64 }),
-        ("bits(31,0)", Number { integer: 0xffffffffu64, float: 0xffffffffu64 as f64 }),
-        ("bits(32,63)", Number { integer: 0xffffffff00000000u64, float: 0xffffffff00000000u64 as f64 }),
-        ("bits(63,32)", Number { integer: 0xffffffff00000000u64, float: 0xffffffff00000000u64 as f64 }),
+        ("bits(0,0)", Number::from_int(1, IntType::I64)),
+        ("bits(0,1)", Number::from_int(3, IntType::I64)),
+        ("bits(1,0)", Number::from_int(3, IntType::I64)),
+        ("bits(1,1)", Number::from_int(2, IntType::I64)),
+        ("bits(0,2)", Number::from_int(7, IntType::I64)),
+        ("bits(2,0)", Number::from_int(7, IntType::I64)),
+        ("bits(0,63)", Number::from_int(0xffffffffffffffffu64 as u128, IntType::U64)),
+        ("bits(63,0)", Number::from_int(0xffffffffffffffffu64 as u128, IntType::U64)),
+        ("bits(0,31)", Number::from_int(0xffffffffu64 as u128, IntType::I64)),
+        ("bits(31,0)", Number::from_int(0xffffffffu64 as u128, IntType::I64)),
+        ("bits(32,63)", Number::from_int(0xffffffff00000000u64 as u128, IntType::U64)),
+        ("bits(63,32)", Number::from_int(0xffffffff00000000u64 as u128, IntType::U64)),
+
+        // if
+        ("if(1, 2, 3)", Number::from_int(2, IntType::I64)),
+        ("if(0, 2, 3)", Number::from_int(3, IntType::I64)),
+        ("if(0, 1/0, 4)", Number::from_int(4, IntType::I64)),
+        ("if(1, 5, 1/0)", Number::from_int(5, IntType::I64)),
+        ("if(1 && 0, 1, 0 || 2)", Number::from_bool(true)),
+        ("if(1 > 2, bit(64), if(0, 1/0, bit(4)))", Number::from_int(0x10, IntType::I64)),
+        ("if(0 ? 1 : 0, 1/0, 1 ? 8 : 1/0)", Number::from_int(8, IntType::I64)),
+        ("if((1), (2), (3)) + 1", Number::from_int(3, IntType::I64)),
 
         // is_pow_of_two
-        ("is_pow_of_two(0)", Number { integer: 0, float: 0 as f64 }),
-        ("is_pow_of_two(1)", Number { integer: 1, float: 1 as f64 }),
-        ("is_pow_of_two(2)", Number { integer: 1, float: 1 as f64 }),
-        ("is_pow_of_two(3)", Number { integer: 0, float: 0 as f64 }),
-        ("is_pow_of_two(4)", Number { integer: 1, float: 1 as f64 }),
-        ("is_pow_of_two(5)", Number { integer: 0, float: 0 as f64 }),
-        ("is_pow_of_two(6)", Number { integer: 0, float: 0 as f64 }),
-        ("is_pow_of_two(7)", Number { integer: 0, float: 0 as f64 }),
-        ("is_pow_of_two(8)", Number { integer: 1, float: 1 as f64 }),
-        ("is_pow_of_two(9)", Number { integer: 0, float: 0 as f64 }),
-        ("is_pow_of_two(10)", Number { integer: 0, float: 0 as f64 }),
-        ("is_pow_of_two(11)", Number { integer: 0, float: 0 as f64 }),
-        ("is_pow_of_two(12)", Number { integer: 0, float: 0 as f64 }),
-        ("is_pow_of_two(16)", Number { integer: 1, float: 1 as f64 }),
-        ("is_pow_of_two(32)", Number { integer: 1, float: 1 as f64 }),
-        ("is_pow_of_two(64)", Number { integer: 1, float: 1 as f64 }),
+        ("is_pow_of_two(0)", Number::from_bool(false)),
+        ("is_pow_of_two(1)", Number::from_bool(true)),
+        ("is_pow_of_two(2)", Number::from_bool(true)),
+        ("is_pow_of_two(3)", Number::from_bool(false)),
+        ("is_pow_of_two(4)", Number::from_bool(true)),
+        ("is_pow_of_two(5)", Number::from_bool(false)),
+        ("is_pow_of_two(6)", Number::from_bool(false)),
+        ("is_pow_of_two(7)", Number::from_bool(false)),
+        ("is_pow_of_two(8)", Number::from_bool(true)),
+        ("is_pow_of_two(9)", Number::from_bool(false)),
+        ("is_pow_of_two(10)", Number::from_bool(false)),
+        ("is_pow_of_two(11)", Number::from_bool(false)),
+        ("is_pow_of_two(12)", Number::from_bool(false)),
+        ("is_pow_of_two(16)", Number::from_bool(true)),
+        ("is_pow_of_two(32)", Number::from_bool(true)),
+        ("is_pow_of_two(64)", Number::from_bool(true)),
 
         // sum
-        ("sum(0,0)", Number { integer: 0, float: 0 as f64 }),
-        ("sum(1,3)", Number { integer: 4, float: 4 as f64 }),
-        ("sum(0xffff,0xffffffff)", Number { integer: 0xffff + 0xffffffff_u64, float: (0xffff + 0xffffffff_u64) as f64 }),
-        ("sum(-1,4)", Number { integer: 3, float: 3 as f64 }),
-        ("sum(-5,-5,10)", Number { integer: 0, float: 0 as f64 }),
-        ("sum(10,5) * sum(1,2)", Number { integer: 45, float: 45.0 }),
+        ("sum(0,0)", Number::from_int(0, IntType::I64)),
+        ("sum(1,3)", Number::from_int(4, IntType::I64)),
+        ("sum(0xffff,0xffffffff)", Number::from_int((0xffff + 0xffffffff_u64) as u128, IntType::I64)),
+        ("sum(-1,4)", Number::from_int(3, IntType::I64)),
+        ("sum(-5,-5,10)", Number::from_int(0, IntType::I64)),
+        ("sum(10,5) * sum(1,2)", Number::from_int(45, IntType::I64)),
 
         // cel2far
-        ("cel2far(32)",  Number { integer: 89, float: 89.6 }),
-        ("cel2far(0)",   Number { integer: 32, float: 32.0 }),
-        ("cel2far(-32)", Number { integer: 0, float: -25.6 }),
+        ("cel2far(32)",  Number::from_f64(89.6)),
+        ("cel2far(0)",   Number::from_f64(32.0)),
+        ("cel2far(-32)", Number::from_f64(-25.6)),
 
         // far2cel
-        ("far2cel(32)",  Number { integer: 0, float: 0.0 }),
-        ("far2cel(0)",   Number { integer: 0, float: -17.78 }),
-        ("far2cel(-32)", Number { integer: 0, float: -35.556 }),
+        ("far2cel(32)",  Number::from_f64(0.0)),
+        ("far2cel(0)",   Number::from_f64(-160.0 / 9.0)),
+        ("far2cel(-32)", Number::from_f64(-320.0 / 9.0)),
     ];
     for expr_res in expr_results {
-        test_valid_expr(&expr_res.0, &expr_res.1);
+        test_valid_expr(expr_res.0, &expr_res.1);
     }
 }
 
@@ -328,47 +384,51 @@ fn valid_exprs() {
     // paranthesis, operator priority etc.
 
     const MAX_VALID_PARENS: usize = spceval::max_sub_expressions() - 1;
-    debug_assert!((u8::MIN as usize ..= u8::MAX as usize).contains(&MAX_VALID_PARENS)); // Sanity, arbitrary bounds check before str::repeat().
+    debug_assert!((u8::MIN as usize..=u8::MAX as usize).contains(&MAX_VALID_PARENS)); // Sanity, arbitrary bounds check before str::repeat().
     let open_parens = "(".repeat(MAX_VALID_PARENS);
     let close_parens = ")".repeat(MAX_VALID_PARENS);
     let num_max_sub_expr = 0xf0e1d2c3b4a59687;
-    let max_sub_expr = format!("{}{}{}", open_parens, num_max_sub_expr.to_string(), close_parens);
+    let max_sub_expr = format!("{}{}{}", open_parens, num_max_sub_expr, close_parens);
 
     #[rustfmt::skip]
     let expr_results = vec![
-        ("2+2", Number { integer: 4, float: 4.0 }),
-        ("-4", Number { integer: 0xfffffffffffffffc, float: -4.0 }),
-        ("-4 -4", Number { integer: 0xfffffffffffffff8, float: -8.0 }),
-        ("8 +8", Number { integer: 16, float: 16.0 }),
-        ("8 + (-2)", Number { integer: 6, float: 6.0 }),
-        ("-8 - (-2)", Number { integer: 0xfffffffffffffffa, float: -6.0 }),
-        ("-(-2)", Number { integer: 2, float: 2.0 }),
-        ("0", Number { integer: 0, float: 0.0 }),
-        ("(0)", Number { integer: 0, float: 0.0 }),
-        ("(45)", Number { integer: 45, float: 45.0 }),
-        ("(-5)", Number { integer: 0xfffffffffffffffb, float: -5.0 }),
-        ("(((1220)))", Number { integer: 1220, float: 1220.0 }),
-        ("(-.5)", Number { integer: 0, float: -0.5 }),
-        ("(1234)", Number { integer: 1234, float: 1234.0 }),
-        ("(2+2)", Number { integer: 4, float: 4.0 }),
-        ("1+2*3", Number { integer: 7, float: 7.0 }),
-        ("(1+2)*3", Number { integer: 9, float: 9.0 }),
-        ("(1+2)*(5-1)", Number { integer: 12, float: 12.0 }),
-        ("0xf << 1", Number { integer: 0x1e, float: 30.0 }),
-        ("((0x128)) + 0b111", Number { integer: 303, float: 303.0 }),
-        ("1*4+(0b1+0xf)", Number { integer: 20, float: 20.0 }),
-        (".5*0", Number { integer: 0, float: 0.0 }),
-        ("5/(5/(5/(5)))", Number { integer: 1, float: 1.0 }),
-        ("212 + (1 * (3 - (4 * 5)))", Number { integer: 195, float: 195.0 }),
-        ("0*5", Number { integer: 0, float: 0.0 }),
-        ("0/5", Number { integer: 0, float: 0.0 }),
-        ("0x f f f f + 0xf ff f", Number { integer: 0x1fffe, float: 0x1fffeu64 as f64 }),
-        ("2.5e+3+3", Number { integer: 2503, float: 2503.0 } ),
-        ("2.5e+3-4", Number { integer: 2496, float: 2496.0 } ),
-        (&max_sub_expr, Number { integer: num_max_sub_expr, float: num_max_sub_expr as f64 }),
+        ("2+2", Number::from_int(4, IntType::I64)),
+        ("-4", Number::from_int(0xfffffffffffffffc, IntType::I64)),
+        ("-4 -4", Number::from_int(0xfffffffffffffff8, IntType::I64)),
+        ("8 +8", Number::from_int(16, IntType::I64)),
+        ("8 + (-2)", Number::from_int(6, IntType::I64)),
+        ("-8 - (-2)", Number::from_int(0xfffffffffffffffa, IntType::I64)),
+        ("-(-2)", Number::from_int(2, IntType::I64)),
+        ("0", Number::from_int(0, IntType::I64)),
+        ("(0)", Number::from_int(0, IntType::I64)),
+        ("(45)", Number::from_int(45, IntType::I64)),
+        ("(-5)", Number::from_int(0xfffffffffffffffb, IntType::I64)),
+        ("(((1220)))", Number::from_int(1220, IntType::I64)),
+        ("(-.5)", Number::from_f64(-0.5)),
+        ("(1234)", Number::from_int(1234, IntType::I64)),
+        ("(2+2)", Number::from_int(4, IntType::I64)),
+        ("1+2*3", Number::from_int(7, IntType::I64)),
+        ("(1+2)*3", Number::from_int(9, IntType::I64)),
+        ("(1+2)*(5-1)", Number::from_int(12, IntType::I64)),
+        ("0xf << 1", Number::from_int(0x1e, IntType::I64)),
+        ("((0x128)) + 0b111", Number::from_int(303, IntType::I64)),
+        ("1*4+(0b1+0xf)", Number::from_int(20, IntType::I64)),
+        ("1'000 * 2 + 0b1_0", Number::from_int(2002, IntType::I64)),
+        ("0x8000_0000 >> 0x1_f", Number::from_int(1, IntType::I64)),
+        ("16MiB - 4KiB", Number::from_int(0xfff000, IntType::I64)),
+        ("1.5G / 512KB", Number::from_int(3145, IntType::I64)),
+        (".5*0", Number::from_f64(0.0)),
+        ("5/(5/(5/(5)))", Number::from_int(1, IntType::I64)),
+        ("212 + (1 * (3 - (4 * 5)))", Number::from_int(195, IntType::I64)),
+        ("0*5", Number::from_int(0, IntType::I64)),
+        ("0/5", Number::from_int(0, IntType::I64)),
+        ("0x f f f f + 0xf ff f", Number::from_int(0x1fffe, IntType::I64)),
+        ("2.5e+3+3", Number::from_f64(2503.0) ),
+        ("2.5e+3-4", Number::from_f64(2496.0) ),
+        (&max_sub_expr, Number::from_int(num_max_sub_expr, IntType::U64)),
     ];
     for expr_res in expr_results {
-        test_valid_expr(&expr_res.0, &expr_res.1);
+        test_valid_expr(expr_res.0, &expr_res.1);
     }
 }
 
@@ -385,6 +445,10 @@ fn invalid_exprs() {
         ("", ExprErrorKind::EmptyExpr),
         ("()", ExprErrorKind::EmptyExpr),
         ("2 +", ExprErrorKind::InvalidParamCount),
+        ("0 &&", ExprErrorKind::InvalidParamCount),
+        ("1 &&", ExprErrorKind::InvalidParamCount),
+        ("1 ||", ExprErrorKind::InvalidParamCount),
+        ("0 && ()", ExprErrorKind::InvalidParamCount),
         ("- -2", ExprErrorKind::MissingOperand),
         ("+ +2", ExprErrorKind::InvalidExpr),
         (",2", ExprErrorKind::InvalidExpr),
@@ -429,9 +493,9 @@ fn invalid_exprs() {
         ("(-1).5", ExprErrorKind::MissingOperatorOrFunction),
         ("!-0", ExprErrorKind::MissingOperand),
         ("~-0", ExprErrorKind::MissingOperand),
-        ("0 x123", ExprErrorKind::InvalidExpr),
-        ("0 n123", ExprErrorKind::InvalidExpr),
-        ("0 o1011", ExprErrorKind::InvalidExpr),
+        ("0 x123", ExprErrorKind::MissingOperator),
+        ("0 n123", ExprErrorKind::MissingOperator),
+        ("0 o1011", ExprErrorKind::MissingOperator),
         ("2.5e++4", ExprErrorKind::InvalidExpr),
         ("2.5e--1", ExprErrorKind::InvalidExpr),
         ("+55.5", ExprErrorKind::InvalidExpr),
@@ -448,6 +512,7 @@ fn invalid_exprs() {
         ("avg +", ExprErrorKind::MissingParenthesis),
         ("* avg", ExprErrorKind::InvalidExpr),
         ("- avg", ExprErrorKind::InvalidParamCount),
+        ("1 ? if : 2", ExprErrorKind::MissingParenthesis),
         ("avg *", ExprErrorKind::MissingParenthesis),
         ("avg + sum", ExprErrorKind::MissingParenthesis),
         ("avg() + sum(2,5)", ExprErrorKind::InvalidParamCount),
@@ -459,14 +524,1302 @@ fn invalid_exprs() {
         ("bits(0)", ExprErrorKind::InvalidParamCount),
         ("bits(63)", ExprErrorKind::InvalidParamCount),
         ("bits(64)", ExprErrorKind::InvalidParamCount),
-        // TODO if
+        ("if(1)", ExprErrorKind::InvalidParamCount),
+        ("if(1,2)", ExprErrorKind::InvalidParamCount),
+        ("if(1,2,3,4)", ExprErrorKind::InvalidParamCount),
         ("sum(0xff)", ExprErrorKind::InvalidParamCount),
 
+        // Ternary conditional
+        ("?", ExprErrorKind::InvalidExpr),
+        (":", ExprErrorKind::InvalidExpr),
+        ("? 1 : 2", ExprErrorKind::InvalidExpr),
+        ("1 ?", ExprErrorKind::MissingOperator),
+        ("1 ? 2", ExprErrorKind::MissingOperator),
+        ("1 : 2", ExprErrorKind::MissingOperator),
+        ("1 ? 2 : 3 : 4", ExprErrorKind::MissingOperator),
+        ("(1 ? 2) : 3", ExprErrorKind::MissingOperator),
+        ("1 ? (2 : 3)", ExprErrorKind::MissingOperator),
+        ("avg(1 ? 2, 3)", ExprErrorKind::MissingOperator),
+        ("1 ? : 2", ExprErrorKind::MissingOperand),
+        ("1 ? 2 :", ExprErrorKind::InvalidParamCount),
+        ("1 ?? 2 : 3", ExprErrorKind::MissingOperand),
+        ("1 ? () : 2", ExprErrorKind::InvalidParamCount),
+        ("0 ? () : 2", ExprErrorKind::InvalidParamCount),
+        ("0 ? 2 : ()", ExprErrorKind::InvalidParamCount),
+        ("if(1, 2, ())", ExprErrorKind::InvalidParamCount),
+        ("if(0, (), 2)", ExprErrorKind::InvalidParamCount),
+
         // Maximum sub expressions (i.e. parenthesis) since we push/pop these from
         // the stack, we want to keep this limited.
         (max_sub_expr, ExprErrorKind::ExceededMaxSubExpr),
     ];
     for expr_res in expr_results {
-        test_invalid_expr(&expr_res.0, expr_res.1);
+        test_invalid_expr(expr_res.0, expr_res.1);
+        // Other than failing to evaluate, parsing must fail the same way.
+        if expr_res.1 != ExprErrorKind::FailedEvaluation {
+            let res_parse = spceval::parse(expr_res.0);
+            assert_eq!(expr_res.1, res_parse.err().map(|err| err.kind()).unwrap(), "{}", expr_res.0);
+        }
+    }
+}
+
+#[test]
+fn parse_exprs() {
+    // Printed expressions are fully parenthesized and evaluate to the same result.
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("1+2*3", "(1 + (2 * 3))"),
+        ("1 - 2 - 3", "((1 - 2) - 3)"),
+        ("((1))", "1"),
+        ("0xffff 0000 & ~0x1f", "(0xffff0000 & (~0x1f))"),
+        ("-5 as u8 + 1", "(((u8)(-5)) + 1)"),
+        ("(i8)0x1ff >> 1", "(((i8)0x1ff) >> 1)"),
+        ("1 && 0 || 2", "((1 && 0) || 2)"),
+        ("0 ? 1 : 2 ? 3 : 4", "(0 ? 1 : (2 ? 3 : 4))"),
+        ("avg(1, 2 * 3, pi)", "avg(1, (2 * 3), pi)"),
+        ("if(0, 1/0, bit(3))", "if(0, (1 / 0), bit(3))"),
+        ("2 ** 3 ** 2", "(2 ** (3 ** 2))"),
+        ("-2 ** 2 * 3", "((-(2 ** 2)) * 3)"),
+        ("2.5e+3 <= 2 * KiB", "(2.5e+3 <= (2 * KiB))"),
+        ("' ' + 'a'", "(' ' + 'a')"),
+        ("bytes( \"a b\" ) | 1", "(bytes( \"a b\" ) | 1)"),
+    ];
+    for (str_expr, str_printed) in expr_results {
+        let expr = spceval::parse(str_expr).unwrap();
+        assert_eq!(expr.to_string(), str_printed, "{}", str_expr);
+        let num_expected = spceval::evaluate(str_expr).unwrap();
+        test_valid_expr(str_printed, &num_expected);
+    }
+
+    // Assignments are printed with the variables they assign.
+    let expr = spceval::parse("x <<= y + 1").unwrap();
+    assert_eq!(expr.to_string(), "x <<= (y + 1)");
+    assert_eq!(expr.span, Span::new(0, 11));
+
+    // Spans cover the source of each node, excluding whitespace around it and enclosing parentheses.
+    let expr = spceval::parse("  avg(x, (2) * 3 ) as u16 ").unwrap();
+    assert_eq!(expr.span, Span::new(2, 25));
+    let ExprKind::Unary { oper: "(u16)", operand } = expr.kind else {
+        panic!("{:?}", expr)
+    };
+    assert_eq!(operand.span, Span::new(2, 18));
+    let ExprKind::Call { func, args } = operand.kind else {
+        panic!("{:?}", operand)
+    };
+    assert_eq!(func, "avg");
+    assert_eq!(args[0].span, Span::new(6, 7));
+    assert!(matches!(args[0].kind, ExprKind::Var(ref name) if name == "x"));
+    assert_eq!(args[1].span, Span::new(10, 16));
+    let ExprKind::Binary { oper: "*", ref lhs, ref rhs } = args[1].kind else {
+        panic!("{:?}", args[1])
+    };
+    assert_eq!(lhs.span, Span::new(10, 11));
+    assert_eq!(rhs.span, Span::new(15, 16));
+    assert!(matches!(rhs.kind, ExprKind::Num { ref number, .. } if number.as_int() == Some((3, IntType::I64))));
+}
+
+#[test]
+fn valid_exprs_consts() {
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("pi", Number::from_f64(std::f64::consts::PI)),
+        ("e", Number::from_f64(std::f64::consts::E)),
+        ("2 * pi", Number::from_f64(2.0 * std::f64::consts::PI)),
+        ("PAGE_SIZE", Number::from_int(0x1000, IntType::I64)),
+        ("LARGE_PAGE_SIZE / PAGE_SIZE", Number::from_int(512, IntType::I64)),
+        ("HUGE_PAGE_SIZE", Number::from_int(0x40000000, IntType::I64)),
+        ("0xfee00123 & ~(PAGE_SIZE - 1)", Number::from_int(0xfee00000, IntType::I64)),
+        ("4 * KiB", Number::from_int(0x1000, IntType::I64)),
+        ("MiB / KiB", Number::from_int(1024, IntType::I64)),
+        ("GiB", Number::from_int(0x40000000, IntType::I64)),
+        ("TiB", Number::from_int(0x10000000000, IntType::I64)),
+        ("PiB", Number::from_int(0x4000000000000, IntType::I64)),
+        ("U8_MAX", Number::from_int(0xff, IntType::I64)),
+        ("U32_MAX", Number::from_int(0xffffffff, IntType::I64)),
+        ("U64_MAX == ~(u64)0", Number::from_bool(true)),
+        ("I32_MIN", Number::from_int(i32::MIN as u64 as u128, IntType::I64)),
+        ("I64_MIN", Number::from_int(0x8000000000000000, IntType::I64)),
+        ("I64_MAX", Number::from_int(0x7fffffffffffffff, IntType::I64)),
+        ("bit(QWORD_BITS - 1)", Number::from_int(0x8000000000000000, IntType::U64)),
+        ("kb2b(4) == 4 * KiB", Number::from_bool(true)),
+        ("pb2b(1) == PiB", Number::from_bool(true)),
+        ("avg(KiB, 3 * KiB)", Number::from_int(0x800, IntType::I64)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr(expr_res.0, &expr_res.1);
+    }
+
+    // Constants can be used in assignments but can't be assigned to.
+    let mut eval_ctx = EvalContext::new();
+    test_valid_expr_with(&mut eval_ctx, "size = 3 * PAGE_SIZE", &Number::from_int(0x3000, IntType::I64));
+    test_invalid_expr_with(&mut eval_ctx, "PAGE_SIZE = 0x2000", ExprErrorKind::InvalidAssignment);
+    test_invalid_expr_with(&mut eval_ctx, "pi += 1", ExprErrorKind::InvalidAssignment);
+    test_valid_expr_with(&mut eval_ctx, "PAGE_SIZE", &Number::from_int(0x1000, IntType::I64));
+
+    // Constants are numbers and need operators between them.
+    test_invalid_expr("PAGE_SIZE 2", ExprErrorKind::MissingOperator);
+    test_invalid_expr("2 KiB", ExprErrorKind::MissingOperator);
+    test_invalid_expr("pi(2)", ExprErrorKind::MissingOperatorOrFunction);
+    test_invalid_expr("page_size", ExprErrorKind::UndefinedVariable);
+
+    // Constants can be listed and evaluate to their listed value.
+    let (_, number, help) = spceval::constants().find(|(name, ..)| *name == "PAGE_SIZE").unwrap();
+    assert_eq!((number, help), (&Number::from_int(0x1000, IntType::I64), "Page size (4 KiB)"));
+    for (name, number, _) in spceval::constants() {
+        test_valid_expr(name, number);
+    }
+}
+
+#[test]
+fn valid_exprs_int_types() {
+    #[rustfmt::skip]
+    let expr_results = vec![
+        // Literals are i64 unless they don't fit
+        ("5", Number::from_int(5, IntType::I64)),
+        ("0x7fffffffffffffff", Number::from_int(i64::MAX as u64 as u128, IntType::I64)),
+        ("0x8000000000000000", Number::from_int(0x8000000000000000, IntType::U64)),
+        // Casts
+        ("(i8)-1", Number::from_int(-1i8 as u8 as u128, IntType::I8)),
+        ("(u8)-1", Number::from_int(0xff, IntType::U8)),
+        ("(i8)0x80", Number::from_int(-128i8 as u8 as u128, IntType::I8)),
+        ("(u16)0x12345", Number::from_int(0x2345, IntType::U16)),
+        ("(i32)0xfffffff0", Number::from_int(-16i32 as u32 as u128, IntType::I32)),
+        ("(u64)-1", Number::from_int(u64::MAX as u128, IntType::U64)),
+        ("-(i8)1", Number::from_int(-1i8 as u8 as u128, IntType::I8)),
+        ("(u8)(i8)-2", Number::from_int(0xfe, IntType::U8)),
+        ("0xfffffff0 as i32", Number::from_int(-16i32 as u32 as u128, IntType::I32)),
+        ("0xff as i8 as u16", Number::from_int(0xffff, IntType::U16)),
+        ("-1 as u8", Number::from_int(0xff, IntType::U8)),
+        ("(-1) as u8", Number::from_int(0xff, IntType::U8)),
+        ("2 + 0x1ff as u8", Number::from_int(0x101, IntType::I64)),
+        ("sum(0xff, 1) as u8", Number::from_int(0, IntType::U8)),
+        ("bit(3) as i8 - 9", Number::from_int(-1i64 as u64 as u128, IntType::I64)),
+        // Arithmetic wraps in the common type
+        ("(u8)200 + (u8)100", Number::from_int(44, IntType::U8)),
+        ("(i8)127 + (i8)1", Number::from_int(-128i8 as u8 as u128, IntType::I8)),
+        ("(u8)200 + 100", Number::from_int(300, IntType::I64)),
+        ("(u32)1 - (u32)2", Number::from_int(0xffffffff, IntType::U32)),
+        ("(i32)1 - (u32)2", Number::from_int(0xffffffff, IntType::U32)),
+        ("(u16)0x100 * (u16)0x100", Number::from_int(0, IntType::U16)),
+        // Signed division, remainder and comparison
+        ("-8 / 2", Number::from_int(-4i64 as u64 as u128, IntType::I64)),
+        ("-7 % 3", Number::from_int(-1i64 as u64 as u128, IntType::I64)),
+        ("(u64)-8 / 2", Number::from_int(((-8i64 as u64) / 2) as u128, IntType::U64)),
+        ("-1 < 0", Number::from_bool(true)),
+        ("(u64)-1 < 0", Number::from_bool(false)),
+        ("-1 < (u64)0", Number::from_bool(false)),
+        ("(i8)-1 == (i32)-1", Number::from_bool(true)),
+        // Shifts
+        ("-16 >> 2", Number::from_int(-4i64 as u64 as u128, IntType::I64)),
+        ("(u64)-16 >> 60", Number::from_int(0xf, IntType::U64)),
+        ("(i8)-128 >> 7", Number::from_int(-1i8 as u8 as u128, IntType::I8)),
+        ("(u8)1 << 8", Number::from_int(0, IntType::U8)),
+        ("(u8)1 << 7", Number::from_int(0x80, IntType::U8)),
+        ("(u8)1 << 9", Number::from_int(0, IntType::U8)),
+        ("(u8)1 << 128", Number::from_int(0, IntType::U8)),
+        ("(u8)0x80 >> 8", Number::from_int(0, IntType::U8)),
+        ("(u8)0x80 >> 9", Number::from_int(0, IntType::U8)),
+        ("(u8)0x80 >> 128", Number::from_int(0, IntType::U8)),
+        ("(i8)-128 >> 8", Number::from_int(-1i8 as u8 as u128, IntType::I8)),
+        ("1 << 63", Number::from_int(1 << 63, IntType::I64)),
+        ("1 << 64", Number::from_int(0, IntType::I64)),
+        ("1 << 65", Number::from_int(0, IntType::I64)),
+        ("1 << 128", Number::from_int(0, IntType::I64)),
+        ("1 << 129", Number::from_int(0, IntType::I64)),
+        ("1 << 0x100000001", Number::from_int(0, IntType::I64)),
+        ("0x8000 >> 64", Number::from_int(0, IntType::I64)),
+        ("0x8000 >> 65", Number::from_int(0, IntType::I64)),
+        ("0x8000 >> 128", Number::from_int(0, IntType::I64)),
+        ("-8 >> 64", Number::from_int(-1i64 as u64 as u128, IntType::I64)),
+        ("-8 >> 65", Number::from_int(-1i64 as u64 as u128, IntType::I64)),
+        ("-8 >> 128", Number::from_int(-1i64 as u64 as u128, IntType::I64)),
+        ("(i128)1 << 127", Number::from_int(1 << 127, IntType::I128)),
+        ("(i128)1 << 128", Number::from_int(0, IntType::I128)),
+        ("(i128)1 << 129", Number::from_int(0, IntType::I128)),
+        ("(i128)-1 >> 128", Number::from_int(u128::MAX, IntType::I128)),
+        ("(i128)-1 >> 129", Number::from_int(u128::MAX, IntType::I128)),
+        ("I128_MAX >> 128", Number::from_int(0, IntType::I128)),
+        ("~(u8)0", Number::from_int(0xff, IntType::U8)),
+        // Functions
+        ("avg(-2, -4)", Number::from_int(-3i64 as u64 as u128, IntType::I64)),
+        ("pow(-2, 3)", Number::from_int(-8i64 as u64 as u128, IntType::I64)),
+        ("pow(2, 63)", Number::from_int(0x8000000000000000, IntType::U64)),
+        ("pow((u8)2, 7)", Number::from_int(0x80, IntType::U8)),
+        ("is_pow_of_two((i8)-128)", Number::from_bool(false)),
+        // 128-bit
+        ("0x10000000000000000", Number::from_int(1 << 64, IntType::I128)),
+        ("0xffffffffffffffffffffffffffffffff", Number::from_int(u128::MAX, IntType::U128)),
+        ("U64_MAX + 1", Number::from_int(0, IntType::U64)),
+        ("(u128)U64_MAX + 1", Number::from_int(1 << 64, IntType::U128)),
+        ("(i128)-1", Number::from_int(u128::MAX, IntType::I128)),
+        ("-1 as u128", Number::from_int(u128::MAX, IntType::U128)),
+        ("U64_MAX as i128 + 1", Number::from_int(1 << 64, IntType::I128)),
+        ("(i128)-1 < 0", Number::from_bool(true)),
+        ("-((i128)1 << 100) >> 98", Number::from_int(-4i128 as u128, IntType::I128)),
+        ("0x0123456789abcdef0123456789abcdef >> 64", Number::from_int(0x0123456789abcdef, IntType::I128)),
+        ("bit(127)", Number::from_int(1 << 127, IntType::U128)),
+        ("bits(64, 127)", Number::from_int(!0u128 << 64, IntType::U128)),
+        ("pow(2, 100)", Number::from_int(1 << 100, IntType::I128)),
+        ("pow(-2, 101)", Number::from_int(-(1i128 << 101) as u128, IntType::I128)),
+        ("I128_MIN / (-1)", Number::from_int(i128::MIN as u128, IntType::I128)),
+        ("U128_MAX == ~(u128)0", Number::from_bool(true)),
+        ("kb2b((u16)64)", Number::from_int(0, IntType::U16)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr(expr_res.0, &expr_res.1);
+    }
+
+    // Casts apply to operands, they're not operands themselves.
+    test_invalid_expr("1 as i7", ExprErrorKind::InvalidType);
+    test_invalid_expr("1 as", ExprErrorKind::InvalidType);
+    test_invalid_expr("1 as (u8)", ExprErrorKind::InvalidType);
+    test_invalid_expr("as u8", ExprErrorKind::MissingOperator);
+    test_invalid_expr("(i8)", ExprErrorKind::InvalidParamCount);
+    test_invalid_expr("5 (i8)3", ExprErrorKind::MissingOperator);
+    test_invalid_expr("(5) (i8)3", ExprErrorKind::MissingOperator);
+    test_invalid_expr("5 ~3", ExprErrorKind::MissingOperator);
+    test_invalid_expr("pow((u8)2, 8)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("(i8)1 / (i8)0", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("pow(2, 128)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("2 ** 63", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("2 ** 64", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("(0 - 2) ** 64", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("2 ** 128", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("1 << (-1)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("1 >> (i8)-1", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("(i8)2 ** 7", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("(u8)2 ** 8", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("((i8)2) ** 7", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("10.0 ** 400", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("2 **", ExprErrorKind::InvalidParamCount);
+    test_invalid_expr("** 2", ExprErrorKind::InvalidExpr);
+}
+
+#[test]
+fn valid_exprs_bit_funcs() {
+    #[rustfmt::skip]
+    let expr_results = vec![
+        // Rotation within the width of the type
+        ("rol(0x81, 1, 8)", Number::from_int(0x03, IntType::U8)),
+        ("ror(0x81, 1, 8)", Number::from_int(0xc0, IntType::U8)),
+        ("rol(0x12345678, 8, 32)", Number::from_int(0x34567812, IntType::U32)),
+        ("ror(0x12345678, 4, 16)", Number::from_int(0x8567, IntType::U16)),
+        ("rol(0x12345678, 0 - 8, 32)", Number::from_int(0x78123456, IntType::U32)),
+        ("rol(0x80, 9, 8)", Number::from_int(0x01, IntType::U8)),
+        ("ror(1, 1)", Number::from_int(0x8000000000000000, IntType::I64)),
+        ("rol((u16)0x8001, 4)", Number::from_int(0x0018, IntType::U16)),
+        ("rol((i8)-128, 1, 8)", Number::from_int(0x01, IntType::I8)),
+        ("rol((i8)-1, 4, 16)", Number::from_int(0xffff, IntType::U16)),
+        ("ror(1, 1, 128)", Number::from_int(1 << 127, IntType::U128)),
+        ("(u8)0x81 <<< 1", Number::from_int(0x03, IntType::U8)),
+        ("(u16)1 >>> 1", Number::from_int(0x8000, IntType::U16)),
+        ("(u32)0xf000000f >>> 4 <<< 4", Number::from_int(0xf000000f, IntType::U32)),
+        ("1 <<< 64", Number::from_int(1, IntType::I64)),
+        // Bit counts and scans
+        ("popcnt(0xf0f0)", Number::from_int(8, IntType::I64)),
+        ("popcnt(-1)", Number::from_int(64, IntType::I64)),
+        ("popcnt(-1, 8)", Number::from_int(8, IntType::I64)),
+        ("popcnt((i16)-1)", Number::from_int(16, IntType::I64)),
+        ("parity(7)", Number::from_int(1, IntType::I64)),
+        ("parity(0x8001, 16)", Number::from_int(0, IntType::I64)),
+        ("clz(1)", Number::from_int(63, IntType::I64)),
+        ("clz(1, 32)", Number::from_int(31, IntType::I64)),
+        ("lzcnt((u8)0x10)", Number::from_int(3, IntType::I64)),
+        ("clz(0, 16)", Number::from_int(16, IntType::I64)),
+        ("clz(-1)", Number::from_int(0, IntType::I64)),
+        ("ctz(0x100)", Number::from_int(8, IntType::I64)),
+        ("tzcnt(0, 32)", Number::from_int(32, IntType::I64)),
+        ("ctz(0)", Number::from_int(64, IntType::I64)),
+        ("bsf(0x18)", Number::from_int(3, IntType::I64)),
+        ("bsr(0x18)", Number::from_int(4, IntType::I64)),
+        ("bsr(-1, 16)", Number::from_int(15, IntType::I64)),
+        ("bsr(bit(127))", Number::from_int(127, IntType::I64)),
+        // Bit and byte reversal
+        ("bitrev(1, 8)", Number::from_int(0x80, IntType::U8)),
+        ("bitrev(0x0f, 16)", Number::from_int(0xf000, IntType::U16)),
+        ("bitrev(1)", Number::from_int(1 << 63, IntType::I64)),
+        ("bswap16(0x1234)", Number::from_int(0x3412, IntType::U16)),
+        ("bswap16((i16)0x00ff)", Number::from_int(0xff00, IntType::U16)),
+        ("bswap16(-1)", Number::from_int(0xffff, IntType::U16)),
+        ("bswap32(0x12345678)", Number::from_int(0x78563412, IntType::U32)),
+        ("bswap32((u8)0x12)", Number::from_int(0x12000000, IntType::U32)),
+        ("bswap64(0x0102030405060708)", Number::from_int(0x0807060504030201, IntType::U64)),
+        ("bswap16(0x1234, 16)", Number::from_int(0x3412, IntType::U16)),
+        ("bswap32(-2, 32)", Number::from_int(0xfeffffff, IntType::U32)),
+        ("bswap64((i8)1, 64)", Number::from_int(0x0100000000000000, IntType::U64)),
+        // Parallel bit deposit and extract
+        ("pdep(0b101, 0xf0)", Number::from_int(0x50, IntType::I64)),
+        ("pdep(0xff, 0x8001)", Number::from_int(0x8001, IntType::I64)),
+        ("pext(0x50, 0xf0)", Number::from_int(0b101, IntType::I64)),
+        ("pext(0x12345678, 0xff00ff00)", Number::from_int(0x1256, IntType::I64)),
+        ("pext((u8)0xff, 0x1ff, 8)", Number::from_int(0xff, IntType::U8)),
+        ("pdep((u32)-1, (u32)0xf000000f)", Number::from_int(0xf000000f, IntType::U32)),
+        // Bit fields and extension
+        ("extract(0xfee00123, 31, 20)", Number::from_int(0xfee, IntType::I64)),
+        ("extract(0xfee00123, 20, 31)", Number::from_int(0xfee, IntType::I64)),
+        ("extract(0x1234, 3, 3)", Number::from_int(0, IntType::I64)),
+        ("extract((u8)0xa5, 7, 4)", Number::from_int(0xa, IntType::U8)),
+        ("extract((i8)-1, 7, 4)", Number::from_int(0xf, IntType::I8)),
+        ("extract((u16)0xabcd, 15, 8)", Number::from_int(0xab, IntType::U16)),
+        ("extract(-1, 63, 0)", Number::from_int(u64::MAX as u128, IntType::I64)),
+        ("extract((i128)-1, 127, 64)", Number::from_int(u64::MAX as u128, IntType::I128)),
+        ("insert(0xfee00123, 0xabc, 31, 20)", Number::from_int(0xabc00123, IntType::I64)),
+        ("insert(0, 0x1ff, 7, 4)", Number::from_int(0xf0, IntType::I64)),
+        ("insert((u16)0xffff, 0, 8, 15)", Number::from_int(0x00ff, IntType::U16)),
+        ("insert((i8)0, 1, 7, 7)", Number::from_int(0x80, IntType::I8)),
+        ("sext(0x800000000000, 48)", Number::from_int(0xffff800000000000, IntType::I64)),
+        ("sext(0x7fffffffffff, 48)", Number::from_int(0x7fffffffffff, IntType::I64)),
+        ("sext((u16)0x80, 8)", Number::from_int(-128i128 as u128, IntType::I64)),
+        ("sext((u8)0x80, 8)", Number::from_int(-128i128 as u128, IntType::I64)),
+        ("sext((u8)0x7f, 8)", Number::from_int(0x7f, IntType::I64)),
+        ("sext(0xffffffffffffffff, 64)", Number::from_int(-1i128 as u128, IntType::I64)),
+        ("sext((u128)1 << 99, 100)", Number::from_int(!((1u128 << 99) - 1), IntType::I128)),
+        ("sext((u128)0x80, 8)", Number::from_int(-128i128 as u128, IntType::I128)),
+        ("sext(1, 1)", Number::from_int(u64::MAX as u128, IntType::I64)),
+        ("zext(-1, 32)", Number::from_int(0xffffffff, IntType::U64)),
+        ("zext(0x1234, 128)", Number::from_int(0x1234, IntType::U128)),
+        ("zext((i16)-2, 8)", Number::from_int(0xfe, IntType::U64)),
+        ("zext((i8)-1, 16)", Number::from_int(0xffff, IntType::U64)),
+        ("zext((i8)-1, 8)", Number::from_int(0xff, IntType::U64)),
+        ("zext((i128)-1, 64)", Number::from_int(u64::MAX as u128, IntType::U128)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr(expr_res.0, &expr_res.1);
+    }
+
+    test_invalid_expr("rol(1, 1, 12)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("ror(1, 1, 0 - 8)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("rol(1.5, 1)", ExprErrorKind::InvalidParamType);
+    test_invalid_expr("ror(1, 1, 8.0)", ExprErrorKind::InvalidParamType);
+    test_invalid_expr("1.0 <<< 1", ExprErrorKind::InvalidParamType);
+    test_invalid_expr("rol(1)", ExprErrorKind::InvalidParamCount);
+    test_invalid_expr("bsf(0)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("bsr(0x100, 8)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("popcnt(1, 7)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("popcnt(1.0)", ExprErrorKind::InvalidParamType);
+    test_invalid_expr("pext(1, 2.0)", ExprErrorKind::InvalidParamType);
+    test_invalid_expr("bswap16(1, 8)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("bswap32(1, 64)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("bswap64(1, 0 - 64)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("bswap64(1, 64.0)", ExprErrorKind::InvalidParamType);
+    test_invalid_expr("bswap16(1, 16, 16)", ExprErrorKind::InvalidParamCount);
+    test_invalid_expr("pdep(1)", ExprErrorKind::InvalidParamCount);
+    test_invalid_expr("extract(1, 128, 0)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("extract(1, 64, 0)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("extract((u8)1, 15, 8)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("insert((i16)1, 1, 0, 16)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("insert(1, 1, 0 - 1, 0)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("sext(1, 0)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("zext(1, 129)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("insert(1, 1.0, 3, 0)", ExprErrorKind::InvalidParamType);
+    test_invalid_expr("extract(1, 3)", ExprErrorKind::InvalidParamCount);
+
+    // Integers wider than 128 bits can only be operated on with a width.
+    let mut eval_ctx = EvalContext::new();
+    eval_ctx.set_int_mode(IntMode::Arbitrary);
+    let exprs = vec![
+        "popcnt(1 << 200)",
+        "clz(1 << 200)",
+        "ctz(1 << 200)",
+        "bsr(1 << 200)",
+        "bitrev(1 << 200)",
+        "pdep(1 << 200, 1)",
+        "pext(1, 1 << 200)",
+        "rol(1 << 200, 1)",
+        "ror(1 << 200, 1)",
+        "(1 << 200) <<< 1",
+        "(1 << 200) >>> 1",
+    ];
+    for expr in exprs {
+        test_invalid_expr_with(&mut eval_ctx, expr, ExprErrorKind::FailedEvaluation);
+    }
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("popcnt((1 << 200) + 3, 64)", Number::from_int(2, IntType::I64)),
+        ("bsr((1 << 200) + 0x10, 128)", Number::from_int(4, IntType::I64)),
+        ("rol((1 << 200) + 1, 4, 8)", Number::from_int(0x10, IntType::U8)),
+        ("popcnt(U64_MAX)", Number::from_int(64, IntType::I64)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr_with(&mut eval_ctx, expr_res.0, &expr_res.1);
+    }
+}
+
+#[test]
+fn valid_exprs_int_funcs() {
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("min(3, 1, 2)", Number::from_int(1, IntType::I64)),
+        ("max(3, 1, 2)", Number::from_int(3, IntType::I64)),
+        ("min(0 - 1, 5)", Number::from_int(-1i64 as u64 as u128, IntType::I64)),
+        ("min(0 - 1, (u64)0)", Number::from_int(0, IntType::U64)),
+        ("max((u8)200, (i8)-1)", Number::from_int(255, IntType::U8)),
+        ("max(1, 2.5)", Number::from_f64(2.5)),
+        ("clamp(15, 0, 10)", Number::from_int(10, IntType::I64)),
+        ("clamp(0 - 5, 0, 10)", Number::from_int(0, IntType::I64)),
+        ("clamp(7, 0, 10)", Number::from_int(7, IntType::I64)),
+        ("clamp(0.5, 1, 2)", Number::from_f64(1.0)),
+        ("abs(0 - 42)", Number::from_int(42, IntType::I64)),
+        ("abs((u8)200)", Number::from_int(200, IntType::U8)),
+        ("abs(0 - 1.5)", Number::from_f64(1.5)),
+        ("gcd(12, 18)", Number::from_int(6, IntType::I64)),
+        ("gcd(0 - 12, 18, 8)", Number::from_int(2, IntType::I64)),
+        ("gcd(0, 5)", Number::from_int(5, IntType::I64)),
+        ("lcm(4, 6)", Number::from_int(12, IntType::I64)),
+        ("lcm(3, 4, 5)", Number::from_int(60, IntType::I64)),
+        ("lcm(0, 5)", Number::from_int(0, IntType::I64)),
+        ("div_ceil(10, 4)", Number::from_int(3, IntType::I64)),
+        ("div_ceil(8, 4)", Number::from_int(2, IntType::I64)),
+        ("div_ceil(0 - 10, 4)", Number::from_int(-2i64 as u64 as u128, IntType::I64)),
+        ("div_ceil(0 - 10, 0 - 4)", Number::from_int(3, IntType::I64)),
+        ("div_ceil(1, 0.5)", Number::from_f64(2.0)),
+        ("div_ceil(10, 4.0)", Number::from_f64(3.0)),
+        ("div_round(10, 4)", Number::from_int(3, IntType::I64)),
+        ("div_round(9, 4)", Number::from_int(2, IntType::I64)),
+        ("div_round(0 - 10, 4)", Number::from_int(-3i64 as u64 as u128, IntType::I64)),
+        ("div_round(11, 0 - 4)", Number::from_int(-3i64 as u64 as u128, IntType::I64)),
+        ("isqrt(99)", Number::from_int(9, IntType::I64)),
+        ("isqrt(U64_MAX)", Number::from_int(u32::MAX as u128, IntType::U64)),
+        ("ilog2(1)", Number::from_int(0, IntType::I64)),
+        ("ilog2(4097)", Number::from_int(12, IntType::I64)),
+        ("ilog2(U128_MAX)", Number::from_int(127, IntType::I64)),
+        ("ilog10(999)", Number::from_int(2, IntType::I64)),
+        ("ilog10(1000)", Number::from_int(3, IntType::I64)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr(expr_res.0, &expr_res.1);
+    }
+
+    // Arbitrary-precision integers are exact.
+    let mut eval_ctx = EvalContext::new();
+    eval_ctx.set_int_mode(IntMode::Arbitrary);
+    test_valid_expr_with(&mut eval_ctx, "isqrt(pow(2, 200)) == pow(2, 100)", &Number::from_bool(true));
+    test_valid_expr_with(&mut eval_ctx, "ilog2(pow(2, 200))", &Number::from_int(200, IntType::I64));
+    test_valid_expr_with(&mut eval_ctx, "ilog10(pow(10, 50))", &Number::from_int(50, IntType::I64));
+
+    let expr_errors = vec![
+        ("clamp(1, 10, 0)", ExprErrorKind::FailedEvaluation),
+        ("div_ceil(1, 0)", ExprErrorKind::FailedEvaluation),
+        ("div_round(1.0, 0)", ExprErrorKind::FailedEvaluation),
+        ("isqrt(0 - 1)", ExprErrorKind::FailedEvaluation),
+        ("ilog2(0)", ExprErrorKind::FailedEvaluation),
+        ("ilog10(0 - 10)", ExprErrorKind::FailedEvaluation),
+        ("gcd(1.5, 3)", ExprErrorKind::InvalidParamType),
+        ("isqrt(4.0)", ExprErrorKind::InvalidParamType),
+        ("min(1)", ExprErrorKind::InvalidParamCount),
+        ("clamp(1, 2)", ExprErrorKind::InvalidParamCount),
+    ];
+    for (str_expr, expr_error_kind) in expr_errors {
+        test_invalid_expr(str_expr, expr_error_kind);
+    }
+}
+
+#[test]
+fn valid_exprs_align_funcs() {
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("align_up(0x1234, 0x1000)", Number::from_int(0x2000, IntType::I64)),
+        ("align_up(0x2000, 0x1000)", Number::from_int(0x2000, IntType::I64)),
+        ("align_up(0, 8)", Number::from_int(0, IntType::I64)),
+        ("align_up(13, 1)", Number::from_int(13, IntType::I64)),
+        ("align_up(0 - 5, 4)", Number::from_int(-4i64 as u64 as u128, IntType::I64)),
+        ("align_up((u8)0xf1, 0x10)", Number::from_int(0x100, IntType::I64)),
+        ("align_down(0x1234, 0x1000)", Number::from_int(0x1000, IntType::I64)),
+        ("align_down(0 - 5, 4)", Number::from_int(-8i64 as u64 as u128, IntType::I64)),
+        ("align_down(U64_MAX, bit(63))", Number::from_int(1 << 63, IntType::U64)),
+        ("is_aligned(0x3000, PAGE_SIZE)", Number::from_bool(true)),
+        ("is_aligned(0x3008, 16)", Number::from_bool(false)),
+        ("pages(0)", Number::from_int(0, IntType::I64)),
+        ("pages(1)", Number::from_int(1, IntType::I64)),
+        ("pages(0x2000)", Number::from_int(2, IntType::I64)),
+        ("pages(0x2001)", Number::from_int(3, IntType::I64)),
+        ("pages(5 * MiB, LARGE_PAGE_SIZE)", Number::from_int(3, IntType::I64)),
+        ("pages(U64_MAX)", Number::from_int(1 << 52, IntType::U64)),
+        ("page_base(0xfee00123)", Number::from_int(0xfee00000, IntType::I64)),
+        ("page_base(0x1234567, LARGE_PAGE_SIZE)", Number::from_int(0x1200000, IntType::I64)),
+        ("page_offset(0xfee00123)", Number::from_int(0x123, IntType::I64)),
+        ("page_offset(0x1234567, 0x10000)", Number::from_int(0x4567, IntType::I64)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr(expr_res.0, &expr_res.1);
+    }
+
+    // Arbitrary-precision integers are aligned exactly.
+    let mut eval_ctx = EvalContext::new();
+    eval_ctx.set_int_mode(IntMode::Arbitrary);
+    test_valid_expr_with(&mut eval_ctx, "align_up(U128_MAX, 16) == pow(2, 128)", &Number::from_bool(true));
+    test_valid_expr_with(&mut eval_ctx, "pages(pow(2, 130) + 1) - pow(2, 118)", &Number::from_int(1, IntType::I64));
+    test_valid_expr_with(&mut eval_ctx, "page_offset(0 - 1)", &Number::from_int(0xfff, IntType::I64));
+
+    test_invalid_expr("align_up(0x1234, 0x1800)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("align_up((u8)0xf1, (u8)0x10)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("align_down(0x1234, 0)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("is_aligned(0x1234, 0 - 16)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("pages(0x1234, 1000)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr("page_base(1.5)", ExprErrorKind::InvalidParamType);
+    test_invalid_expr("align_up(1)", ExprErrorKind::InvalidParamCount);
+}
+
+#[test]
+fn valid_exprs_float_promotion() {
+    // Integers are promoted to floats when either operand is a float.
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("7 / 2", Number::from_int(3, IntType::I64)),
+        ("7.0 / 2", Number::from_f64(3.5)),
+        ("1.5 + (u8)1", Number::from_f64(2.5)),
+        ("-0.5 * 3", Number::from_f64(-1.5)),
+        ("5.5 % 2", Number::from_f64(1.5)),
+        ("(i32)-2.7", Number::from_int(-2i32 as u32 as u128, IntType::I32)),
+        ("2.5 as u8 + 1", Number::from_int(3, IntType::I64)),
+        ("2.5 * 2 == 5", Number::from_bool(true)),
+        ("0.5 < 1", Number::from_bool(true)),
+        ("!0.0", Number::from_bool(true)),
+        ("0.5 && 1", Number::from_bool(true)),
+        ("(1 < 2) + 1", Number::from_int(2, IntType::I64)),
+        ("b2kb(1536)", Number::from_f64(1.5)),
+        ("b2kb(2048)", Number::from_int(2, IntType::I64)),
+        ("mb2gb((u32)512)", Number::from_f64(0.5)),
+        ("b2kb(1536.0)", Number::from_f64(1.5)),
+        ("sum(1, 0.5)", Number::from_f64(1.5)),
+        ("avg(1, 2.0)", Number::from_f64(1.5)),
+        ("if(0.5, 1.5, 2)", Number::from_f64(1.5)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr(expr_res.0, &expr_res.1);
+    }
+
+    // Floats are neither arbitrary-precision integers nor words.
+    let mut eval_ctx = EvalContext::new();
+    eval_ctx.set_int_mode(IntMode::Word(IntType::U8));
+    test_valid_expr_with(&mut eval_ctx, "300.5 + 1", &Number::from_f64(301.5));
+    eval_ctx.set_int_mode(IntMode::Arbitrary);
+    let number = spceval::evaluate_with(&mut eval_ctx, "2.5 * 2").unwrap();
+    assert!(matches!(number, Number::Float(_)));
+
+    // Bitwise operators and functions require integers.
+    let exprs = vec![
+        "1.5 & 1",
+        "1 | 2.0",
+        "2.0 ^ 1",
+        "~1.5",
+        "1.0 << 2",
+        "1 >> 0.5",
+        "bit(1.0)",
+        "bits(0, 3.0)",
+        "pow(2.0, 3)",
+        "is_pow_of_two(4.0)",
+    ];
+    for expr in exprs {
+        test_invalid_expr(expr, ExprErrorKind::InvalidParamType);
+    }
+    let mut eval_ctx = EvalContext::new();
+    test_valid_expr_with(&mut eval_ctx, "x = 1.0", &Number::from_f64(1.0));
+    test_invalid_expr_with(&mut eval_ctx, "x <<= 1", ExprErrorKind::InvalidParamType);
+}
+
+#[test]
+fn valid_exprs_float_funcs() {
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("sqrt(16)", Number::from_f64(4.0)),
+        ("sqrt(2)", Number::from_f64(2f64.sqrt())),
+        ("cbrt(0 - 27)", Number::from_f64(-3.0)),
+        ("exp(0)", Number::from_f64(1.0)),
+        ("ln(e)", Number::from_f64(1.0)),
+        ("log2(MiB)", Number::from_f64(20.0)),
+        ("log10(1000)", Number::from_f64(3.0)),
+        ("log(81, 3)", Number::from_f64(81f64.log(3.0))),
+        ("fpow(2, 0.5)", Number::from_f64(2f64.sqrt())),
+        ("fpow(2, 0 - 1)", Number::from_f64(0.5)),
+        ("sin(pi / 2)", Number::from_f64(1.0)),
+        ("cos(0)", Number::from_f64(1.0)),
+        ("tan(pi / 4)", Number::from_f64(std::f64::consts::FRAC_PI_4.tan())),
+        ("asin(1)", Number::from_f64(std::f64::consts::FRAC_PI_2)),
+        ("acos(1)", Number::from_f64(0.0)),
+        ("atan(1) * 4 == pi", Number::from_bool(true)),
+        ("atan2(1, 0 - 1)", Number::from_f64(1f64.atan2(-1.0))),
+        ("floor(2.7)", Number::from_f64(2.0)),
+        ("floor(0 - 2.5)", Number::from_f64(-3.0)),
+        ("ceil(2.1)", Number::from_f64(3.0)),
+        ("round(2.5)", Number::from_f64(3.0)),
+        ("round(0 - 2.5)", Number::from_f64(-3.0)),
+        ("trunc(0 - 2.7)", Number::from_f64(-2.0)),
+        ("floor((u8)200)", Number::from_int(200, IntType::U8)),
+        ("ceil(log2(5000)) as u32", Number::from_int(13, IntType::U32)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr(expr_res.0, &expr_res.1);
+    }
+
+    // Results that aren't finite numbers are domain errors.
+    let exprs = vec!["sqrt(0 - 1)", "ln(0)", "log2(0 - 8)", "log(8, 1)", "asin(2)", "exp(1000)", "fpow(0, 0 - 1)"];
+    for expr in exprs {
+        test_invalid_expr(expr, ExprErrorKind::FailedEvaluation);
+    }
+    test_invalid_expr("log(8)", ExprErrorKind::InvalidParamCount);
+    test_invalid_expr("sin()", ExprErrorKind::InvalidParamCount);
+}
+
+#[test]
+fn valid_exprs_float_bits_funcs() {
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("0x1.8p3 + 1", Number::from_f64(13.0)),
+        ("0x10P3", Number::from_f64(128.0)),
+        ("f32bits(1)", Number::from_int(0x3f800000, IntType::U32)),
+        ("f32bits(0 - 2.0)", Number::from_int(0xc0000000, IntType::U32)),
+        ("f32bits(0x1.8p0)", Number::from_int(0x3fc00000, IntType::U32)),
+        ("f64bits(1.0)", Number::from_int(0x3ff0000000000000, IntType::U64)),
+        ("f16bits(1.0)", Number::from_int(0x3c00, IntType::U16)),
+        ("f16bits(65504)", Number::from_int(0x7bff, IntType::U16)),
+        ("f16bits(65520)", Number::from_int(0x7c00, IntType::U16)),
+        ("f16bits(0 - 0x1p-24)", Number::from_int(0x8001, IntType::U16)),
+        ("f16bits(0x1p-25)", Number::from_int(0, IntType::U16)),
+        ("f16bits(0x1.8p-25)", Number::from_int(1, IntType::U16)),
+        ("f16bits(0x1.002p0)", Number::from_int(0x3c00, IntType::U16)),
+        ("f16bits(0x1.006p0)", Number::from_int(0x3c02, IntType::U16)),
+        ("bf16bits(pi)", Number::from_int(0x4049, IntType::U16)),
+        ("bits2f16(0x3c00)", Number::from_f64(1.0)),
+        ("bits2f16(0x7bff)", Number::from_f64(65504.0)),
+        ("bits2f16(1)", Number::from_f64(2f64.powi(-24))),
+        ("bits2f16(0xc000)", Number::from_f64(-2.0)),
+        ("bits2bf16(0x4049)", Number::from_f64(3.140625)),
+        ("bits2f32(0x3fc00000)", Number::from_f64(1.5)),
+        ("bits2f64(0x400921fb54442d18) == pi", Number::from_bool(true)),
+        ("bits2f32(0x7fc00000) != bits2f32(0x7fc00000)", Number::from_bool(true)),
+        ("f16bits(bits2f16(0x7c00))", Number::from_int(0x7c00, IntType::U16)),
+        ("f32bits(bits2f32(0xff800000))", Number::from_int(0xff800000, IntType::U32)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr(expr_res.0, &expr_res.1);
+    }
+
+    // Hexadecimal numbers have no size suffix, so "P" is only ever the binary exponent.
+    test_invalid_expr("0x10P", ExprErrorKind::MissingOperator);
+    test_invalid_expr("0x1.8p", ExprErrorKind::InvalidExpr);
+    test_invalid_expr("bits2f32(1.5)", ExprErrorKind::InvalidParamType);
+    test_invalid_expr("f32bits()", ExprErrorKind::InvalidParamCount);
+}
+
+#[test]
+fn valid_exprs_char_lits() {
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("'A'", Number::from_int(0x41, IntType::I64)),
+        ("'a' - 'A'", Number::from_int(0x20, IntType::I64)),
+        ("' '", Number::from_int(0x20, IntType::I64)),
+        ("'\\n' | '\\0'", Number::from_int(0xa, IntType::I64)),
+        ("'\\'' + '\\\\' + '\\\"' + '\"'", Number::from_int(0x27 + 0x5c + 0x22 + 0x22, IntType::I64)),
+        ("'ELF\\x7f'", Number::from_int(0x454c467f, IntType::I64)),
+        ("'RIFF' == 0x52494646", Number::from_bool(true)),
+        ("(u8)'\\xff'", Number::from_int(0xff, IntType::U8)),
+        ("'\\xff\\xff\\xff\\xff\\xff\\xff\\xff\\xff'", Number::from_int(u64::MAX as u128, IntType::U64)),
+        ("bytes(\"abc\")", Number::from_int(0x636261, IntType::I64)),
+        ("bytes(\"\\x7fELF\") == 0x464c457f", Number::from_bool(true)),
+        ("bytes ( \"MZ\" ) + 1", Number::from_int(0x5a4e, IntType::I64)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr(expr_res.0, &expr_res.1);
+    }
+
+    #[rustfmt::skip]
+    let expr_errors = vec![
+        ("'A", 0),
+        ("''", 0),
+        ("1 + 'abc\\q'", 8),
+        ("'\\x4'", 1),
+        ("'é'", 1),
+        ("'0123456789abcdefg'", 0),
+        ("bytes(\"abc)", 6),
+    ];
+    for (str_expr, idx_expr) in expr_errors {
+        let err = spceval::evaluate(str_expr).unwrap_err();
+        assert_eq!(err.kind(), ExprErrorKind::InvalidExpr, "{}", str_expr);
+        assert_eq!(err.index(), idx_expr, "{}", str_expr);
+    }
+    test_invalid_expr("bytes(\"abc\"", ExprErrorKind::MissingParenthesis);
+    test_invalid_expr("2 'A'", ExprErrorKind::MissingOperator);
+}
+
+#[test]
+fn valid_exprs_arbitrary_precision() {
+    let mut eval_ctx = EvalContext::new();
+    eval_ctx.set_int_mode(IntMode::Arbitrary);
+
+    // Results that fit in 128 bits are typed like literals.
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("U64_MAX * U64_MAX", Number::from_int((u64::MAX as u128) * (u64::MAX as u128), IntType::U128)),
+        ("I64_MAX + 1", Number::from_int(1 << 63, IntType::U64)),
+        ("0 - U64_MAX - 1", Number::from_int((-(1i128 << 64)) as u128, IntType::I128)),
+        ("pow(2, 200) / pow(2, 190)", Number::from_int(1024, IntType::I64)),
+        ("pow(2, 200) >> 190", Number::from_int(1024, IntType::I64)),
+        ("(1 << 130) >> 129", Number::from_int(2, IntType::I64)),
+        ("PiB * PiB * PiB / pow(2, 140)", Number::from_int(1 << 10, IntType::I64)),
+        ("pow(10, 40) % 7", Number::from_int(4, IntType::I64)),
+        ("pow(2, 128) == U128_MAX + 1", Number::from_bool(true)),
+        ("-pow(2, 200) < I128_MIN", Number::from_bool(true)),
+        ("pow(2, 200) & 0xff", Number::from_int(0, IntType::I64)),
+        ("(~pow(2, 200)) & 0xff", Number::from_int(0xff, IntType::I64)),
+        ("is_pow_of_two(pow(2, 200))", Number::from_bool(true)),
+        ("is_pow_of_two(pow(2, 200) + 2)", Number::from_bool(false)),
+        ("!pow(2, 200)", Number::from_bool(false)),
+        ("pow(2, 200) ? 1 : 2", Number::from_int(1, IntType::I64)),
+        ("if(pow(2, 200), 1, 2)", Number::from_int(1, IntType::I64)),
+        ("pow(2, 200) && 1", Number::from_bool(true)),
+        ("sum(U64_MAX, U64_MAX, 2)", Number::from_int(1 << 65, IntType::I128)),
+        ("avg(pow(2, 200), 0) >> 199", Number::from_int(1, IntType::I64)),
+        ("b2kb(kb2b(pow(2, 120)))", Number::from_int(1 << 120, IntType::I128)),
+        // Casts still wrap at the width of their type.
+        ("(u64)pow(2, 200)", Number::from_int(0, IntType::U64)),
+        ("(u8)(pow(2, 200) - 1)", Number::from_int(0xff, IntType::U8)),
+        ("(u8)200 + (u8)100", Number::from_int(44, IntType::U8)),
+        ("(u8)200 + 100", Number::from_int(300, IntType::I64)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr_with(&mut eval_ctx, expr_res.0, &expr_res.1);
+    }
+
+    // Results that don't fit in 128 bits keep their low 128 bits.
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("pow(2, 200)", "1606938044258990275541962092341162602522202993782792835301376", 0, IntType::U128),
+        ("pow(2, 200) - 1", "1606938044258990275541962092341162602522202993782792835301375", u128::MAX, IntType::U128),
+        ("-pow(2, 200)", "-1606938044258990275541962092341162602522202993782792835301376", 0, IntType::I128),
+        ("U128_MAX * 2", "680564733841876926926749214863536422910", u128::MAX - 1, IntType::U128),
+        ("1 << 200", "1606938044258990275541962092341162602522202993782792835301376", 0, IntType::U128),
+        ("x = PiB * PiB * PiB", "1427247692705959881058285969449495136382746624", 0, IntType::U128),
+        ("x *= 2", "2854495385411919762116571938898990272765493248", 0, IntType::U128),
+    ];
+    for (str_expr, str_big, integer, int_type) in expr_results {
+        let number = spceval::evaluate_with(&mut eval_ctx, str_expr).unwrap();
+        assert!(number.is_truncated(), "{}", str_expr);
+        assert_eq!(number.to_string(), str_big, "{}", str_expr);
+        assert_eq!(number.as_int(), Some((integer, int_type)), "{}", str_expr);
+    }
+
+    test_invalid_expr_with(&mut eval_ctx, "pow(2, 70000)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr_with(&mut eval_ctx, "1 << 70000", ExprErrorKind::FailedEvaluation);
+    test_valid_expr_with(&mut eval_ctx, "0 << 70000", &Number::from_int(0, IntType::I64));
+    test_invalid_expr_with(&mut eval_ctx, "1 << (-1)", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr_with(&mut eval_ctx, "pow(2, 200) / 0", ExprErrorKind::FailedEvaluation);
+    test_invalid_expr_with(&mut eval_ctx, "pow(2, 200) % 0", ExprErrorKind::FailedEvaluation);
+
+    // Variables assigned in arbitrary-precision mode wrap when used in fixed-width mode.
+    eval_ctx.set_int_mode(IntMode::Fixed);
+    let number = spceval::evaluate_with(&mut eval_ctx, "x + 1").unwrap();
+    assert_eq!(number, Number::from_int(1, IntType::U128));
+    test_valid_expr("U64_MAX * U64_MAX", &Number::from_int(1, IntType::U64));
+}
+
+#[test]
+fn valid_exprs_word_mode() {
+    let mut eval_ctx = EvalContext::new();
+    test_valid_expr_with(&mut eval_ctx, "x = 0x1234", &Number::from_int(0x1234, IntType::I64));
+    eval_ctx.set_int_mode(IntMode::Word(IntType::I8));
+
+    // All integers, including literals, constants, variables and casts, are of the word's type.
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("~0", Number::from_int(0xff, IntType::I8)),
+        ("200", Number::from_int(0xc8, IntType::I8)),
+        ("0x1234", Number::from_int(0x34, IntType::I8)),
+        ("U64_MAX", Number::from_int(0xff, IntType::I8)),
+        ("0x7f + 1", Number::from_int(0x80, IntType::I8)),
+        ("1 << 8", Number::from_int(0, IntType::I8)),
+        ("0x80 >> 7", Number::from_int(0xff, IntType::I8)),
+        ("0x80 < 0", Number::from_bool(true)),
+        ("(u8)0xff + 1", Number::from_int(0, IntType::I8)),
+        ("(u16)0x1234", Number::from_int(0x34, IntType::I8)),
+        ("x", Number::from_int(0x34, IntType::I8)),
+        ("x += 0x50", Number::from_int(0x84, IntType::I8)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr_with(&mut eval_ctx, expr_res.0, &expr_res.1);
+    }
+
+    eval_ctx.set_int_mode(IntMode::Word(IntType::U16));
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("0xb800 * 16 + 0x10", Number::from_int(0x8010, IntType::U16)),
+        ("0 - 1", Number::from_int(0xffff, IntType::U16)),
+        ("0xffff < 0x8000", Number::from_bool(false)),
+        ("x", Number::from_int(0xff84, IntType::U16)),
+        ("(u32)0x12345678", Number::from_int(0x5678, IntType::U16)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr_with(&mut eval_ctx, expr_res.0, &expr_res.1);
+    }
+
+    // Variables keep the type they were assigned with.
+    eval_ctx.set_int_mode(IntMode::Fixed);
+    test_valid_expr_with(&mut eval_ctx, "x", &Number::from_int(0x84, IntType::I8));
+}
+
+#[test]
+fn valid_exprs_overflow() {
+    let mut eval_ctx = EvalContext::new();
+    test_valid_expr_with(&mut eval_ctx, "I64_MAX + 1", &Number::from_int(1 << 63, IntType::I64));
+
+    // Overflows are reported at the offending operator or function.
+    eval_ctx.set_overflow(Overflow::Checked);
+    #[rustfmt::skip]
+    let expr_errors = vec![
+        ("I64_MAX + 1", 8),
+        ("1 + (I64_MIN - 1)", 13),
+        ("-I64_MIN", 0),
+        ("U64_MAX * 2", 8),
+        ("I64_MIN / (-1)", 8),
+        ("(u8)0xf0 << 4", 9),
+        ("1 << 64", 2),
+        ("1 << 100000", 2),
+        ("(u32)1 - (u32)2", 7),
+        ("2 * sum(I64_MAX, 1)", 4),
+        ("kb2b(U64_MAX)", 0),
+        ("align_up(U64_MAX, 16)", 0),
+        ("abs(I64_MIN)", 0),
+        ("lcm(U64_MAX, 2)", 0),
+    ];
+    for (str_expr, idx_expr) in expr_errors {
+        let res = spceval::evaluate_with(&mut eval_ctx, str_expr);
+        let err = res.expect_err(str_expr);
+        assert_eq!(err.kind(), ExprErrorKind::FailedEvaluation, "{}", str_expr);
+        assert_eq!(err.index(), idx_expr, "{}", str_expr);
+    }
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("I64_MAX - 1 + 1", Number::from_int(i64::MAX as u128, IntType::I64)),
+        ("(u8)0x0f << 3 >> 3", Number::from_int(0x0f, IntType::U8)),
+        ("~0 & (1 << 62)", Number::from_int(1 << 62, IntType::I64)),
+        ("(u8)0x1ff", Number::from_int(0xff, IntType::U8)),
+        ("avg(I64_MAX, I64_MAX)", Number::from_int(i64::MAX as u128, IntType::I64)),
+        ("avg(U64_MAX, U64_MAX - 2)", Number::from_int(u64::MAX as u128 - 1, IntType::U64)),
+        ("avg(I64_MIN, 0 - 1)", Number::from_int((i64::MIN / 2) as u64 as u128, IntType::I64)),
+        ("0 << 64", Number::from_int(0, IntType::I64)),
+        ("0 << 100000", Number::from_int(0, IntType::I64)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr_with(&mut eval_ctx, expr_res.0, &expr_res.1);
+    }
+    test_valid_expr_with(&mut eval_ctx, "x = I64_MAX", &Number::from_int(i64::MAX as u128, IntType::I64));
+    test_invalid_expr_with(&mut eval_ctx, "x += 1", ExprErrorKind::FailedEvaluation);
+    eval_ctx.set_int_mode(IntMode::Word(IntType::U8));
+    test_invalid_expr_with(&mut eval_ctx, "0 - 1", ExprErrorKind::FailedEvaluation);
+    eval_ctx.set_int_mode(IntMode::Arbitrary);
+    test_valid_expr_with(&mut eval_ctx, "0 - 1", &Number::from_int(u64::MAX as u128, IntType::I64));
+
+    // Overflows produce the closest value of the type.
+    eval_ctx.set_int_mode(IntMode::Fixed);
+    eval_ctx.set_overflow(Overflow::Saturating);
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("I64_MAX + 1", Number::from_int(i64::MAX as u128, IntType::I64)),
+        ("I64_MIN - 1", Number::from_int(i64::MIN as u64 as u128, IntType::I64)),
+        ("U64_MAX * 2", Number::from_int(u64::MAX as u128, IntType::U64)),
+        ("(u32)1 - (u32)2", Number::from_int(0, IntType::U32)),
+        ("(i8)-100 * (i8)2", Number::from_int(0x80, IntType::I8)),
+        ("1 << 100000", Number::from_int(i64::MAX as u128, IntType::I64)),
+        ("(0 - 1) << 100000", Number::from_int(i64::MIN as u64 as u128, IntType::I64)),
+        ("(u8)1 << 8", Number::from_int(0xff, IntType::U8)),
+        ("0 << 100000", Number::from_int(0, IntType::I64)),
+        ("x += 1", Number::from_int(i64::MAX as u128, IntType::I64)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr_with(&mut eval_ctx, expr_res.0, &expr_res.1);
+    }
+
+    // Like pow(), "**" fails on overflow regardless of the overflow policy.
+    let err = spceval::evaluate_with(&mut eval_ctx, "((i32)2) ** 31").unwrap_err();
+    assert_eq!(err.kind(), ExprErrorKind::FailedEvaluation);
+    assert_eq!(err.index(), 9);
+    assert!(err.message().starts_with("for operator '**' at 9"), "{}", err.message());
+
+    // As is aligning up past the largest integer of the type.
+    let err = spceval::evaluate_with(&mut eval_ctx, "align_up(U64_MAX, 4096)").unwrap_err();
+    assert_eq!(err.kind(), ExprErrorKind::FailedEvaluation);
+    assert_eq!(err.index(), 0);
+}
+
+#[test]
+fn valid_exprs_vars() {
+    // Expressions are evaluated in order using the same context, so later
+    // expressions can use variables assigned by earlier ones.
+    let mut eval_ctx = EvalContext::new();
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("base = 0xfee00000", Number::from_int(0xfee00000, IntType::I64)),
+        ("base", Number::from_int(0xfee00000, IntType::I64)),
+        ("base + 0x20", Number::from_int(0xfee00020, IntType::I64)),
+        ("  mask=~0xfff", Number::from_int(!0xfffu64 as u128, IntType::I64)),
+        ("(base + 0x3f0) & mask", Number::from_int(0xfee00000, IntType::I64)),
+        ("_x1 = 5", Number::from_int(5, IntType::I64)),
+        ("_x1 += 3", Number::from_int(8, IntType::I64)),
+        ("_x1 -= 1", Number::from_int(7, IntType::I64)),
+        ("_x1 *= 2", Number::from_int(14, IntType::I64)),
+        ("_x1 |= 1", Number::from_int(15, IntType::I64)),
+        ("_x1 <<= 4", Number::from_int(240, IntType::I64)),
+        ("_x1 >>= 2", Number::from_int(60, IntType::I64)),
+        ("_x1 &= 0x3c", Number::from_int(60, IntType::I64)),
+        ("_x1 ^= 0xff", Number::from_int(0xc3, IntType::I64)),
+        ("_x1 /= 3", Number::from_int(65, IntType::I64)),
+        ("_x1 %= 7", Number::from_int(2, IntType::I64)),
+        ("_x1 **= 3", Number::from_int(8, IntType::I64)),
+        ("_x1 /= 4", Number::from_int(2, IntType::I64)),
+        ("_x1 == 2", Number::from_bool(true)),
+        ("_x1 <= 1", Number::from_bool(false)),
+        ("_x1 >= 2", Number::from_bool(true)),
+        ("_x1 != 2", Number::from_bool(false)),
+        ("bit(_x1)", Number::from_int(4, IntType::I64)),
+        ("bit((_x1))", Number::from_int(4, IntType::I64)),
+        ("avg(base, base + 2)", Number::from_int(0xfee00001, IntType::I64)),
+        ("_x1 = _x1 * _x1 + 1", Number::from_int(5, IntType::I64)),
+        ("_x1 != 0 && 100 / _x1 > 2", Number::from_bool(true)),
+        ("bits2 = 3", Number::from_int(3, IntType::I64)),
+        ("bits(bits2, 0)", Number::from_int(0xf, IntType::I64)),
+    ];
+    for expr_res in expr_results {
+        test_valid_expr_with(&mut eval_ctx, expr_res.0, &expr_res.1);
+    }
+
+    assert_eq!(eval_ctx.var("base").unwrap().as_int(), Some((0xfee00000, IntType::I64)));
+    assert_eq!(eval_ctx.var("_x1").unwrap().as_int(), Some((5, IntType::I64)));
+    assert!(eval_ctx.var("undefined").is_none());
+}
+
+#[test]
+fn invalid_exprs_vars() {
+    let mut eval_ctx = EvalContext::new();
+    test_valid_expr_with(&mut eval_ctx, "x = 2", &Number::from_int(2, IntType::I64));
+
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("y", ExprErrorKind::UndefinedVariable),
+        ("x + y", ExprErrorKind::UndefinedVariable),
+        ("z = y", ExprErrorKind::UndefinedVariable),
+        ("y += 1", ExprErrorKind::UndefinedVariable),
+        ("x = 1/0", ExprErrorKind::FailedEvaluation),
+        ("x /= 0", ExprErrorKind::FailedEvaluation),
+        ("avg = 5", ExprErrorKind::InvalidAssignment),
+        ("bit += 1", ExprErrorKind::InvalidAssignment),
+        ("x =", ExprErrorKind::EmptyExpr),
+        ("x +=", ExprErrorKind::EmptyExpr),
+        ("= 5", ExprErrorKind::InvalidExpr),
+        ("5 = x", ExprErrorKind::InvalidExpr),
+        ("x = = 5", ExprErrorKind::InvalidExpr),
+        ("x = y = 5", ExprErrorKind::InvalidExpr),
+        ("x += = 5", ExprErrorKind::InvalidExpr),
+        ("x x", ExprErrorKind::MissingOperator),
+        ("x 5", ExprErrorKind::MissingOperator),
+        ("x avg(1,2)", ExprErrorKind::MissingOperator),
+        ("x(5)", ExprErrorKind::MissingOperatorOrFunction),
+        ("(x)x", ExprErrorKind::MissingOperatorOrFunction),
+        ("avg x", ExprErrorKind::MissingParenthesis),
+    ];
+    for expr_res in expr_results {
+        test_invalid_expr_with(&mut eval_ctx, expr_res.0, expr_res.1);
+    }
+
+    // Failed assignments must not modify variables.
+    assert_eq!(eval_ctx.var("x").unwrap().as_int(), Some((2, IntType::I64)));
+    assert!(eval_ctx.var("z").is_none());
+
+    // Variables don't persist without a context.
+    test_valid_expr("x = 2", &Number::from_int(2, IntType::I64));
+    test_invalid_expr("x", ExprErrorKind::UndefinedVariable);
+}
+
+#[test]
+fn compiled_exprs() {
+    // A compiled expression evaluates like the same expression evaluated directly, for any value of its variables.
+    let mut eval_ctx = EvalContext::new();
+    eval_ctx.set_var("base", Number::from_int(0xfee00000, IntType::U64));
+    let compiled = spceval::compile("x > 7 ? base + (x << 12) : 0 - x").unwrap();
+    for x in 0..16 {
+        eval_ctx.set_var("x", Number::from_int(x, IntType::I64));
+        let num_expected = spceval::evaluate_with(&mut eval_ctx, "x > 7 ? base + (x << 12) : 0 - x").unwrap();
+        let num_compiled = compiled.evaluate(&mut eval_ctx).unwrap();
+        assert_eq!(num_compiled, num_expected, "x = {}", x);
+    }
+
+    // Assignments and compound assignments are made on every evaluation.
+    let compiled = spceval::compile("count += 2").unwrap();
+    eval_ctx.set_var("count", Number::from_int(0, IntType::I64));
+    for _ in 0..5 {
+        compiled.evaluate(&mut eval_ctx).unwrap();
+    }
+    assert_eq!(eval_ctx.var("count").unwrap().as_int(), Some((10, IntType::I64)));
+
+    // Errors are reported when compiling or when evaluating, like they are for evaluating directly.
+    assert_eq!(spceval::compile("2 +").err().map(|err| err.kind()), Some(ExprErrorKind::InvalidParamCount));
+    let compiled = spceval::compile("100 / y").unwrap();
+    assert_eq!(compiled.evaluate(&mut eval_ctx).err().map(|err| err.kind()), Some(ExprErrorKind::UndefinedVariable));
+    eval_ctx.set_var("y", Number::from_int(0, IntType::I64));
+    assert_eq!(compiled.evaluate(&mut eval_ctx).err().map(|err| err.kind()), Some(ExprErrorKind::FailedEvaluation));
+    eval_ctx.set_var("y", Number::from_int(4, IntType::I64));
+    assert_eq!(compiled.evaluate(&mut eval_ctx).unwrap().as_int(), Some((25, IntType::I64)));
+}
+
+#[test]
+fn valid_exprs_user_funcs() {
+    let mut eval_ctx = EvalContext::new();
+    spceval::define(&mut eval_ctx, "align(x, a) = (x + a - 1) & ~(a - 1)").unwrap();
+    spceval::define(&mut eval_ctx, "  pfn ( addr ) =addr >> 12").unwrap();
+    spceval::define(&mut eval_ctx, "answer() = 42").unwrap();
+    spceval::define(&mut eval_ctx, "page_end(addr) = align(addr + 1, PAGE_SIZE) - 1").unwrap();
+    spceval::define(&mut eval_ctx, "half(x) = x / 2").unwrap();
+    spceval::define(&mut eval_ctx, "safe_div(x, y) = y ? x / y : 0").unwrap();
+
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("align(0x1001, 0x1000)", Number::from_int(0x2000, IntType::I64)),
+        ("align(0x1000, 0x1000)", Number::from_int(0x1000, IntType::I64)),
+        ("pfn(0xfee00000) + 1", Number::from_int(0xfee01, IntType::I64)),
+        ("answer()", Number::from_int(42, IntType::I64)),
+        ("page_end(0x1234)", Number::from_int(0x1fff, IntType::I64)),
+        ("avg(half(8), half(4.0))", Number::from_f64(3.0)),
+        ("safe_div(7, 0) + safe_div(7, 2)", Number::from_int(3, IntType::I64)),
+        ("x = align(5, 8)", Number::from_int(8, IntType::I64)),
+        // Variables and functions are separate, a variable can have the name of a function.
+        ("half = 3", Number::from_int(3, IntType::I64)),
+        ("half(x) + half", Number::from_int(7, IntType::I64)),
+    ];
+    for (str_expr, number) in expr_results {
+        test_valid_expr_with(&mut eval_ctx, str_expr, &number);
+    }
+
+    // Functions and compiled expressions keep calling the definition they were parsed with.
+    let compiled = spceval::compile_with(&eval_ctx, "page_end(x)").unwrap();
+    spceval::define(&mut eval_ctx, "align(x, a) = x").unwrap();
+    test_valid_expr_with(&mut eval_ctx, "page_end(x)", &Number::from_int(0xfff, IntType::I64));
+    test_valid_expr_with(&mut eval_ctx, "align(x, 16)", &Number::from_int(8, IntType::I64));
+    assert_eq!(compiled.evaluate(&mut eval_ctx).unwrap().as_int(), Some((0xfff, IntType::I64)));
+
+    // Definitions are normalized.
+    let mut funcs: Vec<_> = eval_ctx.funcs().filter(|(name, _)| name.starts_with('p')).collect();
+    funcs.sort();
+    assert_eq!(
+        funcs,
+        [("page_end", "page_end(addr) = align(addr + 1, PAGE_SIZE) - 1"), ("pfn", "pfn(addr) = addr >> 12")]
+    );
+    assert!(eval_ctx.remove_func("pfn"));
+    test_invalid_expr_with(&mut eval_ctx, "pfn(0x1000)", ExprErrorKind::MissingOperatorOrFunction);
+}
+
+#[test]
+fn invalid_exprs_user_funcs() {
+    let mut eval_ctx = EvalContext::new();
+    spceval::define(&mut eval_ctx, "f(x, y) = x / y").unwrap();
+
+    #[rustfmt::skip]
+    let def_results = vec![
+        ("", ExprErrorKind::InvalidDefinition, 0),
+        ("5(x) = x", ExprErrorKind::InvalidDefinition, 0),
+        ("avg(x) = x", ExprErrorKind::InvalidDefinition, 0),
+        ("pi(x) = x", ExprErrorKind::InvalidDefinition, 0),
+        ("g = 5", ExprErrorKind::MissingParenthesis, 2),
+        ("g(x y) = x", ExprErrorKind::InvalidDefinition, 4),
+        ("g(x, ) = x", ExprErrorKind::InvalidDefinition, 5),
+        ("g(x, x) = x", ExprErrorKind::InvalidDefinition, 5),
+        ("g(e) = e", ExprErrorKind::InvalidDefinition, 2),
+        ("g(x", ExprErrorKind::InvalidDefinition, 3),
+        ("g(x) x", ExprErrorKind::InvalidDefinition, 5),
+        ("g(x) == x", ExprErrorKind::InvalidDefinition, 5),
+        ("g(x) =", ExprErrorKind::EmptyExpr, 5),
+        ("g(x) = x +", ExprErrorKind::InvalidParamCount, 9),
+        ("g(x) = x + y", ExprErrorKind::UndefinedVariable, 11),
+        ("g(x) = y = x", ExprErrorKind::InvalidDefinition, 7),
+        ("g(x) = g(x)", ExprErrorKind::MissingOperatorOrFunction, 8),
+        ("g(x) = f(x)", ExprErrorKind::InvalidParamCount, 7),
+    ];
+    for (str_def, expr_error_kind, idx_expr) in def_results {
+        let err = spceval::define(&mut eval_ctx, str_def).unwrap_err();
+        assert_eq!((err.kind(), err.index()), (expr_error_kind, idx_expr), "{}", str_def);
+    }
+    assert_eq!(eval_ctx.funcs().count(), 1);
+
+    // Errors in the body are reported at the call.
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("f(1)", ExprErrorKind::InvalidParamCount),
+        ("f(1, 2, 3)", ExprErrorKind::InvalidParamCount),
+        ("f 1", ExprErrorKind::MissingOperator),
+        ("1 + f(1, 0)", ExprErrorKind::FailedEvaluation),
+        ("f(1, y)", ExprErrorKind::UndefinedVariable),
+    ];
+    for (str_expr, expr_error_kind) in expr_results {
+        test_invalid_expr_with(&mut eval_ctx, str_expr, expr_error_kind);
+    }
+    assert_eq!(spceval::evaluate_with(&mut eval_ctx, "1 + f(1, 0)").unwrap_err().index(), 4);
+
+    // Functions aren't known without the context they were defined in.
+    test_invalid_expr("f(1, 2)", ExprErrorKind::MissingOperatorOrFunction);
+}
+
+// Looks up the base address of a device by its index in an address map, like an application
+// embedding the library would.
+struct DeviceBase {
+    bases: Vec<u64>,
+}
+
+impl Function for DeviceBase {
+    fn name(&self) -> &str {
+        "dev_base"
+    }
+
+    fn params(&self) -> Range<u8> {
+        Range { start: 1, end: 2 }
+    }
+
+    fn syntax(&self) -> &str {
+        "<index>"
+    }
+
+    fn help(&self) -> &str {
+        "Base address of device"
+    }
+
+    fn evaluate(&self, idx_expr: usize, params: &[Number]) -> Result<Number, ExprError> {
+        match self.bases.get(params[0].to_u32().unwrap_or(u32::MAX) as usize) {
+            Some(base) => Ok(Number::from_int(*base as u128, IntType::U64)),
+            None => {
+                Err(ExprError::new(idx_expr, ExprErrorKind::FailedEvaluation, format!("for device at {}", idx_expr)))
+            }
+        }
+    }
+}
+
+// Counts down from its last result, so it has no parameters.
+struct Countdown(std::sync::atomic::AtomicU64);
+
+impl Function for Countdown {
+    fn name(&self) -> &str {
+        "avg"
+    }
+
+    fn params(&self) -> Range<u8> {
+        Range { start: 0, end: 1 }
+    }
+
+    fn syntax(&self) -> &str {
+        ""
+    }
+
+    fn help(&self) -> &str {
+        "Count down"
     }
+
+    fn evaluate(&self, _idx_expr: usize, _params: &[Number]) -> Result<Number, ExprError> {
+        let count = self.0.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+        Ok(Number::from_int(count as u128, IntType::I64))
+    }
+}
+
+#[test]
+fn registered_funcs() {
+    let mut eval_ctx = EvalContext::new();
+    eval_ctx.registry_mut().register(DeviceBase { bases: vec![0xfee00000, 0xfec00000] }).unwrap();
+    spceval::define(&mut eval_ctx, "dev_reg(dev, reg) = dev_base(dev) + reg * 0x10").unwrap();
+
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("dev_base(1)", Number::from_int(0xfec00000, IntType::U64)),
+        ("dev_base(0) + 0x20", Number::from_int(0xfee00020, IntType::U64)),
+        ("dev_reg(1, 3)", Number::from_int(0xfec00030, IntType::U64)),
+        ("avg(dev_base(0), dev_base(1)) >> 20", Number::from_int(0xfed, IntType::U64)),
+    ];
+    for (str_expr, number) in expr_results {
+        test_valid_expr_with(&mut eval_ctx, str_expr, &number);
+    }
+
+    #[rustfmt::skip]
+    let expr_results = vec![
+        ("dev_base(2)", ExprErrorKind::FailedEvaluation),
+        ("dev_base(0, 1)", ExprErrorKind::InvalidParamCount),
+        ("dev_base", ExprErrorKind::InvalidParamCount),
+        ("dev_base = 5", ExprErrorKind::InvalidAssignment),
+    ];
+    for (str_expr, expr_error_kind) in expr_results {
+        test_invalid_expr_with(&mut eval_ctx, str_expr, expr_error_kind);
+    }
+    let err = spceval::define(&mut eval_ctx, "dev_base(x) = x").unwrap_err();
+    assert_eq!(err.kind(), ExprErrorKind::InvalidDefinition);
+
+    // Contexts share the registry until one of them modifies it.
+    let mut eval_ctx_clone = eval_ctx.clone();
+    eval_ctx_clone.registry_mut().register(Countdown(3.into())).unwrap();
+    test_valid_expr_with(&mut eval_ctx_clone, "avg() + avg()", &Number::from_int(5, IntType::I64));
+    test_invalid_expr_with(&mut eval_ctx_clone, "avg(1, 2)", ExprErrorKind::InvalidParamCount);
+    test_valid_expr_with(&mut eval_ctx, "avg(1, 3)", &Number::from_int(2, IntType::I64));
+    assert!(eval_ctx.registry().contains("dev_base"));
+    assert_eq!(eval_ctx.registry().iter().filter(|func| func.name() == "avg").count(), 1);
+    assert_eq!(eval_ctx_clone.registry().iter().find(|func| func.name() == "avg").unwrap().help(), "Count down");
+    assert!(eval_ctx_clone.registry_mut().unregister("avg"));
+    test_valid_expr_with(&mut eval_ctx_clone, "avg(1, 3)", &Number::from_int(2, IntType::I64));
+
+    // Applications can leave out the built-in functions.
+    let mut registry = FuncRegistry::without_builtins();
+    registry.register(Named("pages", Range { start: 1, end: 2 })).unwrap();
+    registry.register(DeviceBase { bases: vec![0x1000] }).unwrap();
+    let mut eval_ctx = EvalContext::new();
+    *eval_ctx.registry_mut() = registry;
+    test_valid_expr_with(&mut eval_ctx, "dev_base(0)", &Number::from_int(0x1000, IntType::U64));
+    test_invalid_expr_with(&mut eval_ctx, "avg(1, 2)", ExprErrorKind::MissingOperatorOrFunction);
+    let names: Vec<&str> = eval_ctx.registry().iter().map(|func| func.name()).collect();
+    assert_eq!(names, ["dev_base", "pages"]);
+}
+
+// Returns its first parameter, under any name and number of parameters.
+struct Named(&'static str, Range<u8>);
+
+impl Function for Named {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn params(&self) -> Range<u8> {
+        self.1.clone()
+    }
+
+    fn syntax(&self) -> &str {
+        "<n>"
+    }
+
+    fn help(&self) -> &str {
+        "First parameter"
+    }
+
+    fn evaluate(&self, _idx_expr: usize, params: &[Number]) -> Result<Number, ExprError> {
+        Ok(params.first().cloned().unwrap_or(Number::from_int(0, IntType::I64)))
+    }
+}
+
+#[test]
+fn invalid_registered_funcs() {
+    let mut eval_ctx = EvalContext::new();
+    #[rustfmt::skip]
+    let funcs = vec![
+        Named("", Range { start: 1, end: 2 }),
+        Named("dev base", Range { start: 1, end: 2 }),
+        Named(" dev_base", Range { start: 1, end: 2 }),
+        Named("1dev", Range { start: 1, end: 2 }),
+        Named("dev+base", Range { start: 1, end: 2 }),
+        Named("PAGE_SIZE", Range { start: 1, end: 2 }),
+        Named("if", Range { start: 3, end: 4 }),
+        Named("bytes", Range { start: 1, end: 2 }),
+        Named("dev_base", Range { start: 0, end: 0 }),
+        Named("dev_base", Range { start: 5, end: 2 }),
+    ];
+    for func in funcs {
+        let name = func.0;
+        let err = eval_ctx.registry_mut().register(func).unwrap_err();
+        assert_eq!(err.kind(), ExprErrorKind::InvalidDefinition, "{}", name);
+    }
+    assert!(!eval_ctx.registry().contains("dev_base"));
+    test_valid_expr_with(&mut eval_ctx, "if(0, 1 / 0, 2)", &Number::from_int(2, IntType::I64));
+    test_valid_expr_with(&mut eval_ctx, "PAGE_SIZE", &Number::from_int(4096, IntType::I64));
+
+    // Other built-in functions can be replaced.
+    eval_ctx.registry_mut().register(Named("max", Range { start: 1, end: 3 })).unwrap();
+    test_valid_expr_with(&mut eval_ctx, "max(1, 2)", &Number::from_int(1, IntType::I64));
 }
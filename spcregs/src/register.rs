@@ -42,10 +42,8 @@ impl<'a, T: BitRegister> Register<'a, T> {
 
 impl<'a, T: BitRegister> fmt::Display for Register<'a, T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.value.is_none() {
-            write!(f, "{}", self.descriptor)
-        } else {
-            let res: Result<RegisterValue, _> = self.value.unwrap().try_into();
+        if let Some(value) = self.value {
+            let res: Result<RegisterValue, _> = value.try_into();
             match res {
                 Err(_) => write!(f, "Couldn't convert register value"),
                 Ok(val) => {
@@ -53,7 +51,7 @@ impl<'a, T: BitRegister> fmt::Display for Register<'a, T> {
                     assert!(bit_count > 0);
 
                     // First write out the binary bits seperated into groups of 4.
-                    writeln!(f, " {}", utils::get_binary_string(val, Some(bit_count as u32)))?;
+                    writeln!(f, " {}", utils::get_binary_string(val.into(), Some(bit_count as u32)))?;
 
                     // On Windows, we use ASCII encoding as it's likely terminals
                     // don't support or use UTF8 as the default.
@@ -122,6 +120,8 @@ impl<'a, T: BitRegister> fmt::Display for Register<'a, T> {
                     Ok(())
                 }
             }
+        } else {
+            write!(f, "{}", self.descriptor)
         }
     }
 }
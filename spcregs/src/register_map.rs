@@ -47,4 +47,4 @@ static REGISTERS: LazyLock<[&RegisterDescriptor; 3]> = LazyLock::new(|| {[
 ]});
 
 // Can't we push this to the consumer?
-pub static REGISTERMAP: LazyLock<RegisterMap> = LazyLock::new(|| RegisterMap::new());
+pub static REGISTERMAP: LazyLock<RegisterMap> = LazyLock::new(RegisterMap::new);
@@ -1,12 +1,12 @@
-pub fn get_binary_string(val: u64, fixed_bit_width: Option<u32>) -> String {
+pub fn get_binary_string(val: u128, fixed_bit_width: Option<u32>) -> String {
     // Formats the number as binary digits with a space (from the right) for every 4 binary digits.
     static BIN_DIGITS: [char; 2] = ['0', '1'];
-    let mut bin_out: Vec<char> = Vec::with_capacity(88);
+    let mut bin_out: Vec<char> = Vec::with_capacity(160);
     let mut val = val;
 
     let bit_width = match fixed_bit_width {
-        Some(fixed_bit_width) if fixed_bit_width <= u64::BITS => fixed_bit_width,
-        _ => u64::MAX.count_ones() - val.leading_zeros(),
+        Some(fixed_bit_width) if fixed_bit_width <= u128::BITS => fixed_bit_width,
+        _ => u128::MAX.count_ones() - val.leading_zeros(),
     };
 
     // Push first bit (to avoid extra branch in the loop for not pushing ' ' on 0th iteration).
@@ -36,12 +36,12 @@ pub fn get_binary_ruler_string(bit_count: u8) -> String {
     // is insufficient to hold 32 bits). Panic is better than failing in weird ways.
     let bit_count = usize::from(bit_count);
 
-    // Ensure if we ever add 128-bit support this code will at least assert.
-    debug_assert!(bit_count <= 64);
+    // Ensure if we ever add 256-bit support this code will at least assert.
+    debug_assert!(bit_count <= 128);
 
     if bit_count >= 8 {
-        let mut ruler_out = String::with_capacity(88);
-        static BIN_RULER: [&str; 8] = [
+        let mut ruler_out = String::with_capacity(176);
+        static BIN_RULER: [&str; 16] = [
             "|  7:0  |",
             "| 15:8  | ",
             "| 23:16 | ",
@@ -50,6 +50,14 @@ pub fn get_binary_ruler_string(bit_count: u8) -> String {
             "| 47:40 | ",
             "| 55:48 | ",
             "| 63:56 | ",
+            "| 71:64 | ",
+            "| 79:72 | ",
+            "| 87:80 | ",
+            "| 95:88 | ",
+            "|103:96 | ",
+            "|111:104| ",
+            "|119:112| ",
+            "|127:120| ",
         ];
 
         // First we need to pad with spaces at the start for those binary digits
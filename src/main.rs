@@ -1,7 +1,8 @@
-use spceval::{ExprError, Number};
+use spceval::{EvalContext, ExprError, IntMode, IntType, Number, Overflow};
 use spcregs::{BitRegister, Register, RegisterDescriptor, RegisterMap};
 use std::env;
 use std::io::{IsTerminal, Write};
+use std::path::Path;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 #[cfg(debug_assertions)]
@@ -16,7 +17,13 @@ static DEC_RADIX: &str = "Dec :";
 static HEX_RADIX: &str = "Hex :";
 static OCT_RADIX: &str = "Oct :";
 static BIN_RADIX: &str = "Bin :";
+static ASCII_CHARS: &str = "Asc :";
 static EXITING_APP: &str = "Exiting:";
+static INT_MODE: &str = "Integers:";
+static OVERFLOW: &str = "Overflow:";
+static FUNCTION: &str = "Function:";
+static CONSTANT: &str = "Constant:";
+static STARTUP_FILE: &str = ".spcrc";
 static BITS_PLURAL: &str = "bits";
 static BIT_SINGULAR: &str = "bit";
 
@@ -49,39 +56,110 @@ fn byte_index_to_char_index(str_expr: &str, idx_byte: usize) -> usize {
     idx_char
 }
 
-fn write_result(spcio: &mut SpcIo, number: &Number) -> std::io::Result<()> {
+fn write_big_result(spcio: &mut SpcIo, number: &Number) -> std::io::Result<()> {
+    // Integers that don't fit in 128 bits have no fixed width, so there's nothing to pad them to.
+    let big = number.as_big().unwrap();
+    let str_bool = if number.is_zero() { "false" } else { "true" };
+
+    write_color(&mut spcio.stream, BOOL_RADIX, Color::Cyan, true)?;
+    writeln!(spcio.stream, " {} (nat)", str_bool)?;
+    write_color(&mut spcio.stream, DEC_RADIX, Color::Cyan, true)?;
+    writeln!(spcio.stream, " {} (int)  {:e} (f)", big, number.to_f64())?;
+    write_color(&mut spcio.stream, HEX_RADIX, Color::Cyan, true)?;
+    writeln!(spcio.stream, " {:#x} (int)", big)?;
+    write_color(&mut spcio.stream, OCT_RADIX, Color::Cyan, true)?;
+    writeln!(spcio.stream, " {:#o} (int)", big)?;
+    write_color(&mut spcio.stream, BIN_RADIX, Color::Cyan, true)?;
+    writeln!(spcio.stream, " {:b} ({} {})", big, big.bits(), BITS_PLURAL)?;
+
+    // Write a blank line
+    writeln!(spcio.stream)?;
+    Ok(())
+}
+
+fn write_float_result(spcio: &mut SpcIo, float: f64) -> std::io::Result<()> {
+    // Floats have no integer radixes to show.
+    let str_bool = if float == 0.0 { "false" } else { "true" };
+
+    write_color(&mut spcio.stream, BOOL_RADIX, Color::Cyan, true)?;
+    writeln!(spcio.stream, " {:>24} (nat)", str_bool)?;
+    write_color(&mut spcio.stream, DEC_RADIX, Color::Cyan, true)?;
+    writeln!(spcio.stream, " {:>24} (f64)", float)?;
+
+    // Write a blank line
+    writeln!(spcio.stream)?;
+    Ok(())
+}
+
+fn write_result(spcio: &mut SpcIo, number: &Number, int_mode: IntMode) -> std::io::Result<()> {
+    // Booleans are shown as the i64 0 or 1.
+    let (integer, int_type, str_type) = match number {
+        Number::Int { .. } if number.is_truncated() => return write_big_result(spcio, number),
+        Number::Int { integer, int_type, .. } => (*integer, *int_type, format!("({})", int_type)),
+        Number::Float(float) => return write_float_result(spcio, *float),
+        Number::Bool(value) => (*value as u128, IntType::I64, "(bool)".to_string()),
+    };
+
+    // Integers only ever have the bits of their type set, so other radixes are padded to its width.
+    let bits = int_type.bits();
+    let str_dec = if int_type.is_signed() {
+        (int_type.extend(integer) as i128).to_string()
+    } else {
+        integer.to_string()
+    };
+
     // Format as hex
-    let str_hex_zfill = format!("{:#018x}", number.integer);
-    let str_hex = format!("{:#x}", number.integer);
+    let str_hex_zfill = format!("{:#0width$x}", integer, width = 2 + bits as usize / 4);
+    let str_hex = format!("{:#x}", integer);
 
     // Format as octal
-    let str_oct_zfill = format!("{:#022o}", number.integer);
-    let str_oct = format!("{:#o}", number.integer);
+    let str_oct_zfill = format!("{:#0width$o}", integer, width = 2 + bits.div_ceil(3) as usize);
+    let str_oct = format!("{:#o}", integer);
 
-    // Format as binary
-    let str_bin_sfill = spcregs::utils::get_binary_string(number.integer, None);
+    // Format as binary, padded to the width of the integer in word mode.
+    let opt_bin_width = match int_mode {
+        IntMode::Word(_) => Some(bits),
+        _ => None,
+    };
+    let str_bin_sfill = spcregs::utils::get_binary_string(integer, opt_bin_width);
 
     // Compute number of bits to make a binary ruler as well for writing the number of bits.
-    let mut bit_count = u64::MAX.count_ones() - number.integer.leading_zeros();
+    let mut bit_count = opt_bin_width.unwrap_or(u128::MAX.count_ones() - integer.leading_zeros());
     let str_bit_count;
     if bit_count < 2 {
-        bit_count = 1; // Required because bin_digits gets computed as 0 when number.integer is 0.
+        bit_count = 1; // Required because bin_digits gets computed as 0 when integer is 0.
         str_bit_count = BIT_SINGULAR;
     } else {
         str_bit_count = BITS_PLURAL;
     };
 
-    let str_bool = if number.integer != 0 { "true" } else { "false" };
+    let str_bool = if integer != 0 { "true" } else { "false" };
+
+    // Format as a character literal and the bytes in memory (little-endian), escaped so they can be
+    // evaluated again (e.g. "'ELF\x7f'" and "bytes(\"\x7fFLE\")"). Leading zero bytes are left out.
+    let bytes = integer.to_be_bytes();
+    let bytes = &bytes[bytes.iter().position(|&byte| byte != 0).unwrap_or(bytes.len())..];
+    let bytes_le: Vec<u8> = bytes.iter().rev().copied().collect();
+    let str_chars = format!("'{}'", bytes.escape_ascii());
+    let str_bytes = format!("bytes(\"{}\")", bytes_le.escape_ascii());
+
+    // Only show the characters when they're mostly printable, so the row isn't noise for most integers.
+    let count_printable = bytes.iter().filter(|byte| byte.is_ascii_graphic() || **byte == b' ').count();
+    let is_ascii = count_printable > bytes.len() - count_printable;
+
+    // Widen the columns for 128-bit integers so they stay aligned.
+    let width = str_oct_zfill.len().max(24);
+    let width_n = str_oct.len().max(26);
 
     // Write the formatted values
     write_color(&mut spcio.stream, BOOL_RADIX, Color::Cyan, true)?;
-    writeln!(spcio.stream, " {:>24} (nat)", str_bool)?;
+    writeln!(spcio.stream, " {:>width$} (nat)", str_bool)?;
     write_color(&mut spcio.stream, DEC_RADIX, Color::Cyan, true)?;
-    writeln!(spcio.stream, " {:>24} (u64)  {:>26} (f)", number.integer, number.float)?;
+    writeln!(spcio.stream, " {:>width$} {:<6}  {:>width_n$} (f)", str_dec, str_type, number.to_f64())?;
     write_color(&mut spcio.stream, HEX_RADIX, Color::Cyan, true)?;
-    writeln!(spcio.stream, " {:>24} (u64)  {:>26} (n)", str_hex_zfill, str_hex)?;
+    writeln!(spcio.stream, " {:>width$} {:<6}  {:>width_n$} (n)", str_hex_zfill, str_type, str_hex)?;
     write_color(&mut spcio.stream, OCT_RADIX, Color::Cyan, true)?;
-    writeln!(spcio.stream, " {:>24} (u64)  {:>26} (n)", str_oct_zfill, str_oct)?;
+    writeln!(spcio.stream, " {:>width$} {:<6}  {:>width_n$} (n)", str_oct_zfill, str_type, str_oct)?;
     write_color(&mut spcio.stream, BIN_RADIX, Color::Cyan, true)?;
     writeln!(spcio.stream, " {} ({} {})", str_bin_sfill, bit_count, str_bit_count)?;
 
@@ -91,6 +169,11 @@ fn write_result(spcio: &mut SpcIo, number: &Number) -> std::io::Result<()> {
         writeln!(spcio.stream, "      {}", str_bin_ruler)?;
     }
 
+    if is_ascii {
+        write_color(&mut spcio.stream, ASCII_CHARS, Color::Cyan, true)?;
+        writeln!(spcio.stream, " {:>width$} {:<6}  {:>width_n$} (le)", str_chars, str_type, str_bytes)?;
+    }
+
     // Write a blank line
     writeln!(spcio.stream)?;
     Ok(())
@@ -133,12 +216,12 @@ fn write_error(
     Ok(())
 }
 
-fn evaluate_expr(str_expr: &str) -> Result<Number, ExprError> {
+fn evaluate_expr(eval_ctx: &mut EvalContext, str_expr: &str) -> Result<Number, ExprError> {
     // Enable trace level logging while parsing and evaluating using spceval.
     #[cfg(debug_assertions)]
     log::set_max_level(log::LevelFilter::Trace);
 
-    let res = spceval::evaluate(str_expr);
+    let res = spceval::evaluate_with(eval_ctx, str_expr);
 
     // Disable logging.
     #[cfg(debug_assertions)]
@@ -147,31 +230,196 @@ fn evaluate_expr(str_expr: &str) -> Result<Number, ExprError> {
     res
 }
 
-fn evaluate_input(spcio: &mut SpcIo, reg_map: &RegisterMap, str_expr: &str, _app_mode: AppMode) -> std::io::Result<()> {
+fn evaluate_input(
+    spcio: &mut SpcIo,
+    reg_map: &RegisterMap,
+    eval_ctx: &mut EvalContext,
+    str_expr: &str,
+    _app_mode: AppMode,
+) -> std::io::Result<()> {
     let mut tokens = str_expr.trim().splitn(2, ' ').fuse();
     let cmd = tokens.next();
     let args = tokens.next();
     match cmd {
         Some("q") | Some("quit") | Some("exit") => std::process::exit(0),
         Some("") => Ok(()),
+        // Command words are only commands without an argument or with a valid one, so e.g. "word = 5"
+        // assigns a variable named "word".
+        Some("bigint") if args.is_none_or(|arg| parse_int_mode(arg).is_some()) => set_int_mode(spcio, eval_ctx, args),
+        Some("word") if args.is_none_or(|arg| parse_word_mode(arg).is_some()) => set_word_mode(spcio, eval_ctx, args),
+        Some("overflow") if args.is_none_or(|arg| parse_overflow(arg).is_some()) => set_overflow(spcio, eval_ctx, args),
+        Some("def") if args.is_none_or(is_def) => define_func(spcio, eval_ctx, args, _app_mode),
+        Some("consts") if args.is_none_or(|arg| spceval::constants().any(|(name, ..)| name == arg.trim())) => {
+            list_consts(spcio, args)
+        }
         Some(cmd) => {
             if let Some(reg) = reg_map.get(cmd) {
-                evaluate_register(spcio, reg, args, _app_mode)
+                evaluate_register(spcio, reg, eval_ctx, args, _app_mode)
             } else {
-                evaluate_expr_and_write_result(spcio, str_expr, AppMode::Interactive)
+                evaluate_expr_and_write_result(spcio, eval_ctx, str_expr, AppMode::Interactive)
             }
         }
         _ => {
             // Use the original input expression given by the user rather
             // than the trimmed expression as it would mess up the error caret position.
-            evaluate_expr_and_write_result(spcio, str_expr, AppMode::Interactive)
+            evaluate_expr_and_write_result(spcio, eval_ctx, str_expr, AppMode::Interactive)
         }
     }
 }
 
-fn evaluate_expr_and_write_result(spcio: &mut SpcIo, str_expr: &str, app_mode: AppMode) -> std::io::Result<()> {
-    match evaluate_expr(str_expr) {
-        Ok(number) => write_result(spcio, &number),
+fn write_int_mode(spcio: &mut SpcIo, int_mode: IntMode) -> std::io::Result<()> {
+    let str_mode = match int_mode {
+        IntMode::Fixed => "fixed-width".to_string(),
+        IntMode::Arbitrary => "arbitrary-precision".to_string(),
+        IntMode::Word(word_type) => format!("{}-bit words ({})", word_type.bits(), word_type),
+    };
+    write_color(&mut spcio.stream, INT_MODE, Color::Cyan, true)?;
+    writeln!(spcio.stream, " {}", str_mode)?;
+    writeln!(spcio.stream)?;
+    Ok(())
+}
+
+fn parse_int_mode(arg: &str) -> Option<IntMode> {
+    match arg.trim() {
+        "on" => Some(IntMode::Arbitrary),
+        "off" => Some(IntMode::Fixed),
+        _ => None,
+    }
+}
+
+fn set_int_mode(spcio: &mut SpcIo, eval_ctx: &mut EvalContext, args: Option<&str>) -> std::io::Result<()> {
+    if let Some(int_mode) = args.and_then(parse_int_mode) {
+        eval_ctx.set_int_mode(int_mode);
+    }
+    write_int_mode(spcio, eval_ctx.int_mode())
+}
+
+// Words are signed like integer literals unless an unsigned type is given (e.g. "word u16").
+fn parse_word_mode(arg: &str) -> Option<IntMode> {
+    let arg = arg.trim();
+    let opt_word_type = match arg {
+        "off" => return Some(IntMode::Fixed),
+        "8" | "16" | "32" | "64" => IntType::from_name(&format!("i{}", arg)),
+        _ => IntType::from_name(arg),
+    };
+    opt_word_type.filter(|word_type| word_type.bits() <= u64::BITS).map(IntMode::Word)
+}
+
+fn set_word_mode(spcio: &mut SpcIo, eval_ctx: &mut EvalContext, args: Option<&str>) -> std::io::Result<()> {
+    if let Some(int_mode) = args.and_then(parse_word_mode) {
+        eval_ctx.set_int_mode(int_mode);
+    }
+    write_int_mode(spcio, eval_ctx.int_mode())
+}
+
+fn parse_overflow(arg: &str) -> Option<Overflow> {
+    match arg.trim() {
+        "wrapping" => Some(Overflow::Wrapping),
+        "checked" => Some(Overflow::Checked),
+        "saturating" => Some(Overflow::Saturating),
+        _ => None,
+    }
+}
+
+fn set_overflow(spcio: &mut SpcIo, eval_ctx: &mut EvalContext, args: Option<&str>) -> std::io::Result<()> {
+    if let Some(overflow) = args.and_then(parse_overflow) {
+        eval_ctx.set_overflow(overflow);
+    }
+    let str_overflow = match eval_ctx.overflow() {
+        Overflow::Wrapping => "wrapping",
+        Overflow::Checked => "checked",
+        Overflow::Saturating => "saturating",
+    };
+    write_color(&mut spcio.stream, OVERFLOW, Color::Cyan, true)?;
+    writeln!(spcio.stream, " {}", str_overflow)?;
+    writeln!(spcio.stream)?;
+    Ok(())
+}
+
+// Whether the argument looks like a function definition (i.e. a name followed by its parameters),
+// so that errors in it are reported as such rather than as errors in an expression.
+fn is_def(arg: &str) -> bool {
+    arg.split_once('(').is_some_and(|(name, _)| {
+        let name = name.trim();
+        name.starts_with(|chr: char| chr.is_alphabetic() || chr == '_')
+            && name.chars().all(|chr| chr.is_alphanumeric() || chr == '_')
+    })
+}
+
+fn define_func(
+    spcio: &mut SpcIo,
+    eval_ctx: &mut EvalContext,
+    args: Option<&str>,
+    app_mode: AppMode,
+) -> std::io::Result<()> {
+    // Without a definition, list the defined functions.
+    let opt_name = match args {
+        Some(str_def) => match spceval::define(eval_ctx, str_def) {
+            Ok(()) => Some(str_def.trim_start().split(|chr: char| chr == '(' || chr.is_whitespace()).next().unwrap()),
+            // The extra 1 below is for the space following the command.
+            Err(e) => return write_error(spcio, str_def, Some("def".len() + 1), e, app_mode),
+        },
+        None => None,
+    };
+    let mut funcs: Vec<(&str, &str)> =
+        eval_ctx.funcs().filter(|(name, _)| opt_name.is_none_or(|n| n == *name)).collect();
+    funcs.sort();
+    for (_, def) in funcs {
+        write_color(&mut spcio.stream, FUNCTION, Color::Cyan, true)?;
+        writeln!(spcio.stream, " {}", def)?;
+    }
+    writeln!(spcio.stream)?;
+    Ok(())
+}
+
+fn list_consts(spcio: &mut SpcIo, args: Option<&str>) -> std::io::Result<()> {
+    // Without a name, list all the constants.
+    let opt_name = args.map(str::trim);
+    let consts: Vec<_> = spceval::constants().filter(|(name, ..)| opt_name.is_none_or(|n| n == *name)).collect();
+    for (name, number, help) in consts {
+        write_color(&mut spcio.stream, CONSTANT, Color::Cyan, true)?;
+        writeln!(spcio.stream, " {:<16} {:>40}  {}", name, number, help)?;
+    }
+    writeln!(spcio.stream)?;
+    Ok(())
+}
+
+// Defines the functions in the startup file in the user's home directory, one "def" command per
+// line (e.g. "def align(x, a) = (x + a - 1) & ~(a - 1)"). Empty lines and '#' comments are ignored.
+fn load_startup_file(spcio: &mut SpcIo, eval_ctx: &mut EvalContext) -> std::io::Result<()> {
+    let Some(home_dir) = env::var_os("HOME") else {
+        return Ok(());
+    };
+    let path = Path::new(&home_dir).join(STARTUP_FILE);
+    let Ok(str_file) = std::fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    for (idx_line, str_line) in str_file.lines().enumerate() {
+        let str_line = str_line.trim();
+        if str_line.is_empty() || str_line.starts_with('#') {
+            continue;
+        }
+        let str_err = match str_line.strip_prefix("def ") {
+            Some(str_def) => match spceval::define(eval_ctx, str_def) {
+                Ok(()) => continue,
+                Err(e) => e.to_string(),
+            },
+            None => "expected 'def'".to_string(),
+        };
+        write_color(&mut spcio.stream, "Error:", Color::Red, true)?;
+        writeln!(spcio.stream, " {}:{}: {}", path.display(), idx_line + 1, str_err)?;
+    }
+    Ok(())
+}
+
+fn evaluate_expr_and_write_result(
+    spcio: &mut SpcIo,
+    eval_ctx: &mut EvalContext,
+    str_expr: &str,
+    app_mode: AppMode,
+) -> std::io::Result<()> {
+    match evaluate_expr(eval_ctx, str_expr) {
+        Ok(number) => write_result(spcio, &number, eval_ctx.int_mode()),
         Err(e) => write_error(spcio, str_expr, None, e, app_mode),
     }
 }
@@ -186,15 +434,18 @@ fn write_reg_desc_title<T: BitRegister>(spcio: &mut SpcIo, register: &Register<T
 fn evaluate_register(
     spcio: &mut SpcIo,
     reg: &RegisterDescriptor,
+    eval_ctx: &mut EvalContext,
     opt_str_expr: Option<&str>,
     app_mode: AppMode,
 ) -> std::io::Result<()> {
     match opt_str_expr {
         Some(str_expr) => {
-            match evaluate_expr(str_expr) {
+            match evaluate_expr(eval_ctx, str_expr) {
                 Ok(number) => {
                     let mut reg: Register<u64> = Register::new(reg).unwrap();
-                    reg.set_value(number.integer);
+                    // Floats are truncated like "(u64)x" would.
+                    let (integer, _) = number.cast(IntType::U64).as_int().unwrap();
+                    reg.set_value(integer as u64);
                     write_reg_desc_title(spcio, &reg)?;
                     writeln!(spcio.stream, "{}", reg)?;
                 }
@@ -213,15 +464,16 @@ fn evaluate_register(
     Ok(())
 }
 
-fn interactive_mode(spcio: &mut SpcIo, reg_map: &RegisterMap) -> std::io::Result<()> {
+fn interactive_mode(spcio: &mut SpcIo, reg_map: &RegisterMap, eval_ctx: &mut EvalContext) -> std::io::Result<()> {
     let editor_result = rustyline::DefaultEditor::new();
     if let Ok(mut editor) = editor_result {
+        // Variables and functions persist for the duration of the interactive session.
         loop {
             let readline_result = editor.readline(USER_PROMPT);
             if let Ok(str_input) = readline_result {
                 let input_expr = str_input.as_str();
                 let _ = editor.add_history_entry(input_expr);
-                evaluate_input(spcio, reg_map, input_expr, AppMode::Interactive)?;
+                evaluate_input(spcio, reg_map, eval_ctx, input_expr, AppMode::Interactive)?;
             } else {
                 let mut stderr = SpcIo { stream: StandardStream::stderr(spcio.color), color: spcio.color };
                 write_color(&mut stderr.stream, EXITING_APP, Color::Red, true)?;
@@ -257,9 +509,12 @@ fn main() -> std::io::Result<()> {
     let args: Vec<String> = env::args().collect();
     let reg_map = &spcregs::REGISTERMAP;
 
+    let mut eval_ctx = EvalContext::new();
+    load_startup_file(&mut stdout, &mut eval_ctx)?;
+
     if args.len() > 1 {
-        evaluate_input(&mut stdout, reg_map, args.get(1).unwrap(), AppMode::CommandLine)
+        evaluate_input(&mut stdout, reg_map, &mut eval_ctx, args.get(1).unwrap(), AppMode::CommandLine)
     } else {
-        interactive_mode(&mut stdout, reg_map)
+        interactive_mode(&mut stdout, reg_map, &mut eval_ctx)
     }
 }